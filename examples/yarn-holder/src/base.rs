@@ -1,17 +1,22 @@
 mod consts;
 
-use crate::consts::{
-    BASE_RADIUS, BASE_RISE, BASE_RISE_WIDTH, BASE_THICKNESS, EPS, FITMENT, POST_THICKNESS,
-    POST_WIDTH,
-};
+use crate::consts::{POST_THICKNESS, POST_WIDTH};
 use patina_geo::geo3::aabb3::Aabb3;
 use patina_geo::geo3::cylinder::Cylinder;
 use patina_mesh::ser::encode_file;
 use patina_sdf::marching_mesh::MarchingMesh;
+use patina_sdf::render::Render;
 use patina_sdf::sdf::AsSdf;
 use patina_vec::vec3::Vec3;
+use std::f64::consts::PI;
 use std::path::Path;
 
+const BASE_RADIUS: f64 = 120.0 / 2.0;
+const BASE_THICKNESS: f64 = 4.0;
+const BASE_RISE: f64 = 5.0;
+const BASE_RISE_WIDTH: f64 = 5.0;
+const FITMENT: f64 = 0.3;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let mut sdf = Cylinder::new(
@@ -43,18 +48,14 @@ async fn main() -> anyhow::Result<()> {
         )
         .as_sdf(),
     );
-    let mut mesh = MarchingMesh::new(Aabb3::new(
-        Vec3::new(-BASE_RADIUS - EPS, -BASE_RADIUS - EPS, -EPS),
-        Vec3::new(
-            BASE_RADIUS + EPS,
-            BASE_RADIUS + EPS,
-            BASE_THICKNESS + BASE_RISE + EPS,
-        ),
-    ));
+    let mut mesh = MarchingMesh::for_sdf(&sdf)?;
     mesh.min_render_depth(6)
         .max_render_depth(7)
-        .subdiv_max_dot(0.9);
-    let mesh = mesh.build(&sdf);
+        .subdiv_max_dot(0.9)
+        .deterministic(0);
+    let mesh = mesh.build(&sdf)?;
     encode_file(&mesh, Path::new("examples/yarn-holder/output/base.stl")).await?;
+    let preview = Render::for_sdf(&sdf, 640, 480, -PI / 3.0, PI / 6.0)?.render(&sdf);
+    encode_file(&preview, Path::new("examples/yarn-holder/output/base.png")).await?;
     Ok(())
 }
@@ -1,13 +1,2 @@
-pub const BASE_RADIUS: f64 = 120.0 / 2.0;
-pub const BASE_THICKNESS: f64 = 4.0;
-pub const BASE_RISE: f64 = 5.0;
-pub const BASE_RISE_WIDTH: f64 = 5.0;
-pub const EPS: f64 = 0.1;
-
 pub const POST_WIDTH: f64 = 15.0;
 pub const POST_THICKNESS: f64 = 7.0;
-pub const POST_LENGTH: f64 = 160.0;
-pub const FITMENT: f64 = 0.3;
-pub const CLIP_HOLE_RADIUS: f64 = 7.0 / 2.0;
-pub const CATCH_WIDTH: f64 = 4.0;
-pub const CATCH_LENGTH: f64 = 10.0;
@@ -1,17 +1,21 @@
 mod consts;
 
-use crate::consts::{
-    BASE_RADIUS, BASE_THICKNESS, CATCH_LENGTH, CATCH_WIDTH, CLIP_HOLE_RADIUS, EPS, POST_LENGTH,
-    POST_THICKNESS, POST_WIDTH,
-};
+use crate::consts::{POST_THICKNESS, POST_WIDTH};
 use patina_geo::geo3::aabb3::Aabb3;
 use patina_geo::geo3::cylinder::Cylinder;
 use patina_mesh::ser::encode_file;
 use patina_sdf::marching_mesh::MarchingMesh;
-use patina_sdf::sdf::{AsSdf, Sdf};
+use patina_sdf::render::Render;
+use patina_sdf::sdf::AsSdf;
 use patina_vec::vec3::Vec3;
+use std::f64::consts::PI;
 use std::path::Path;
 
+const POST_LENGTH: f64 = 160.0;
+const CLIP_HOLE_RADIUS: f64 = 7.0 / 2.0;
+const CATCH_WIDTH: f64 = 4.0;
+const CATCH_LENGTH: f64 = 10.0;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let post = Aabb3::new(
@@ -42,14 +46,14 @@ async fn main() -> anyhow::Result<()> {
         )
         .as_sdf(),
     );
-    let mut mesh = MarchingMesh::new(Aabb3::new(
-        post.min() - Vec3::new(CATCH_WIDTH + EPS, CATCH_LENGTH + EPS, EPS),
-        post.max() + Vec3::new(POST_WIDTH + EPS, CATCH_LENGTH + EPS, EPS),
-    ));
+    let mut mesh = MarchingMesh::for_sdf(&sdf)?;
     mesh.min_render_depth(6)
         .max_render_depth(8)
-        .subdiv_max_dot(0.99);
-    let mesh = mesh.build(&sdf);
+        .subdiv_max_dot(0.99)
+        .deterministic(0);
+    let mesh = mesh.build(&sdf)?;
     encode_file(&mesh, Path::new("examples/yarn-holder/output/post.stl")).await?;
+    let preview = Render::for_sdf(&sdf, 640, 480, -PI / 3.0, PI / 6.0)?.render(&sdf);
+    encode_file(&preview, Path::new("examples/yarn-holder/output/post.png")).await?;
     Ok(())
 }
@@ -22,6 +22,7 @@ use crate::settings_id::filament_settings_id::{
     FilamentBrand, FilamentMaterial, FilamentSettingsId,
 };
 use crate::settings_id::printer::Printer;
+use patina_progress::Progress;
 use serde::{Deserialize, Serialize};
 #[deny(unused_must_use)]
 use std::io::{Cursor, Write};
@@ -58,42 +59,85 @@ impl ModelContainer {
             .to_string(value)?)
     }
     pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        self.encode_with_progress(&Progress::silent())
+    }
+    /// Encodes the container, reporting each file written to `progress` and stopping with
+    /// [Cancelled](patina_progress::Cancelled) between files once it is cancelled.
+    pub fn encode_with_progress(&self, progress: &Progress) -> anyhow::Result<Vec<u8>> {
+        let files = [
+            self.content_types.is_some(),
+            self.relationships.is_some(),
+            true,
+            self.model_settings.is_some(),
+            self.project_settings.is_some(),
+            self.brim_points.is_some(),
+        ]
+        .into_iter()
+        .filter(|x| *x)
+        .count()
+            + self.filament_settings.len();
+        let stage = progress.stage("encoding 3mf", files as u64);
         let mut buffer = vec![];
         let mut zip = ZipWriter::new(Cursor::new(&mut buffer));
         let opts = SimpleFileOptions::default().last_modified_time(DateTime::default());
+        let write_file = |zip: &mut ZipWriter<_>, name: String, bytes: &[u8]| {
+            stage.check_cancelled()?;
+            zip.start_file(name, opts)?;
+            zip.write_all(bytes)?;
+            stage.inc(1);
+            anyhow::Ok(())
+        };
         if let Some(content_types) = &self.content_types {
-            zip.start_file("[Content_Types].xml", opts.clone())?;
-            zip.write_all(self.to_xml_string(content_types)?.as_bytes())?;
+            write_file(
+                &mut zip,
+                "[Content_Types].xml".to_string(),
+                self.to_xml_string(content_types)?.as_bytes(),
+            )?;
         }
         if let Some(relationships) = &self.relationships {
             zip.add_directory("_rels", opts.clone())?;
-            zip.start_file("_rels/.rels", opts.clone())?;
-            zip.write_all(self.to_xml_string(relationships)?.as_bytes())?;
+            write_file(
+                &mut zip,
+                "_rels/.rels".to_string(),
+                self.to_xml_string(relationships)?.as_bytes(),
+            )?;
         }
         zip.add_directory("3D/", opts.clone())?;
-        zip.start_file("3D/3dmodel.model", opts.clone())?;
-        zip.write_all(self.to_xml_string(&self.model)?.as_bytes())?;
+        write_file(
+            &mut zip,
+            "3D/3dmodel.model".to_string(),
+            self.to_xml_string(&self.model)?.as_bytes(),
+        )?;
         zip.add_directory("Metadata", opts.clone())?;
         if let Some(model_settings) = &self.model_settings {
-            zip.start_file("Metadata/model_settings.config", opts.clone())?;
-            zip.write_all(self.to_xml_string(model_settings)?.as_bytes())?;
+            write_file(
+                &mut zip,
+                "Metadata/model_settings.config".to_string(),
+                self.to_xml_string(model_settings)?.as_bytes(),
+            )?;
         }
         if let Some(project_settings) = &self.project_settings {
-            zip.start_file("Metadata/project_settings.config", opts.clone())?;
-            zip.write_all(serde_json::to_string_pretty(project_settings)?.as_bytes())?;
+            write_file(
+                &mut zip,
+                "Metadata/project_settings.config".to_string(),
+                serde_json::to_string_pretty(project_settings)?.as_bytes(),
+            )?;
         }
         for (index, filament) in self.filament_settings.iter().enumerate() {
-            zip.start_file(
+            write_file(
+                &mut zip,
                 format!("Metadata/filament_settings_{}.config", index + 1),
-                opts.clone(),
+                serde_json::to_string_pretty(filament)?.as_bytes(),
             )?;
-            zip.write_all(serde_json::to_string_pretty(filament)?.as_bytes())?;
         }
         if let Some(brim_points) = &self.brim_points {
-            zip.start_file("Metadata/brim_ear_points.txt", opts.clone())?;
             let mut brim_points_bytes = vec![];
             brim_points.serialize(&mut brim_points_bytes);
-            zip.write_all(&brim_points_bytes)?;
+            write_file(
+                &mut zip,
+                "Metadata/brim_ear_points.txt".to_string(),
+                &brim_points_bytes,
+            )?;
         }
         zip.finish()?;
         Ok(buffer)
@@ -33,11 +33,11 @@ impl SdfModel {
             metadata: vec![],
         }
     }
-    pub fn build(self, marching: MarchingMesh) -> MeshModel {
-        MeshModel {
-            mesh: marching.build(&self.sdf),
+    pub fn build(self, marching: MarchingMesh) -> anyhow::Result<MeshModel> {
+        Ok(MeshModel {
+            mesh: marching.build(&self.sdf)?,
             metadata: self.metadata,
-        }
+        })
     }
     pub fn add_metadata(&mut self, metadata: ModelModifier) {
         self.metadata.push(metadata);
@@ -90,6 +90,20 @@ impl BambuObject {
         }
         object
     }
+    /// An object with a part for each of the meshes made by
+    /// [MarchingMesh::build_materials], each printed with its own filament. The first mesh uses
+    /// filament 1.
+    pub fn from_materials(meshes: Vec<Mesh>) -> Self {
+        let mut object = BambuObject::new();
+        object.name(Some("main_object".to_string()));
+        for (index, mesh) in meshes.into_iter().enumerate() {
+            let mut part = BambuPart::new(mesh);
+            part.name(Some(format!("material_{}", index + 1)));
+            part.material(Some(index + 1));
+            object.add_part(part);
+        }
+        object
+    }
 }
 
 #[test]
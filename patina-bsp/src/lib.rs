@@ -46,8 +46,13 @@ pub struct BspBuilder<'a> {
 
 impl Bsp<1> {
     pub fn from_mesh(mesh: &Mesh) -> Self {
+        Self::from_mesh_with_rng(mesh, &mut rng())
+    }
+    /// Builds the tree choosing splitting planes with `rng`, so that a seeded generator gives
+    /// the same tree every time.
+    pub fn from_mesh_with_rng(mesh: &Mesh, rng: &mut dyn RngCore) -> Self {
         BspBuilder {
-            rng: &mut rng(),
+            rng,
             eps: 10e-10,
             vertices: mesh.vertices().to_vec(),
             max_unbalanced: 10,
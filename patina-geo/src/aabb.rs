@@ -50,6 +50,10 @@ impl<const N: usize> Aabb<N> {
             .into_iter()
             .all(|x| x >= 0.0)
     }
+    /// The point in the box closest to `p`.
+    pub fn nearest_point(&self, p: Vector<f64, N>) -> Vector<f64, N> {
+        p.maximum(self.min).minimum(self.max)
+    }
     pub fn surface_measure(&self) -> f64 {
         let d = self.dimensions().maximum(Vector::splat(0.0));
         if N == 0 {
@@ -57,6 +57,62 @@ impl Triangle3 {
             .cross(self.points()[2] - self.points()[0])
             .length()
     }
+    /// The point on the triangle closest to `p` (Ericson, Real-Time Collision Detection 5.1.5).
+    pub fn nearest_point(&self, p: Vec3) -> Vec3 {
+        let [a, b, c] = self.0;
+        let ab = b - a;
+        let ac = c - a;
+        let ap = p - a;
+        let d1 = ab.dot(ap);
+        let d2 = ac.dot(ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return a;
+        }
+        let bp = p - b;
+        let d3 = ab.dot(bp);
+        let d4 = ac.dot(bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return b;
+        }
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+        let cp = p - c;
+        let d5 = ab.dot(cp);
+        let d6 = ac.dot(cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return c;
+        }
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+        let denom = va + vb + vc;
+        if denom == 0.0 {
+            // Degenerate triangle: fall back to the nearest edge.
+            return self
+                .edges()
+                .map(|e| e.nearest_point(p))
+                .into_iter()
+                .min_by(|x, y| x.distance(p).total_cmp(&y.distance(p)))
+                .unwrap();
+        }
+        a + ab * (vb / denom) + ac * (vc / denom)
+    }
+    /// The signed solid angle subtended by the triangle as seen from `p`, positive when `p` is
+    /// behind the triangle (Van Oosterom and Strackee).
+    pub fn solid_angle(&self, p: Vec3) -> f64 {
+        let [a, b, c] = self.0.map(|x| x - p);
+        let (la, lb, lc) = (a.length(), b.length(), c.length());
+        let numerator = a.dot(b.cross(c));
+        let denominator = la * lb * lc + a.dot(b) * lc + b.dot(c) * la + c.dot(a) * lb;
+        2.0 * numerator.atan2(denominator)
+    }
 }
 
 impl Debug for Triangle3 {
@@ -37,6 +37,15 @@ impl<const N: usize> Segment<N> {
             v.distance(u * proj_fract)
         }
     }
+    pub fn nearest_point(&self, other: Vector<f64, N>) -> Vector<f64, N> {
+        let u = self.p2() - self.p1();
+        let length_squared = u.dot(u);
+        if length_squared == 0.0 {
+            return self.p1();
+        }
+        let proj_fract = (u.dot(other - self.p1()) / length_squared).clamp(0.0, 1.0);
+        self.p1() + u * proj_fract
+    }
     pub fn midpoint(&self) -> Vector<f64, N> {
         (self.p1() + self.p2()) / 2.0
     }
@@ -0,0 +1,175 @@
+use crate::bvh::{Bvh, BvhNodeView};
+use crate::mesh::Mesh;
+use patina_vec::vec3::Vec3;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct MeshNearest {
+    pub triangle: usize,
+    pub distance: f64,
+    pub pos: Vec3,
+}
+
+impl Bvh<3, Mesh, usize> {
+    pub fn mesh(&self) -> &Arc<Mesh> {
+        &self.mesh
+    }
+    /// The triangle closest to `point`, or `None` if the mesh has no triangles.
+    pub fn nearest_triangle(&self, point: Vec3) -> Option<MeshNearest> {
+        let mut best = None;
+        self.root_view().nearest_triangle(point, &mut best);
+        best
+    }
+}
+
+impl<'a> BvhNodeView<'a, 3, Mesh, usize> {
+    pub fn nearest_triangle(&self, point: Vec3, best: &mut Option<MeshNearest>) {
+        for leaf in self.leaves() {
+            let tri = leaf.mesh.triangles()[*leaf.leaf].for_vertices(leaf.mesh.vertices());
+            let pos = tri.nearest_point(point);
+            let distance = pos.distance(point);
+            if best.as_ref().is_none_or(|best| distance < best.distance) {
+                *best = Some(MeshNearest {
+                    triangle: *leaf.leaf,
+                    distance,
+                    pos,
+                });
+            }
+        }
+        let mut children: Vec<_> = self
+            .nodes()
+            .map(|node| (node.aabb().nearest_point(point).distance(point), node))
+            .collect();
+        children.sort_by(|x, y| x.0.total_cmp(&y.0));
+        for (distance, node) in children {
+            if best.as_ref().is_none_or(|best| distance < best.distance) {
+                node.nearest_triangle(point, best);
+            }
+        }
+    }
+}
+
+/// A first order approximation of the solid angle subtended by a cluster of triangles, used for
+/// points far away from the cluster.
+#[derive(Copy, Clone, Debug, Default)]
+struct Dipole {
+    area: Vec3,
+    center: Vec3,
+    radius: f64,
+}
+
+/// Computes the generalized winding number of a triangle mesh, following "Fast Winding Numbers
+/// for Soups and Clouds" (Barill et al. 2018). The winding number is close to 1 inside a closed
+/// mesh and close to 0 outside, and degrades gracefully when the mesh has small holes.
+pub struct WindingBvh {
+    bvh: Bvh<3, Mesh, usize>,
+    dipoles: Vec<Dipole>,
+}
+
+impl WindingBvh {
+    /// Clusters are approximated when the query point is further than this many cluster radii
+    /// away from the cluster center.
+    const ACCURACY: f64 = 2.0;
+
+    pub fn new(mesh: Arc<Mesh>) -> Self {
+        let bvh = Bvh::from_mesh(mesh);
+        let mut dipoles = vec![Dipole::default(); bvh.nodes.len()];
+        if !bvh.mesh.triangles().is_empty() {
+            Self::build_dipoles(&bvh.root_view(), &mut dipoles);
+        }
+        WindingBvh { bvh, dipoles }
+    }
+    fn build_dipoles(node: &BvhNodeView<3, Mesh, usize>, dipoles: &mut [Dipole]) {
+        let mut area = Vec3::zero();
+        let mut weighted = Vec3::zero();
+        let mut total = 0.0;
+        for leaf in node.leaves() {
+            let tri = leaf.mesh.triangles()[*leaf.leaf].for_vertices(leaf.mesh.vertices());
+            let tri_area = tri.area();
+            area += tri.area_vector() / 2.0;
+            weighted += tri.midpoint() * tri_area;
+            total += tri_area;
+        }
+        for child in node.nodes() {
+            Self::build_dipoles(&child, dipoles);
+            let dipole = dipoles[child.index()];
+            let child_area = dipole.area.length();
+            area += dipole.area;
+            weighted += dipole.center * child_area;
+            total += child_area;
+        }
+        let center = if total > 0.0 {
+            weighted / total
+        } else {
+            node.aabb().center()
+        };
+        let aabb = node.aabb();
+        let radius = aabb
+            .vertices()
+            .iter()
+            .map(|v| v.distance(center))
+            .fold(0.0, f64::max);
+        dipoles[node.index()] = Dipole {
+            area,
+            center,
+            radius,
+        };
+    }
+    pub fn bvh(&self) -> &Bvh<3, Mesh, usize> {
+        &self.bvh
+    }
+    pub fn winding_number(&self, point: Vec3) -> f64 {
+        if self.bvh.mesh.triangles().is_empty() {
+            return 0.0;
+        }
+        self.winding_node(&self.bvh.root_view(), point) / (4.0 * PI)
+    }
+    fn winding_node(&self, node: &BvhNodeView<3, Mesh, usize>, point: Vec3) -> f64 {
+        let dipole = &self.dipoles[node.index()];
+        let offset = dipole.center - point;
+        let distance = offset.length();
+        if distance > Self::ACCURACY * dipole.radius {
+            return offset.dot(dipole.area) / (distance * distance * distance);
+        }
+        let mut result = 0.0;
+        for leaf in node.leaves() {
+            let tri = leaf.mesh.triangles()[*leaf.leaf].for_vertices(leaf.mesh.vertices());
+            result += tri.solid_angle(point);
+        }
+        for child in node.nodes() {
+            result += self.winding_node(&child, point);
+        }
+        result
+    }
+}
+
+impl Debug for WindingBvh {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindingBvh")
+            .field("triangles", &self.bvh.mesh.triangles().len())
+            .finish()
+    }
+}
+
+#[test]
+fn test_mesh_bvh3() {
+    use patina_geo::aabb::Aabb;
+    let mesh = Arc::new(Mesh::from_aabb(Aabb::new(
+        Vec3::splat(-1.0),
+        Vec3::splat(1.0),
+    )));
+    let winding = WindingBvh::new(mesh);
+    let nearest = winding
+        .bvh()
+        .nearest_triangle(Vec3::new(3.0, 0.5, 0.0))
+        .unwrap();
+    assert!((nearest.distance - 2.0).abs() < 1e-12);
+    assert!((winding.winding_number(Vec3::zero()) - 1.0).abs() < 1e-9);
+    assert!((winding.winding_number(Vec3::new(0.9, -0.3, 0.2)) - 1.0).abs() < 1e-9);
+    assert!(winding.winding_number(Vec3::new(1.5, 0.0, 0.0)).abs() < 1e-9);
+    assert!(winding.winding_number(Vec3::new(40.0, 3.0, 0.0)).abs() < 1e-3);
+}
@@ -1,4 +1,5 @@
 mod edge_bvh2;
+pub mod mesh_bvh3;
 
 use crate::edge_mesh2::EdgeMesh2;
 use crate::mesh::Mesh;
@@ -143,6 +144,12 @@ impl<const N: usize, V: Clone> BvhBuilder<N, V> {
     }
 }
 
+impl<const N: usize, V: Clone> Default for BvhBuilder<N, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<const N: usize, V: Clone> BvhLeafBuilder<N, V> {
     pub fn new(leaf: V, midpoint: Vector<f64, N>, aabb: Aabb<N>) -> Self {
         BvhLeafBuilder {
@@ -2,7 +2,6 @@ use crate::half_edge_mesh::{HalfEdgeId, HalfEdgeMesh};
 use crate::mesh::Mesh;
 use crate::mesh_triangle::MeshTriangle;
 use crate::ser::encode_test_file;
-use indicatif::ProgressBar;
 use ordered_float::NotNan;
 use patina_geo::aabb::Aabb;
 use patina_vec::vec3::{Vec3, Vector3};
@@ -12,14 +11,14 @@ use rand::{Rng, SeedableRng, rng};
 use rand_xorshift::XorShiftRng;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
-use patina_progress::ProgressBuilder;
+use patina_progress::{Progress, Stage};
 
 pub struct Decimate<'mesh> {
     mesh: &'mesh mut HalfEdgeMesh,
     priorities: PriorityQueue<HalfEdgeId, Score>,
     max_degree: usize,
     min_score: f64,
-    progress_builder: ProgressBuilder,
+    progress: Progress,
 }
 
 #[derive(Ord, Eq, PartialEq, PartialOrd, Clone, Copy, Hash, Debug)]
@@ -35,7 +34,7 @@ impl<'mesh> Decimate<'mesh> {
             priorities: PriorityQueue::new(),
             max_degree: 30,
             min_score: 0.9999,
-            progress_builder: ProgressBuilder::new(),
+            progress: Progress::bars(),
         }
     }
     pub fn max_degree(&mut self, degree: usize) -> &mut Self {
@@ -46,6 +45,12 @@ impl<'mesh> Decimate<'mesh> {
         self.min_score = min_score;
         self
     }
+    /// Where decimation reports progress. Cancelling it stops decimation between contractions,
+    /// leaving a valid but partly decimated mesh.
+    pub fn progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
     fn priority(&self, id: HalfEdgeId) -> Score {
         let v1 = self.mesh[id].vertex();
         let v2 = self.mesh[self.mesh[id].twin()].vertex();
@@ -105,11 +110,18 @@ impl<'mesh> Decimate<'mesh> {
             id,
         }
     }
-    pub fn run_heap(&mut self) {
+    pub fn run_heap(&mut self) -> anyhow::Result<()> {
+        let stage = self
+            .progress
+            .stage("decimating", self.mesh.edge_count() as u64);
+        self.run_heap_with(&stage)
+    }
+    fn run_heap_with(&mut self, stage: &Stage) -> anyhow::Result<()> {
         for (id, e) in self.mesh.edges() {
             self.priorities.push(id, self.priority(id));
         }
         while let Some((id, score)) = self.priorities.pop() {
+            stage.check_cancelled()?;
             assert_eq!(score, self.priority(id));
             if score.score.into_inner() < self.min_score {
                 break;
@@ -134,10 +146,14 @@ impl<'mesh> Decimate<'mesh> {
             for &updated in &updated {
                 self.priorities.push(updated, self.priority(updated));
             }
+            stage.inc(1);
         }
+        Ok(())
     }
-    pub fn run_arbitrary(&mut self) {
-        let progress_bar = self.progress_builder.build(self.mesh.edge_count() as u64);
+    pub fn run_arbitrary(&mut self) -> anyhow::Result<()> {
+        let stage = self
+            .progress
+            .stage("decimating", self.mesh.edge_count() as u64);
         loop {
             let mut priorities: Vec<Score> =
                 self.mesh.edges().map(|(e, _)| self.priority(e)).collect();
@@ -150,10 +166,11 @@ impl<'mesh> Decimate<'mesh> {
             let mut progress = 0;
 
             for score in &priorities[0..last_index / 2] {
+                stage.check_cancelled()?;
                 if self.mesh.get(score.id).is_some()
                     && self.priority(score.id).score.into_inner() > self.min_score
                 {
-                    progress_bar.inc(1);
+                    stage.inc(1);
                     self.mesh.contract_edge(score.id);
                     progress += 1;
                 }
@@ -163,14 +180,14 @@ impl<'mesh> Decimate<'mesh> {
                 break;
             }
         }
-        self.run_heap();
-        progress_bar.finish();
+        self.run_heap_with(&stage)
     }
 }
 
 #[cfg(test)]
 #[tokio::test]
 async fn decimate_test() {
+    use patina_progress::{CancellationToken, Cancelled};
     let mut vertices = vec![];
     let mut triangles = vec![];
     let mut vertex_table = HashMap::new();
@@ -256,8 +273,20 @@ async fn decimate_test() {
     mesh.check_manifold().unwrap();
     encode_test_file(&mesh, "input.stl").await.unwrap();
     let mut hem = HalfEdgeMesh::new(&mesh);
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    let mut progress = Progress::silent();
+    progress.cancellation(cancellation);
+    {
+        let mut decimate = Decimate::new(&mut hem);
+        decimate.progress(progress);
+        assert!(decimate.run_heap().unwrap_err().is::<Cancelled>());
+    }
+    hem.check_manifold().unwrap();
+    assert_eq!(hem.as_mesh().triangles().len(), mesh.triangles().len());
     let mut decimate = Decimate::new(&mut hem);
-    decimate.run_heap();
+    decimate.progress(Progress::silent());
+    decimate.run_heap().unwrap();
     hem.check_manifold().unwrap();
     let mesh = hem.as_mesh();
     encode_test_file(&mesh, "output.stl").await.unwrap();
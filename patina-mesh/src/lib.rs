@@ -21,7 +21,7 @@ mod monotone_decomp;
 pub mod tri_mesh2;
 pub mod bimesh2;
 pub mod mesh_cut;
-mod bvh;
+pub mod bvh;
 mod util;
 pub mod half_edge_mesh;
 pub mod decimate;
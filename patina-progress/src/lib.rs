@@ -1,30 +1,369 @@
-use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressStyle};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-pub struct ProgressBuilder {
+/// What happened to a [Stage] when it is reported.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ProgressEvent {
+    Start,
+    Update,
+    Finish,
+}
+
+/// A snapshot of a [Stage], as passed to a [ProgressReporter].
+#[derive(Debug, Clone)]
+pub struct StageStatus<'a> {
+    stage: &'a [String],
+    position: u64,
+    len: u64,
+    elapsed: Duration,
+}
+
+/// Receives progress from long-running operations. Updates are throttled by [Stage], so a
+/// reporter sees at most about a thousand per stage.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: ProgressEvent, status: &StageStatus);
+}
+
+/// Reports nothing.
+pub struct SilentReporter;
+
+/// Reports through the `log` crate at every tenth of each stage, which suits CI logs.
+pub struct LogReporter {
+    deciles: Mutex<HashMap<Vec<String>, u64>>,
+}
+
+/// Passes every report to a callback, for example to drive a GUI.
+pub struct CallbackReporter<F> {
+    callback: F,
+}
+
+/// Draws a terminal progress bar for each stage, indented by nesting.
+pub struct BarReporter {
+    multi: MultiProgress,
     style: ProgressStyle,
-    finish: ProgressFinish,
+    bars: Mutex<HashMap<Vec<String>, ProgressBar>>,
 }
 
-impl ProgressBuilder {
+/// Shared between the caller and an operation, which stops at the next check once it is
+/// cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+/// The error returned by an operation that stopped because its [CancellationToken] was
+/// cancelled. Operations returning `anyhow::Error` can be checked with `error.is::<Cancelled>()`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cancelled;
+
+/// Where an operation reports its progress, and how it learns that it should stop.
+#[derive(Clone)]
+pub struct Progress {
+    reporter: Arc<dyn ProgressReporter>,
+    cancellation: CancellationToken,
+}
+
+/// A named step of an operation with `len` units of work, which may contain nested stages. It
+/// is reported as finished when dropped.
+pub struct Stage {
+    progress: Progress,
+    path: Vec<String>,
+    len: u64,
+    position: AtomicU64,
+    start: Instant,
+}
+
+impl<'a> StageStatus<'a> {
+    /// The names of the enclosing stages, outermost first, ending with this one.
+    pub fn stage(&self) -> &'a [String] {
+        self.stage
+    }
+    pub fn name(&self) -> &'a str {
+        self.stage.last().unwrap()
+    }
+    /// The number of enclosing stages.
+    pub fn depth(&self) -> usize {
+        self.stage.len() - 1
+    }
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+    pub fn fraction(&self) -> f64 {
+        if self.len == 0 {
+            1.0
+        } else {
+            (self.position as f64 / self.len as f64).min(1.0)
+        }
+    }
+    /// The time remaining, extrapolated from the rate so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.position == 0 {
+            return None;
+        }
+        let remaining = self.len.saturating_sub(self.position);
+        Some(
+            self.elapsed
+                .mul_f64(remaining as f64 / self.position as f64),
+        )
+    }
+}
+
+impl ProgressReporter for SilentReporter {
+    fn report(&self, _: ProgressEvent, _: &StageStatus) {}
+}
+
+impl LogReporter {
+    pub fn new() -> Self {
+        LogReporter {
+            deciles: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for LogReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for LogReporter {
+    fn report(&self, event: ProgressEvent, status: &StageStatus) {
+        let name = status.stage().join(" / ");
+        let mut deciles = self.deciles.lock().unwrap();
+        match event {
+            ProgressEvent::Start => {
+                deciles.insert(status.stage().to_vec(), 0);
+                log::info!("{name}: started");
+            }
+            ProgressEvent::Update => {
+                let decile = (status.fraction() * 10.0) as u64;
+                let previous = deciles.entry(status.stage().to_vec()).or_default();
+                if decile > *previous && decile < 10 {
+                    *previous = decile;
+                    match status.eta() {
+                        Some(eta) => log::info!("{name}: {}0%, eta {eta:.0?}", decile),
+                        None => log::info!("{name}: {}0%", decile),
+                    }
+                }
+            }
+            ProgressEvent::Finish => {
+                deciles.remove(status.stage());
+                log::info!("{name}: finished in {:.1?}", status.elapsed());
+            }
+        }
+    }
+}
+
+impl<F: Fn(ProgressEvent, &StageStatus) + Send + Sync> CallbackReporter<F> {
+    pub fn new(callback: F) -> Self {
+        CallbackReporter { callback }
+    }
+}
+
+impl<F: Fn(ProgressEvent, &StageStatus) + Send + Sync> ProgressReporter for CallbackReporter<F> {
+    fn report(&self, event: ProgressEvent, status: &StageStatus) {
+        (self.callback)(event, status)
+    }
+}
+
+impl BarReporter {
     pub fn new() -> Self {
-        ProgressBuilder {
+        BarReporter {
+            multi: MultiProgress::new(),
             style: ProgressStyle::with_template(
-                "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+                "[{elapsed_precise}] {prefix}{bar:40.cyan/blue} {pos:>7}/{len:7} (eta {eta}) {msg}",
             )
             .unwrap()
             .progress_chars("##-"),
-            finish: ProgressFinish::Abandon,
+            bars: Mutex::new(HashMap::new()),
         }
     }
-    pub fn build(&self, len: u64) -> ProgressBar {
-        ProgressBar::new(len)
-            .with_style(self.style.clone())
-            .with_finish(self.finish.clone())
+}
+
+impl Default for BarReporter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+impl ProgressReporter for BarReporter {
+    fn report(&self, event: ProgressEvent, status: &StageStatus) {
+        let mut bars = self.bars.lock().unwrap();
+        match event {
+            ProgressEvent::Start => {
+                let bar = self.multi.add(
+                    ProgressBar::new(status.len())
+                        .with_style(self.style.clone())
+                        .with_finish(ProgressFinish::Abandon)
+                        .with_prefix("  ".repeat(status.depth()))
+                        .with_message(status.name().to_string()),
+                );
+                bars.insert(status.stage().to_vec(), bar);
+            }
+            ProgressEvent::Update => {
+                if let Some(bar) = bars.get(status.stage()) {
+                    bar.set_position(status.position());
+                }
+            }
+            ProgressEvent::Finish => {
+                if let Some(bar) = bars.remove(status.stage()) {
+                    bar.set_position(status.position());
+                    bar.finish();
+                }
+            }
+        }
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the operation was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+impl Progress {
+    pub fn new(reporter: impl ProgressReporter + 'static) -> Self {
+        Progress {
+            reporter: Arc::new(reporter),
+            cancellation: CancellationToken::new(),
+        }
+    }
+    /// Draws progress bars on the terminal.
+    pub fn bars() -> Self {
+        Self::new(BarReporter::new())
+    }
+    pub fn silent() -> Self {
+        Self::new(SilentReporter)
+    }
+    pub fn log() -> Self {
+        Self::new(LogReporter::new())
+    }
+    pub fn callback(
+        callback: impl Fn(ProgressEvent, &StageStatus) + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(CallbackReporter::new(callback))
+    }
+    /// Stops operations reporting here once `cancellation` is cancelled.
+    pub fn cancellation(&mut self, cancellation: CancellationToken) -> &mut Self {
+        self.cancellation = cancellation;
+        self
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+    pub fn check_cancelled(&self) -> Result<(), Cancelled> {
+        self.cancellation.check()
+    }
+    /// Starts a top-level stage.
+    pub fn stage(&self, name: impl Into<String>, len: u64) -> Stage {
+        Stage::new(self.clone(), vec![name.into()], len)
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::bars()
+    }
+}
+
+impl Stage {
+    fn new(progress: Progress, path: Vec<String>, len: u64) -> Self {
+        let stage = Stage {
+            progress,
+            path,
+            len,
+            position: AtomicU64::new(0),
+            start: Instant::now(),
+        };
+        stage.report(ProgressEvent::Start, 0);
+        stage
+    }
+    fn report(&self, event: ProgressEvent, position: u64) {
+        self.progress.reporter.report(
+            event,
+            &StageStatus {
+                stage: &self.path,
+                position,
+                len: self.len,
+                elapsed: self.start.elapsed(),
+            },
+        );
+    }
+    /// Starts a stage nested inside this one.
+    pub fn stage(&self, name: impl Into<String>, len: u64) -> Stage {
+        let mut path = self.path.clone();
+        path.push(name.into());
+        Stage::new(self.progress.clone(), path, len)
+    }
+    /// Records `delta` more units of work, reporting when the completed thousandth changes.
+    pub fn inc(&self, delta: u64) {
+        let old = self.position.fetch_add(delta, Ordering::Relaxed);
+        let new = old + delta;
+        let thousandth = |position: u64| {
+            (position as u128 * 1000)
+                .checked_div(self.len as u128)
+                .unwrap_or(0)
+        };
+        if thousandth(old) != thousandth(new) {
+            self.report(ProgressEvent::Update, new);
+        }
+    }
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.is_cancelled()
+    }
+    pub fn check_cancelled(&self) -> Result<(), Cancelled> {
+        self.progress.check_cancelled()
+    }
+    pub fn finish(self) {}
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        self.report(ProgressEvent::Finish, self.position());
+    }
+}
+
+/// A share of the work of a [Stage], which is added to it when dropped. Recursive operations
+/// [divide](Self::divide) their share among their children, so the stage advances smoothly even
+/// when the shape of the recursion is not known in advance.
 pub struct ProgressGuard<'a> {
-    progress_bar: &'a ProgressBar,
+    stage: &'a Stage,
     increment: u64,
 }
 
@@ -35,21 +374,18 @@ pub struct ProgressGuardIter<'a> {
 }
 
 impl<'a> ProgressGuard<'a> {
-    pub fn new(progress_bar: &'a ProgressBar, increment: u64) -> Self {
-        ProgressGuard {
-            progress_bar,
-            increment,
-        }
+    pub fn new(stage: &'a Stage, increment: u64) -> Self {
+        ProgressGuard { stage, increment }
     }
     pub fn divide(self, count: u64) -> impl Iterator<Item = Self> {
         ProgressGuardIter {
-            increment: (self.increment + count - 1) / count,
+            increment: self.increment.div_ceil(count),
             guard: self,
             count,
         }
     }
     pub fn take(&mut self) -> Self {
-        let result = ProgressGuard::new(self.progress_bar, self.increment);
+        let result = ProgressGuard::new(self.stage, self.increment);
         self.increment = 0;
         result
     }
@@ -63,7 +399,7 @@ impl<'a> Iterator for ProgressGuardIter<'a> {
         let increment = self.guard.increment.min(self.increment);
         self.guard.increment -= increment;
         Some(ProgressGuard {
-            progress_bar: self.guard.progress_bar,
+            stage: self.guard.stage,
             increment,
         })
     }
@@ -71,6 +407,66 @@ impl<'a> Iterator for ProgressGuardIter<'a> {
 
 impl<'a> Drop for ProgressGuard<'a> {
     fn drop(&mut self) {
-        self.progress_bar.inc(self.increment);
+        if self.increment > 0 {
+            self.stage.inc(self.increment);
+        }
+    }
+}
+
+#[test]
+fn test_progress() {
+    let events = Arc::new(Mutex::new(vec![]));
+    let mut progress = Progress::callback({
+        let events = events.clone();
+        move |event, status| {
+            events.lock().unwrap().push((
+                event,
+                status.stage().join("/"),
+                status.position(),
+                status.eta().is_some(),
+            ))
+        }
+    });
+    let cancellation = CancellationToken::new();
+    progress.cancellation(cancellation.clone());
+    {
+        let outer = progress.stage("outer", 2);
+        {
+            let inner = outer.stage("inner", 4000);
+            let guard = ProgressGuard::new(&inner, 4000);
+            drop(guard.divide(8).collect::<Vec<_>>());
+        }
+        outer.inc(1);
+        assert!(outer.check_cancelled().is_ok());
+        cancellation.cancel();
+        assert_eq!(outer.check_cancelled(), Err(Cancelled));
     }
+    let events = events.lock().unwrap();
+    assert_eq!(
+        events[0],
+        (ProgressEvent::Start, "outer".to_string(), 0, false)
+    );
+    assert_eq!(
+        events[1],
+        (ProgressEvent::Start, "outer/inner".to_string(), 0, false)
+    );
+    // Each of the eight shares moves the inner stage on by an eighth.
+    let inner_updates = events
+        .iter()
+        .filter(|(event, stage, _, _)| *event == ProgressEvent::Update && stage == "outer/inner")
+        .count();
+    assert_eq!(inner_updates, 8);
+    assert_eq!(
+        events[10],
+        (ProgressEvent::Finish, "outer/inner".to_string(), 4000, true)
+    );
+    assert_eq!(
+        events[11],
+        (ProgressEvent::Update, "outer".to_string(), 1, true)
+    );
+    assert_eq!(
+        events[12],
+        (ProgressEvent::Finish, "outer".to_string(), 1, true)
+    );
+    assert!(progress.is_cancelled());
 }
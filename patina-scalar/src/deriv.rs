@@ -277,4 +277,39 @@ impl<const N: usize> Scalar for Deriv<N> {
             }
         }
     }
+
+    fn sin(self) -> Self {
+        let cos = self.value.cos();
+        Deriv {
+            value: self.value.sin(),
+            deriv: (0..N)
+                .map(|axis| self.deriv[axis] * cos)
+                .collect_array()
+                .unwrap(),
+        }
+    }
+
+    fn cos(self) -> Self {
+        let sin = self.value.sin();
+        Deriv {
+            value: self.value.cos(),
+            deriv: (0..N)
+                .map(|axis| -self.deriv[axis] * sin)
+                .collect_array()
+                .unwrap(),
+        }
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        let length_squared = self.value * self.value + x.value * x.value;
+        Deriv {
+            value: self.value.atan2(x.value),
+            deriv: (0..N)
+                .map(|axis| {
+                    (x.value * self.deriv[axis] - self.value * x.deriv[axis]) / length_squared
+                })
+                .collect_array()
+                .unwrap(),
+        }
+    }
 }
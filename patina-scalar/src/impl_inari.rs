@@ -17,7 +17,7 @@ impl Scalar for DecInterval {
     fn piecewise(self, neg: Self, pos: Self) -> Self {
         if self.precedes(Self::from_f64(0.0)) {
             neg
-        } else if Self::from_f64(1.0).precedes(Self::from_f64(0.0)) {
+        } else if Self::from_f64(0.0).precedes(self) {
             pos
         } else {
             neg.convex_hull(pos)
@@ -35,4 +35,16 @@ impl Scalar for DecInterval {
     fn abs(self) -> Self {
         DecInterval::abs(self)
     }
+
+    fn sin(self) -> Self {
+        DecInterval::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        DecInterval::cos(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        DecInterval::atan2(self, x)
+    }
 }
@@ -35,6 +35,10 @@ pub trait Scalar:
     fn from_f64(value: f64) -> Self;
     fn sqrt(self) -> Self;
     fn abs(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    /// The angle of the point `(x, self)`, like [f64::atan2].
+    fn atan2(self, x: Self) -> Self;
     fn sign(self) -> Self {
         self.piecewise(Self::from_f64(-1.0), Self::from_f64(1.0))
     }
@@ -68,4 +72,16 @@ impl Scalar for f64 {
     fn abs(self) -> Self {
         f64::abs(self)
     }
+
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        f64::atan2(self, x)
+    }
 }
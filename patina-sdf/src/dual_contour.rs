@@ -0,0 +1,557 @@
+use crate::marching_mesh::MarchingMesh;
+use crate::octree::{OctreeIndex, OctreePath};
+use crate::sdf::Sdf3;
+use crate::sdf::bounds::{is_empty, padded_bounds};
+use anyhow::{Context, bail, ensure};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_mesh::mesh::Mesh;
+use patina_mesh::mesh_triangle::MeshTriangle;
+use patina_scalar::deriv::Deriv;
+use patina_scalar::newton::Newton;
+use patina_vec::mat3::Mat3;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::Vec3;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Directions in which the normals vary less than this, relative to the direction in which they
+/// vary most, are treated as flat. Vertices stay at the average of their edge crossings along
+/// those directions, so nearly flat surfaces do not produce spikes.
+const TRUNCATION: f64 = 0.02;
+
+/// The corners of a square face, counterclockwise in its two axes.
+const FACE_CORNERS: [[usize; 2]; 4] = [[0, 0], [1, 0], [1, 1], [0, 1]];
+
+/// A point of the grid, in units of the smallest leaf size.
+type GridPoint = [usize; 3];
+/// The grid edge from a point along an axis.
+type GridEdge = (GridPoint, usize);
+
+/// A leaf of the octree, with its simplified [Sdf3] if the surface may pass through it.
+struct Leaf {
+    min: GridPoint,
+    size: usize,
+    sdf: Option<Sdf3>,
+}
+
+/// The leaves of the octree, sorted by position.
+struct LeafTable {
+    leaves: Vec<Leaf>,
+    lookup: HashMap<(GridPoint, usize), usize>,
+    depth: usize,
+}
+
+impl LeafTable {
+    fn new(mut leaves: Vec<Leaf>, depth: usize) -> Self {
+        leaves.sort_by_key(|leaf| (leaf.min, leaf.size));
+        let lookup = leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| ((leaf.min, leaf.size), index))
+            .collect();
+        LeafTable {
+            leaves,
+            lookup,
+            depth,
+        }
+    }
+    /// The leaf containing the smallest-size cell at `cell`.
+    fn find(&self, cell: GridPoint) -> Option<usize> {
+        (0..=self.depth).find_map(|depth| {
+            let size = 1 << depth;
+            self.lookup
+                .get(&(cell.map(|x| x / size * size), size))
+                .copied()
+        })
+    }
+}
+
+/// A grid edge crossed by the surface that is an edge of the smallest leaves around it, along
+/// with those leaves counterclockwise around its axis.
+struct CrossedEdge {
+    edge: GridEdge,
+    size: usize,
+    leaves: [usize; 4],
+}
+
+/// Meshes an [Sdf3] by dual contouring. Unlike [MarchingMesh], vertices are placed inside the
+/// cells of the octree rather than on their edges, at the point that best fits the planes given
+/// by the surface normals where the surface crosses the edges. This puts vertices on sharp edges
+/// and corners instead of cutting them off.
+///
+/// The octree is refined where [MarchingMesh] would refine it. Each leaf gets one vertex for
+/// every separate piece of surface passing through it, so the mesh is manifold as long as no two
+/// pieces of surface in neighboring cells meet along more than one of their shared edges.
+pub struct DualContour {
+    aabb: Aabb3,
+    min_render_depth: usize,
+    max_render_depth: usize,
+    subdiv_max_dot: f64,
+}
+
+impl DualContour {
+    pub fn new(aabb: &Aabb3) -> Self {
+        DualContour {
+            aabb: *aabb,
+            min_render_depth: 4,
+            max_render_depth: 6,
+            subdiv_max_dot: 0.9,
+        }
+    }
+    /// Meshes all of `sdf`, within its [bounds](crate::sdf::Sdf::bounds) padded so that the
+    /// surface stays clear of the edges of the grid.
+    pub fn for_sdf(sdf: &Sdf3) -> anyhow::Result<Self> {
+        Ok(Self::new(&padded_bounds(sdf, "mesh")?))
+    }
+    /// See [MarchingMesh::min_render_depth].
+    pub fn min_render_depth(&mut self, min_render_depth: usize) -> &mut Self {
+        self.min_render_depth = min_render_depth;
+        self
+    }
+    /// See [MarchingMesh::max_render_depth].
+    pub fn max_render_depth(&mut self, max_render_depth: usize) -> &mut Self {
+        self.max_render_depth = max_render_depth;
+        self
+    }
+    /// See [MarchingMesh::subdiv_max_dot].
+    pub fn subdiv_max_dot(&mut self, subdiv_max_dot: f64) -> &mut Self {
+        self.subdiv_max_dot = subdiv_max_dot;
+        self
+    }
+    fn position(&self, point: GridPoint) -> Vec3 {
+        let fraction = Vec3::from(point.map(|x| x as f64)) / (1 << self.max_render_depth) as f64;
+        self.aabb.min() + fraction.mul_elements(self.aabb.dimensions())
+    }
+    /// The leaves under `path`, split down to the minimum depth and below it wherever
+    /// [MarchingMesh] would split them.
+    fn find_leaves(&self, marching: &MarchingMesh, sdf: &Sdf3, path: OctreePath) -> Vec<Leaf> {
+        let aabb = path.aabb_inside(&self.aabb);
+        let size = 1 << (self.max_render_depth - path.depth());
+        let position: GridPoint = path.position().into();
+        let min = position.map(|x| x * size);
+        let p = Vector::from_fn(|axis| {
+            DecInterval::try_from((aabb.min()[axis], aabb.max()[axis])).unwrap()
+        });
+        let (simplified, range) = sdf.evaluate_constrain(p);
+        if !range.is_empty() && (range.inf() > 0.0 || range.sup() < 0.0) {
+            return vec![Leaf {
+                min,
+                size,
+                sdf: None,
+            }];
+        }
+        let sdf = simplified.as_ref().unwrap_or(sdf);
+        let subdivide = path.depth() < self.min_render_depth
+            || path.depth() < self.max_render_depth && marching.should_subdivide(&path, &aabb, sdf);
+        if !subdivide {
+            return vec![Leaf {
+                min,
+                size,
+                sdf: Some(sdf.clone()),
+            }];
+        }
+        (0..8)
+            .into_par_iter()
+            .flat_map_iter(|child| {
+                let index = OctreeIndex::from([child & 1 != 0, child & 2 != 0, child & 4 != 0]);
+                self.find_leaves(marching, sdf, path.push_back(index))
+            })
+            .collect()
+    }
+    /// Where the surface crosses the edge of length `size` from `start` along `axis`, and the
+    /// normal there.
+    fn find_crossing(&self, sdf: &Sdf3, (start, axis): GridEdge, size: usize) -> (Vec3, Vec3) {
+        let mut end = start;
+        end[axis] += size;
+        let start = self.position(start);
+        let range = self.position(end) - start;
+        let t = Newton::new().solve(0.0..1.0, |t| {
+            sdf.evaluate_deriv1(
+                start.map(Deriv::constant) + range.map(Deriv::constant) * Deriv::variable(t, 0),
+            )
+        });
+        let t = match t {
+            Some(t) => t.into_inner(),
+            None => {
+                let (d1, d2) = (sdf.evaluate(start), sdf.evaluate(start + range));
+                d1 / (d1 - d2)
+            }
+        };
+        let position = start + range * t.clamp(0.0, 1.0);
+        let mut normal = sdf.normal(position);
+        if !normal.length().is_finite() {
+            normal = Vec3::zero();
+        }
+        (position, normal)
+    }
+    /// The crossed edges that are edges of the smallest leaves around them. Edges of larger
+    /// leaves that are split by smaller ones, even ones the surface does not pass through, are
+    /// left to the smaller ones.
+    fn crossed_edges(
+        &self,
+        table: &LeafTable,
+        inside: &impl Fn(GridPoint) -> bool,
+    ) -> anyhow::Result<Vec<CrossedEdge>> {
+        let leaves = &table.leaves;
+        let mut crossed = vec![];
+        for leaf in leaves.iter().filter(|leaf| leaf.sdf.is_some()) {
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                for [du, dv] in FACE_CORNERS {
+                    let mut start = leaf.min;
+                    start[u] += du * leaf.size;
+                    start[v] += dv * leaf.size;
+                    let mut end = start;
+                    end[axis] += leaf.size;
+                    if inside(start) == inside(end) {
+                        continue;
+                    }
+                    let around = FACE_CORNERS.map(|[du, dv]| {
+                        let mut cell = start;
+                        cell[u] = cell[u].checked_sub(1 - du)?;
+                        cell[v] = cell[v].checked_sub(1 - dv)?;
+                        table.find(cell)
+                    });
+                    let Some(around) = around.into_iter().collect::<Option<Vec<_>>>() else {
+                        bail!(
+                            "the surface reaches the edge of the grid near {}",
+                            self.position(start)
+                        );
+                    };
+                    if around.iter().any(|&other| leaves[other].size < leaf.size) {
+                        continue;
+                    }
+                    ensure!(
+                        around.iter().all(|&other| leaves[other].sdf.is_some()),
+                        "the surface crosses an edge of a leaf it was shown to miss near {}",
+                        self.position(start)
+                    );
+                    crossed.push(CrossedEdge {
+                        edge: (start, axis),
+                        size: leaf.size,
+                        leaves: around.try_into().unwrap(),
+                    });
+                }
+            }
+        }
+        crossed.sort_by_key(|crossed| crossed.edge);
+        crossed.dedup_by_key(|crossed| crossed.edge);
+        Ok(crossed)
+    }
+    /// The squares tiling the face of `leaf` on `side` of it along `normal`, each the face of
+    /// the smaller of `leaf` and the leaf across it.
+    fn face_tiles(
+        &self,
+        leaf: &Leaf,
+        normal: usize,
+        side: usize,
+        table: &LeafTable,
+    ) -> Vec<(GridPoint, usize)> {
+        let (u, v) = ((normal + 1) % 3, (normal + 2) % 3);
+        let mut corner = leaf.min;
+        corner[normal] += side * leaf.size;
+        let mut tiles = vec![];
+        let mut stack = vec![(corner, leaf.size)];
+        while let Some((corner, size)) = stack.pop() {
+            let mut across = corner;
+            if side == 0 {
+                across[normal] = across[normal].wrapping_sub(1);
+            }
+            let across_size = table
+                .find(across)
+                .map_or(size, |other| table.leaves[other].size);
+            if across_size >= size {
+                tiles.push((corner, size));
+                continue;
+            }
+            for [du, dv] in FACE_CORNERS {
+                let mut sub = corner;
+                sub[u] += du * size / 2;
+                sub[v] += dv * size / 2;
+                stack.push((sub, size / 2));
+            }
+        }
+        tiles
+    }
+    /// Groups the crossed edges around `leaf` into the separate pieces of surface through it.
+    /// Two crossed edges are joined when the surface runs between them across one of the tiles
+    /// of its faces. Where a tile has more than two crossings, the sign at its center decides
+    /// which are joined, so that both leaves sharing the tile agree.
+    fn components(
+        &self,
+        leaf: &Leaf,
+        sdf: &Sdf3,
+        table: &LeafTable,
+        crossed: &[CrossedEdge],
+        crossing_table: &HashMap<GridEdge, usize>,
+        inside: &impl Fn(GridPoint) -> bool,
+    ) -> Vec<Vec<usize>> {
+        let mut links: HashMap<usize, Vec<usize>> = HashMap::new();
+        for normal in 0..3 {
+            let (u, v) = ((normal + 1) % 3, (normal + 2) % 3);
+            for side in 0..2 {
+                for (corner, size) in self.face_tiles(leaf, normal, side, table) {
+                    let corners = FACE_CORNERS.map(|[du, dv]| {
+                        let mut point = corner;
+                        point[u] += du * size;
+                        point[v] += dv * size;
+                        point
+                    });
+                    // The crossings counterclockwise around the tile, each with whether the
+                    // boundary of the tile runs inside the solid after it.
+                    let mut around = vec![];
+                    for k in 0..4 {
+                        let (a, b) = (corners[k], corners[(k + 1) % 4]);
+                        let axis = if k.is_multiple_of(2) { u } else { v };
+                        let forward = a[axis] < b[axis];
+                        let start = if forward { a } else { b };
+                        let mut on_side = (0..size)
+                            .filter_map(|offset| {
+                                let mut point = start;
+                                point[axis] += offset;
+                                crossing_table.get(&(point, axis)).copied()
+                            })
+                            .collect::<Vec<_>>();
+                        if !forward {
+                            on_side.reverse();
+                        }
+                        around.extend(
+                            on_side
+                                .into_iter()
+                                .map(|index| (index, inside(crossed[index].edge.0) != forward)),
+                        );
+                    }
+                    let mut pairs = vec![];
+                    if around.len() == 2 {
+                        pairs.push((around[0].0, around[1].0));
+                    } else if around.len() > 2 {
+                        let center = (self.position(corners[0]) + self.position(corners[2])) / 2.0;
+                        let center_inside = sdf.evaluate(center) < 0.0;
+                        for (k, &(index, inside_after)) in around.iter().enumerate() {
+                            if inside_after != center_inside {
+                                pairs.push((index, around[(k + 1) % around.len()].0));
+                            }
+                        }
+                    }
+                    for (a, b) in pairs {
+                        links.entry(a).or_default().push(b);
+                        links.entry(b).or_default().push(a);
+                    }
+                }
+            }
+        }
+        let mut visited = HashSet::new();
+        let mut components = vec![];
+        let mut starts = links.keys().copied().collect::<Vec<_>>();
+        starts.sort();
+        for start in starts {
+            if !visited.insert(start) {
+                continue;
+            }
+            let mut component = vec![];
+            let mut stack = vec![start];
+            while let Some(edge) = stack.pop() {
+                component.push(edge);
+                for &next in &links[&edge] {
+                    if visited.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+    /// The point in the leaf of `size` at `min` that best fits the tangent planes at
+    /// `crossings`.
+    fn solve_vertex(&self, min: GridPoint, size: usize, crossings: &[(Vec3, Vec3)]) -> Vec3 {
+        let mass_point = crossings.iter().map(|(p, _)| *p).sum::<Vec3>() / crossings.len() as f64;
+        let mut ata = Mat3::from_fn(|_, _| 0.0);
+        let mut atb = Vec3::zero();
+        for (p, n) in crossings {
+            ata = ata + Mat3::from_fn(|i, j| n[i] * n[j]);
+            atb += *n * n.dot(*p - mass_point);
+        }
+        let eigen = ata.symmetric_eigen();
+        let largest = eigen[0].0.max(0.0);
+        let mut vertex = mass_point;
+        for (value, vector) in eigen {
+            if value > TRUNCATION * largest {
+                vertex += vector * (vector.dot(atb) / value);
+            }
+        }
+        let min_position = self.position(min);
+        let max_position = self.position(min.map(|x| x + size));
+        Vec3::from_fn(|axis| vertex[axis].clamp(min_position[axis], max_position[axis]))
+    }
+    /// Meshes the surface of `sdf`. Fails if the surface reaches the edge of the grid, where the
+    /// mesh would not be closed.
+    pub fn build(&self, sdf: &Sdf3) -> anyhow::Result<Mesh> {
+        let mut marching = MarchingMesh::new(&self.aabb);
+        marching.subdiv_max_dot(self.subdiv_max_dot);
+        let table = LeafTable::new(
+            self.find_leaves(&marching, sdf, OctreePath::new_root()),
+            self.max_render_depth,
+        );
+        let leaves = &table.leaves;
+
+        let mut corners = leaves
+            .iter()
+            .filter(|leaf| leaf.sdf.is_some())
+            .flat_map(|leaf| {
+                (0..8).map(move |corner| {
+                    let mut point = leaf.min;
+                    for (axis, x) in point.iter_mut().enumerate() {
+                        *x += ((corner >> axis) & 1) * leaf.size;
+                    }
+                    point
+                })
+            })
+            .collect::<Vec<_>>();
+        corners.sort();
+        corners.dedup();
+        let positions = corners
+            .iter()
+            .map(|&corner| self.position(corner))
+            .collect::<Vec<_>>();
+        let mut values = vec![0.0; corners.len()];
+        sdf.evaluate_batch(&positions, &mut values);
+        let values: HashMap<GridPoint, f64> = corners.into_iter().zip(values).collect();
+        let inside = |point: GridPoint| values[&point] < 0.0;
+
+        let crossed = self.crossed_edges(&table, &inside)?;
+        let crossing_table: HashMap<GridEdge, usize> = crossed
+            .iter()
+            .enumerate()
+            .map(|(index, crossed)| (crossed.edge, index))
+            .collect();
+        let crossings = crossed
+            .par_iter()
+            .map(|crossed| {
+                let sdf = leaves[crossed.leaves[0]].sdf.as_ref().unwrap();
+                self.find_crossing(sdf, crossed.edge, crossed.size)
+            })
+            .collect::<Vec<_>>();
+
+        let mut touched = vec![false; leaves.len()];
+        for crossed in &crossed {
+            for leaf in crossed.leaves {
+                touched[leaf] = true;
+            }
+        }
+        let pieces = leaves
+            .par_iter()
+            .zip(touched.par_iter())
+            .map(|(leaf, &touched)| match &leaf.sdf {
+                Some(sdf) if touched => {
+                    self.components(leaf, sdf, &table, &crossed, &crossing_table, &inside)
+                }
+                _ => vec![],
+            })
+            .collect::<Vec<_>>();
+        let mut vertices = vec![];
+        let mut vertex_table: HashMap<(usize, usize), usize> = HashMap::new();
+        for (index, (leaf, components)) in leaves.iter().zip(&pieces).enumerate() {
+            for component in components {
+                let points = component
+                    .iter()
+                    .map(|&crossed| crossings[crossed])
+                    .collect::<Vec<_>>();
+                for &crossed in component {
+                    vertex_table.insert((index, crossed), vertices.len());
+                }
+                vertices.push(self.solve_vertex(leaf.min, leaf.size, &points));
+            }
+        }
+
+        let mut triangles = vec![];
+        for (index, crossed) in crossed.iter().enumerate() {
+            let mut polygon = crossed
+                .leaves
+                .map(|leaf| vertex_table[&(leaf, index)])
+                .to_vec();
+            if !inside(crossed.edge.0) {
+                polygon.reverse();
+            }
+            // A larger leaf on one side of the edge fills two of the four places around it.
+            polygon.dedup();
+            if polygon.len() > 1 && polygon.first() == polygon.last() {
+                polygon.pop();
+            }
+            for k in 1..polygon.len().saturating_sub(1) {
+                triangles.push(MeshTriangle::new(polygon[0], polygon[k], polygon[k + 1]));
+            }
+        }
+        Ok(Mesh::new(vertices, triangles))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sdf::AsSdf;
+    use patina_geo::geo3::cylinder::Cylinder;
+    use patina_geo::sphere::Sphere;
+    use patina_mesh::ser::encode_test_file;
+    use patina_vec::vec2::Vec2;
+
+    #[tokio::test]
+    async fn test_dual_contour() -> anyhow::Result<()> {
+        let cube = Aabb3::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 2.0, 0.5)).as_sdf();
+        let mut dual = DualContour::for_sdf(&cube)?;
+        dual.min_render_depth(2).max_render_depth(4);
+        let mesh = dual.build(&cube)?;
+        mesh.check_manifold().unwrap();
+        assert!(
+            mesh.vertices()
+                .iter()
+                .all(|v| cube.evaluate(*v).abs() < 1e-9)
+        );
+        for corner in [Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 2.0, 0.5)] {
+            assert!(
+                mesh.vertices()
+                    .iter()
+                    .any(|v| (*v - corner).length() < 1e-9)
+            );
+        }
+        encode_test_file(&mesh, "cube.stl").await?;
+
+        // The flat faces are covered by larger leaves than the edges.
+        dual.min_render_depth(4);
+        let uniform = dual.build(&cube)?;
+        uniform.check_manifold().unwrap();
+        assert!(mesh.vertices().len() < uniform.vertices().len());
+
+        // A surface cut off by the grid is an error rather than an open mesh.
+        let cut = Aabb3::new(Vec3::new(0.0, -2.0, -2.0), Vec3::new(2.0, 3.0, 1.5));
+        assert!(DualContour::new(&cut).build(&cube).is_err());
+
+        // The rims of the caps stay sharp.
+        let cylinder = Cylinder::new(Vec3::zero(), Vec3::axis_z() * 2.0, 1.0).as_sdf();
+        let mut dual = DualContour::for_sdf(&cylinder)?;
+        dual.min_render_depth(3).max_render_depth(5);
+        let mesh = dual.build(&cylinder)?;
+        mesh.check_manifold().unwrap();
+        let rim = mesh
+            .vertices()
+            .iter()
+            .filter(|v| {
+                (v.z() - 2.0).abs() < 1e-6 && (Vec2::new(v.x(), v.y()).length() - 1.0).abs() < 1e-3
+            })
+            .count();
+        assert!(rim >= 16, "{rim}");
+        encode_test_file(&mesh, "cylinder.stl").await?;
+
+        let part = cube
+            .difference(&Sphere::new(Vec3::new(1.0, 2.0, 0.5), 0.8).as_sdf())
+            .union(&Sphere::new(Vec3::new(-1.0, 0.0, 0.0), 0.5).as_sdf());
+        let mesh = DualContour::for_sdf(&part)?.build(&part)?;
+        mesh.check_manifold().unwrap();
+        encode_test_file(&mesh, "part.stl").await?;
+        Ok(())
+    }
+}
@@ -133,4 +133,16 @@ impl Scalar for Exact {
     fn abs(self) -> Self {
         Exact::try_from(self.interval().abs()).unwrap()
     }
+
+    fn sin(self) -> Self {
+        Exact::try_from(self.interval().sin()).unwrap()
+    }
+
+    fn cos(self) -> Self {
+        Exact::try_from(self.interval().cos()).unwrap()
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        Exact::try_from(self.interval().atan2(x.interval())).unwrap()
+    }
 }
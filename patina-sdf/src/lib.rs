@@ -8,6 +8,11 @@
 
 pub mod sdf;
 pub mod marching_mesh;
+pub mod marching_squares;
+pub mod dual_contour;
+pub mod mesh_cache;
+pub mod mass;
+pub mod render;
 // pub mod sdf;
 // pub mod geo;
 #[cfg(test)]
@@ -1,7 +1,10 @@
+use anyhow::{Context, ensure};
 use itertools::Itertools;
 use std::cell::OnceCell;
 // use patina_calc::{EvalVisitor, Expr, ExprProgramBuilder, Program, ProgramVisit, Solver};
 use crate::octree::{Octree, OctreeBranch, OctreePath, OctreeView, OctreeViewMut};
+use crate::sdf::bounds::padded_bounds;
+use crate::sdf::node::{SdfNode, structural_hash};
 use crate::sdf::{Sdf, Sdf3};
 use crate::transvoxel::cube_edge::{CubeEdge, CubeEdgeSet};
 use crate::transvoxel::cube_face::{CubeFace, CubeFaceSet};
@@ -10,8 +13,6 @@ use crate::transvoxel::cube_triangle::CubeTriMesh;
 use crate::transvoxel::cube_vertex;
 use crate::transvoxel::cube_vertex::{CubeVertex, CubeVertexSet, cube_corners, cube_points};
 use inari::DecInterval;
-use indicatif::{MultiProgress, ProgressBar, ProgressIterator, ProgressStyle};
-use indicatif::{ParallelProgressIterator, ProgressFinish};
 use ordered_float::NotNan;
 use parking_lot::Mutex;
 use patina_geo::aabb::Aabb;
@@ -19,17 +20,23 @@ use patina_geo::geo3::aabb3::Aabb3;
 use patina_geo::geo3::triangle3::Triangle3;
 use patina_mesh::mesh::Mesh;
 use patina_mesh::mesh_triangle::MeshTriangle;
-use patina_progress::{ProgressBuilder, ProgressGuard};
+use patina_progress::{Cancelled, Progress, ProgressGuard, Stage};
 use patina_scalar::deriv::Deriv;
 use patina_scalar::newton::Newton;
 use patina_vec::vec3::{Vec3, Vector3};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
 use rayon::iter::ParallelIterator;
+use serde::Serialize;
 use rayon::iter::{IndexedParallelIterator, ParallelBridge};
 use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator};
+use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+mod materials;
+
 #[derive(Debug, Default)]
 struct MarchingNodeValue {}
 
@@ -67,13 +74,17 @@ pub struct MarchingMesh {
     min_render_depth: usize,
     max_render_depth: usize,
     subdiv_max_dot: f64,
+    max_deviation: Option<f64>,
+    seed: Option<u64>,
     aabb: Aabb3,
     mesh_builder: Mutex<MeshBuilder>,
-    progress_builder: ProgressBuilder,
+    progress: Progress,
 }
 
 type MarchingOctree = Octree<MarchingNodeKey, MarchingNodeValue>;
 type MarchingOctreeBranch = OctreeBranch<MarchingNodeKey, MarchingNodeValue>;
+/// Whether to split the cell at a path, given its bounds and the simplified SDF within it.
+type Subdivide<'a> = dyn Fn(&OctreePath, &Aabb3, &Sdf3) -> bool + Sync + 'a;
 
 impl MarchingMesh {
     pub fn new(aabb: &Aabb3) -> Self {
@@ -81,15 +92,22 @@ impl MarchingMesh {
             min_render_depth: 6,
             max_render_depth: 10,
             subdiv_max_dot: 0.9,
+            max_deviation: None,
+            seed: None,
             aabb:*aabb,
             mesh_builder: Mutex::new(MeshBuilder {
                 vertex_table: HashMap::new(),
                 vertices: vec![],
                 triangles: vec![],
             }),
-            progress_builder: ProgressBuilder::new(),
+            progress: Progress::bars(),
         }
     }
+    /// Meshes all of `sdf`, within its [bounds](Sdf::bounds) padded so that the surface stays
+    /// clear of the edges of the grid.
+    pub fn for_sdf(sdf: &Sdf3) -> anyhow::Result<Self> {
+        Ok(Self::new(&padded_bounds(sdf, "mesh")?))
+    }
     pub fn min_render_depth(&mut self, min_render_depth: usize) -> &mut Self {
         self.min_render_depth = min_render_depth;
         self
@@ -102,10 +120,60 @@ impl MarchingMesh {
         self.subdiv_max_dot = subdiv_max_dot;
         self
     }
+    /// Makes [Self::build] reproducible: root finding is seeded with `seed`, each vertex is
+    /// solved in the same one of the cells sharing it, and the vertices and triangles of the mesh
+    /// are put in a canonical order that does not depend on how the work was scheduled across
+    /// threads.
+    pub fn deterministic(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Where [Self::build] reports progress. Cancelling it makes the build stop early and
+    /// return [Cancelled](patina_progress::Cancelled).
+    pub fn progress(&mut self, progress: Progress) -> &mut Self {
+        self.progress = progress;
+        self
+    }
+    /// Refines the octree until the surface of each cell is within `max_deviation` of the
+    /// triangles that mesh it, instead of comparing normals against
+    /// [subdiv_max_dot](Self::subdiv_max_dot). The deviation is measured by projecting onto the
+    /// surface, in the units of the model, so half the nozzle width is a natural choice when
+    /// printing. Cells
+    /// still stop at [max_render_depth](Self::max_render_depth), so
+    /// [build_with_deviation](Self::build_with_deviation) reports what was achieved.
+    pub fn max_deviation(&mut self, max_deviation: f64) -> &mut Self {
+        self.max_deviation = Some(max_deviation);
+        self
+    }
+    /// A key for caching the mesh that [Self::build] makes for `sdf`. It covers every setting
+    /// that affects the result, so two builds with the same key produce the same mesh.
+    pub fn cache_key(&self, sdf: &Sdf3) -> u64 {
+        #[derive(Serialize)]
+        struct CacheKey {
+            sdf: SdfNode,
+            min_render_depth: usize,
+            max_render_depth: usize,
+            subdiv_max_dot: f64,
+            max_deviation: Option<f64>,
+            seed: Option<u64>,
+            min: Vec3,
+            max: Vec3,
+        }
+        structural_hash(&CacheKey {
+            sdf: sdf.node(),
+            min_render_depth: self.min_render_depth,
+            max_render_depth: self.max_render_depth,
+            subdiv_max_dot: self.subdiv_max_dot,
+            max_deviation: self.max_deviation,
+            seed: self.seed,
+            min: self.aabb.min(),
+            max: self.aabb.max(),
+        })
+    }
     fn find_marching_cube(&self, aabb: &Aabb3, sdf: &Sdf<3>) -> CubeTriMesh {
-        let mut result = CubeVertexSet::new();
-        for cv in cube_corners() {
-            let p = (0..3)
+        let corners = cube_corners();
+        let ps = corners.map(|cv| {
+            (0..3)
                 .map(|axis| {
                     if cv[axis] > 0 {
                         aabb.max()[axis]
@@ -113,8 +181,12 @@ impl MarchingMesh {
                         aabb.min()[axis]
                     }
                 })
-                .collect();
-            let d = sdf.evaluate(p);
+                .collect()
+        });
+        let mut ds = [0.0; 8];
+        sdf.evaluate_batch(&ps, &mut ds);
+        let mut result = CubeVertexSet::new();
+        for (cv, d) in corners.into_iter().zip(ds) {
             result[cv] = d >= 0.0;
         }
         CubeInput::new(CubeFaceSet::new(), CubeEdgeSet::new(), result).as_mesh()
@@ -132,13 +204,13 @@ impl MarchingMesh {
             }
         }
     }
-    fn add_marching_cube_sub(
+    /// The faces and edges of the cell `octree` whose neighbors are divided, and so need
+    /// transition cells.
+    fn transitions(
         &self,
         root: &MarchingOctree,
         octree: &MarchingOctree,
-        aabb: &Aabb3,
-        sdf: &Sdf<3>,
-    ) {
+    ) -> (CubeFaceSet, CubeEdgeSet) {
         let mut faces = CubeFaceSet::new();
         for face in CubeFace::all() {
             let mut is_divided;
@@ -159,20 +231,32 @@ impl MarchingMesh {
             }
             edges[edge] = is_divided;
         }
-
+        (faces, edges)
+    }
+    fn add_marching_cube_sub(
+        &self,
+        root: &MarchingOctree,
+        octree: &MarchingOctree,
+        aabb: &Aabb3,
+        sdf: &Sdf<3>,
+    ) {
+        let (faces, edges) = self.transitions(root, octree);
         let mut to_sample = CubeVertexSet::corners();
         faces.add_samples_to(&mut to_sample);
         edges.add_samples_to(&mut to_sample);
         if to_sample != CubeVertexSet::new() {
             to_sample[cube_vertex::cube_center()] |= true;
         }
+        let samples: Vec<CubeVertex> = cube_points().filter(|&cv| to_sample[cv]).collect();
+        let ps: Vec<Vec3> = samples
+            .iter()
+            .map(|&cv| self.path_position(octree.path(), cv))
+            .collect();
+        let mut evals = vec![0.0; ps.len()];
+        sdf.evaluate_batch(&ps, &mut evals);
         let mut vertices = CubeVertexSet::new();
-        for cv in cube_points() {
-            if to_sample[cv] {
-                let v = self.path_position(octree.path(), cv);
-                let eval = sdf.evaluate(v);
-                vertices[cv] = eval >= 0.0;
-            }
+        for (cv, eval) in samples.into_iter().zip(evals) {
+            vertices[cv] = eval >= 0.0;
         }
         let transvoxel = CubeInput::new(faces, edges, vertices);
         let mesh2 = transvoxel.as_mesh();
@@ -205,21 +289,30 @@ impl MarchingMesh {
         }
         let v1p = self.path_position(path, v1);
         let v2p = self.path_position(path, v2);
-        *mesh_builder
-            .vertex_table
-            .entry((
-                v1p.map(|x| NotNan::new(x).unwrap()),
-                v2p.map(|x| NotNan::new(x).unwrap()),
-            ))
-            .or_insert_with(|| {
-                mesh_builder.vertices.push(VertexBuilder {
-                    sdf: sdf.clone(),
-                    path: *path,
-                    v1,
-                    v2,
-                });
-                mesh_builder.vertices.len() - 1
-            })
+        let builder = VertexBuilder {
+            sdf: sdf.clone(),
+            path: *path,
+            v1,
+            v2,
+        };
+        match mesh_builder.vertex_table.entry((
+            v1p.map(|x| NotNan::new(x).unwrap()),
+            v2p.map(|x| NotNan::new(x).unwrap()),
+        )) {
+            Entry::Occupied(entry) => {
+                // Cells simplify the SDF differently, so the vertex is solved in the cell with
+                // the smallest path rather than whichever cell got here first.
+                let vertex = &mut mesh_builder.vertices[*entry.get()];
+                if builder.path < vertex.path {
+                    *vertex = builder;
+                }
+                *entry.get()
+            }
+            Entry::Vacant(entry) => {
+                mesh_builder.vertices.push(builder);
+                *entry.insert(mesh_builder.vertices.len() - 1)
+            }
+        }
     }
     fn init_sdf(&self, tree: &mut MarchingOctree, sdf: &Sdf3) -> Option<Sdf3> {
         match tree.key().sdf.clone() {
@@ -240,20 +333,39 @@ impl MarchingMesh {
         tree.key_mut().sdf = SdfState::Sdf(sdf.clone());
         Some(sdf)
     }
-    fn build_octree(&self, tree: &mut MarchingOctree, sdf: &Sdf3, progress: ProgressGuard) {
+    fn build_octree(
+        &self,
+        tree: &mut MarchingOctree,
+        sdf: &Sdf3,
+        subdivide: &Subdivide,
+        progress: ProgressGuard,
+    ) {
+        if self.progress.is_cancelled() {
+            return;
+        }
         let aabb = tree.path().aabb_inside(&self.aabb);
         let sdf = self.init_sdf(tree, sdf);
         let Some(sdf) = sdf else {
             return;
         };
         if tree.path().depth() < self.min_render_depth {
-            self.build_branch(tree, &sdf, progress);
+            self.build_branch(tree, &sdf, subdivide, progress);
             return;
         }
         if tree.path().depth() >= self.max_render_depth {
             return;
         }
-        let mcube = self.find_marching_cube(&aabb, &sdf);
+        if subdivide(tree.path(), &aabb, &sdf) {
+            self.build_branch(tree, &sdf, subdivide, progress);
+        }
+    }
+    /// Whether the surface of `sdf` in the cell at `path` is too far from its marching cube
+    /// triangles, or too curved if no [max_deviation](Self::max_deviation) is set.
+    pub(crate) fn should_subdivide(&self, path: &OctreePath, aabb: &Aabb3, sdf: &Sdf3) -> bool {
+        let mcube = self.find_marching_cube(aabb, sdf);
+        if let Some(max_deviation) = self.max_deviation {
+            return self.cell_deviation(path, aabb, &mcube, sdf) > max_deviation;
+        }
         let vertices = mcube
             .triangles()
             .iter()
@@ -261,16 +373,46 @@ impl MarchingMesh {
             .collect::<HashSet<_>>();
         let mut normals = vec![];
         for (v1, v2) in vertices {
-            let (_, normal) = self.find_vertex(tree.path(), v1, v2, &sdf);
+            let (_, normal) = self.find_vertex(path, v1, v2, sdf);
             normals.push(normal);
         }
-        if normals
+        normals
             .iter()
             .tuple_combinations()
             .any(|(n1, n2)| n1.dot(*n2) < self.subdiv_max_dot)
-        {
-            self.build_branch(tree, &sdf, progress);
+    }
+    /// How far the surface strays from the marching cube triangles of the cell at `path`. A cell
+    /// without triangles whose center has a different sign from its corners hides a feature the
+    /// triangles miss entirely, so its deviation is unbounded.
+    fn cell_deviation(
+        &self,
+        path: &OctreePath,
+        aabb: &Aabb3,
+        mcube: &CubeTriMesh,
+        sdf: &Sdf3,
+    ) -> f64 {
+        if mcube.triangles().is_empty() {
+            let mut ds = [0.0; 2];
+            sdf.evaluate_batch(&[aabb.min(), aabb.center()], &mut ds);
+            return if (ds[0] >= 0.0) == (ds[1] >= 0.0) {
+                0.0
+            } else {
+                f64::INFINITY
+            };
         }
+        let mut positions = HashMap::new();
+        let triangles = mcube
+            .triangles()
+            .iter()
+            .map(|tri| {
+                tri.vertices().map(|(v1, v2)| {
+                    *positions
+                        .entry((v1, v2))
+                        .or_insert_with(|| self.find_vertex(path, v1, v2, sdf).0)
+                })
+            })
+            .collect::<Vec<_>>();
+        triangle_deviation(sdf, &triangles)
     }
     fn position(&self, aabb: &Aabb3, v: CubeVertex) -> Vec3 {
         (0..3)
@@ -285,6 +427,21 @@ impl MarchingMesh {
     fn position_range(&self, aabb: &Aabb3, v1: CubeVertex, v2: CubeVertex) -> (Vec3, Vec3) {
         (self.position(aabb, v1), self.position(aabb, v2))
     }
+    /// Where `sdf` crosses zero between `start` and `end`, as a fraction of the way along.
+    /// Newton's method is seeded if the build is [deterministic](Self::deterministic).
+    fn solve_segment(&self, sdf: &Sdf3, start: Vec3, end: Vec3) -> Option<f64> {
+        let range = end - start;
+        let lsdf = |t| {
+            sdf.evaluate_deriv1(
+                start.map(Deriv::constant) + range.map(Deriv::constant) * Deriv::variable(t, 0),
+            )
+        };
+        let t = match self.seed {
+            Some(seed) => Newton::with_rng(XorShiftRng::seed_from_u64(seed)).solve(0.0..1.0, lsdf),
+            None => Newton::new().solve(0.0..1.0, lsdf),
+        };
+        t.map(|t| t.into_inner())
+    }
     fn find_vertex(
         &self,
         path: &OctreePath,
@@ -300,9 +457,8 @@ impl MarchingMesh {
                 min.map(Deriv::constant) + range.map(Deriv::constant) * Deriv::variable(t, 0),
             )
         };
-        let t = Newton::new().solve(0.0..1.0, lsdf);
-        let t = if let Some(t) = t {
-            t.into_inner()
+        let t = if let Some(t) = self.solve_segment(sdf, min, max) {
+            t
         } else {
             println!("{:?}->{:?}", min, max);
             for x in -1..=11 {
@@ -316,7 +472,13 @@ impl MarchingMesh {
         let normal: Vec3 = sdf.normal(eval_position);
         (vertex_position, normal)
     }
-    fn build_branch(&self, tree: &mut MarchingOctree, sdf: &Sdf3, progress: ProgressGuard) {
+    fn build_branch(
+        &self,
+        tree: &mut MarchingOctree,
+        sdf: &Sdf3,
+        subdivide: &Subdivide,
+        progress: ProgressGuard,
+    ) {
         let depth = tree.path().depth();
         match tree.view_mut() {
             OctreeViewMut::Leaf(_, _) => {
@@ -339,7 +501,7 @@ impl MarchingMesh {
                     .par_iter_mut(),
             )
             .for_each(|(child, progress)| {
-                self.build_octree(child, sdf, progress.take());
+                self.build_octree(child, sdf, subdivide, progress.take());
             });
     }
 
@@ -387,12 +549,15 @@ impl MarchingMesh {
     }
 
     fn build_mesh_leaf(&self, root: &MarchingOctree, tree: &MarchingOctree, sdf: &Sdf3) {
+        if self.progress.is_cancelled() {
+            return;
+        }
         let aabb = tree.path().aabb_inside(&self.aabb);
         self.add_marching_cube_sub(root, tree, &aabb, sdf);
     }
 
     fn get_neighbors(
-        &mut self,
+        &self,
         octree: &MarchingOctree,
         depth: usize,
         neighbors: &mut HashSet<OctreePath>,
@@ -415,7 +580,7 @@ impl MarchingMesh {
             }
         }
     }
-    fn refine_path(&mut self, octree: &mut MarchingOctree, path: OctreePath, sdf: Option<&Sdf3>) {
+    fn refine_path(&self, octree: &mut MarchingOctree, path: OctreePath, sdf: Option<&Sdf3>) {
         if path.depth() == 1 {
             return;
         }
@@ -445,59 +610,126 @@ impl MarchingMesh {
         }
     }
 
-    fn refine_neighbors(&mut self, octree: &mut MarchingOctree, sdf: &Sdf3) {
-        for depth in (0..=self.max_render_depth as u16).rev().progress_with(
-            self.progress_builder
-                .build(self.max_render_depth as u64)
-                .with_message("refining neighbors"),
-        ) {
+    fn refine_neighbors(
+        &self,
+        octree: &mut MarchingOctree,
+        sdf: &Sdf3,
+        stage: &Stage,
+    ) -> anyhow::Result<()> {
+        let stage = stage.stage("refining neighbors", self.max_render_depth as u64 + 1);
+        for depth in (0..=self.max_render_depth as u16).rev() {
+            stage.check_cancelled()?;
             let mut to_refine = HashSet::new();
             self.get_neighbors(octree, depth as usize, &mut to_refine);
             for path in to_refine {
                 self.refine_path(octree, path, Some(sdf));
             }
+            stage.inc(1);
         }
+        Ok(())
     }
 
-    fn collect_mesh(&mut self) -> Mesh {
+    fn collect_mesh(&mut self, stage: &Stage) -> anyhow::Result<Mesh> {
         let mesh_builder = self.mesh_builder.lock();
+        let stage = stage.stage("collecting mesh", mesh_builder.vertices.len() as u64);
         let vertices = mesh_builder
             .vertices
             .par_iter()
-            .map(|x| self.find_vertex(&x.path, x.v1, x.v2, &x.sdf).0)
-            .progress_with(
-                self.progress_builder
-                    .build(mesh_builder.vertices.len() as u64)
-                    .with_message("collecting mesh"),
-            )
-            .collect();
-        let mesh = Mesh::new(vertices, mesh_builder.triangles.clone());
+            .map(|x| {
+                stage.check_cancelled()?;
+                let vertex = self.find_vertex(&x.path, x.v1, x.v2, &x.sdf).0;
+                stage.inc(1);
+                Ok(vertex)
+            })
+            .collect::<Result<_, Cancelled>>()?;
+        let mesh = if self.seed.is_some() {
+            Self::canonical_mesh(&mesh_builder, vertices)
+        } else {
+            Mesh::new(vertices, mesh_builder.triangles.clone())
+        };
         mesh.check_manifold().unwrap();
-        mesh
+        Ok(mesh)
     }
 
-    pub fn build(mut self, sdf: &Sdf3) -> Mesh {
-        let mut octree = MarchingOctree::new_root();
+    /// Orders the vertices by the grid edge they lie on, and the triangles by their vertices
+    /// starting from the lowest, which keeps their orientation.
+    fn canonical_mesh(mesh_builder: &MeshBuilder, vertices: Vec<Vec3>) -> Mesh {
+        let mut order = mesh_builder
+            .vertex_table
+            .iter()
+            .map(|(key, &index)| (key, index))
+            .collect::<Vec<_>>();
+        order.sort();
+        let mut remap = vec![0; vertices.len()];
+        for (new, &(_, old)) in order.iter().enumerate() {
+            remap[old] = new;
+        }
+        let vertices = order.iter().map(|&(_, old)| vertices[old]).collect();
+        let mut triangles = mesh_builder
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let mut vs = triangle.vertices().map(|v| remap[v]);
+                let lowest = (0..3).min_by_key(|&i| vs[i]).unwrap();
+                vs.rotate_left(lowest);
+                MeshTriangle::from(vs)
+            })
+            .collect::<Vec<_>>();
+        triangles.sort();
+        Mesh::new(vertices, triangles)
+    }
 
+    /// Builds the mesh as [Self::build] does, along with the largest deviation of the mesh from
+    /// the surface of `sdf`, sampled at the centroid and edge midpoints of each triangle.
+    pub fn build_with_deviation(self, sdf: &Sdf3) -> anyhow::Result<(Mesh, f64)> {
+        let mesh = self.build(sdf)?;
+        let triangles = mesh
+            .triangles()
+            .iter()
+            .map(|tri| tri.vertices().map(|v| mesh.vertices()[v]))
+            .collect::<Vec<_>>();
+        let deviation = triangle_deviation(sdf, &triangles);
+        Ok((mesh, deviation))
+    }
+
+    /// Builds the octree for `sdf`, splitting cells past the minimum depth where `subdivide`
+    /// says to, and refines it so that neighboring leaves differ by at most one level. These are
+    /// the first two of the four steps of `meshing`.
+    fn build_tree(
+        &self,
+        sdf: &Sdf3,
+        subdivide: &Subdivide,
+        meshing: &Stage,
+    ) -> anyhow::Result<MarchingOctree> {
+        let mut octree = MarchingOctree::new_root();
         let max_progress = 1 << (3 * self.max_render_depth);
-        let progress = self
-            .progress_builder
-            .build(max_progress)
-            .with_message("building tree");
+        let progress = meshing.stage("building tree", max_progress);
         self.build_octree(
             &mut octree,
             sdf,
+            subdivide,
             ProgressGuard::new(&progress, max_progress),
         );
         progress.finish();
+        meshing.check_cancelled()?;
+        meshing.inc(1);
+
+        self.refine_neighbors(&mut octree, sdf, meshing)?;
+        meshing.inc(1);
+        Ok(octree)
+    }
 
-        self.refine_neighbors(&mut octree, sdf);
+    pub fn build(mut self, sdf: &Sdf3) -> anyhow::Result<Mesh> {
+        let meshing = self.progress.stage("meshing", 4);
+        let octree = self.build_tree(
+            sdf,
+            &|path, aabb, sdf| self.should_subdivide(path, aabb, sdf),
+            &meshing,
+        )?;
         // let mut comp = Complexity::new();
         // comp.add_tree(&octree);
-        let progress = self
-            .progress_builder
-            .build(max_progress)
-            .with_message("building mesh");
+        let max_progress = 1 << (3 * self.max_render_depth);
+        let progress = meshing.stage("building mesh", max_progress);
         self.build_mesh(
             &octree,
             &octree,
@@ -505,10 +737,39 @@ impl MarchingMesh {
             ProgressGuard::new(&progress, max_progress),
         );
         progress.finish();
-        self.collect_mesh()
+        meshing.check_cancelled()?;
+        meshing.inc(1);
+        let mesh = self.collect_mesh(&meshing)?;
+        meshing.inc(1);
+        Ok(mesh)
     }
 }
 
+/// The largest distance from the surface of `sdf` of the centroid and edge midpoints of any of
+/// `triangles`. Vertices lie on the surface, so these are where flat triangles stray furthest.
+/// Samples are [projected](Sdf::project) onto the surface, since the SDF is only a lower bound on
+/// the distance wherever it is not exact. Where the projection fails, the SDF stands in.
+fn triangle_deviation(sdf: &Sdf3, triangles: &[[Vec3; 3]]) -> f64 {
+    let samples = triangles
+        .iter()
+        .flat_map(|&[a, b, c]| {
+            [
+                (a + b + c) / 3.0,
+                (a + b) / 2.0,
+                (b + c) / 2.0,
+                (c + a) / 2.0,
+            ]
+        })
+        .collect::<Vec<_>>();
+    samples
+        .par_iter()
+        .map(|&p| match sdf.project(p) {
+            Some(q) => (q - p).length(),
+            None => sdf.evaluate(p).abs(),
+        })
+        .reduce(|| 0.0, f64::max)
+}
+
 #[derive(Debug)]
 struct Complexity {
     depth_to_complexity_to_count: BTreeMap<usize, BTreeMap<usize, usize>>,
@@ -542,3 +803,119 @@ impl Complexity {
         }
     }
 }
+
+#[test]
+fn test_max_deviation() {
+    use crate::sdf::AsSdf;
+    use patina_geo::sphere::Sphere;
+    let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 5.0).as_sdf();
+    let deviation = |max_deviation: f64| {
+        let mut marching = MarchingMesh::for_sdf(&sphere).unwrap();
+        marching.min_render_depth(2).max_render_depth(7);
+        marching.max_deviation(max_deviation).progress(Progress::silent());
+        let (mesh, deviation) = marching.build_with_deviation(&sphere).unwrap();
+        (mesh.triangles().len(), deviation)
+    };
+    let (coarse_triangles, coarse) = deviation(0.2);
+    let (fine_triangles, fine) = deviation(0.02);
+    assert!(coarse <= 0.2, "{}", coarse);
+    assert!(fine <= 0.02, "{}", fine);
+    assert!(fine < coarse);
+    assert!(fine_triangles > coarse_triangles);
+}
+
+#[test]
+fn test_vertex_cell() {
+    use crate::octree::OctreeIndex;
+    use crate::sdf::AsSdf;
+    use patina_geo::sphere::Sphere;
+    let sphere = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let marching = MarchingMesh::new(&Aabb::new(Vec3::splat(-2.0), Vec3::splat(2.0)));
+    let root = OctreePath::new_root();
+    let low = root.push_back(OctreeIndex::from([false, false, false]));
+    let high = root.push_back(OctreeIndex::from([true, false, false]));
+    // The edge between the two cells, from each side.
+    let from_low = (low, CubeVertex::from([2, 0, 0]), CubeVertex::from([2, 2, 0]));
+    let from_high = (high, CubeVertex::from([0, 0, 0]), CubeVertex::from([0, 2, 0]));
+    for order in [[from_low, from_high], [from_high, from_low]] {
+        let mut builder = MeshBuilder {
+            vertex_table: HashMap::new(),
+            vertices: vec![],
+            triangles: vec![],
+        };
+        for (path, v1, v2) in order {
+            assert_eq!(marching.get_vertex(&path, v1, v2, &sphere, &mut builder), 0);
+        }
+        assert_eq!(builder.vertices.len(), 1);
+        assert_eq!(builder.vertices[0].path, low);
+        assert_eq!(builder.vertices[0].v1, CubeVertex::from([2, 0, 0]));
+    }
+}
+
+#[test]
+fn test_triangle_deviation() {
+    use crate::sdf::AsSdf;
+    use patina_geo::sphere::Sphere;
+    // Stretching underestimates distances along the stretch, so the SDF here is a quarter of
+    // the distance to the tip.
+    let ellipsoid = Sphere::new(Vec3::zero(), 1.0)
+        .as_sdf()
+        .scale(Vec3::new(4.0, 1.0, 1.0));
+    let triangle = [
+        Vec3::new(4.1, 0.0, 0.0),
+        Vec3::new(4.1, 0.001, 0.0),
+        Vec3::new(4.1, 0.0, 0.001),
+    ];
+    let deviation = triangle_deviation(&ellipsoid, &[triangle]);
+    assert!((deviation - 0.1).abs() < 1e-3, "{}", deviation);
+}
+
+#[test]
+fn test_cancel() {
+    use crate::sdf::AsSdf;
+    use patina_geo::sphere::Sphere;
+    use patina_progress::{CancellationToken, ProgressEvent};
+    let sphere = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let cancellation = CancellationToken::new();
+    let mut progress = Progress::callback({
+        let cancellation = cancellation.clone();
+        move |event, status| {
+            if event == ProgressEvent::Finish && status.name() == "building tree" {
+                cancellation.cancel();
+            }
+        }
+    });
+    progress.cancellation(cancellation);
+    let mut marching = MarchingMesh::for_sdf(&sphere).unwrap();
+    marching.min_render_depth(3).max_render_depth(4).progress(progress);
+    let error = marching.build(&sphere).unwrap_err();
+    assert!(error.is::<Cancelled>());
+}
+
+#[test]
+fn test_deterministic() {
+    use crate::sdf::AsSdf;
+    use patina_geo::sphere::Sphere;
+    let sdf = Sphere::new(Vec3::new(-0.25, 0.0, 0.0), 0.5)
+        .as_sdf()
+        .union(&Sphere::new(Vec3::new(0.25, 0.0, 0.0), 0.5).as_sdf());
+    let build = || {
+        let mut marching = MarchingMesh::for_sdf(&sdf).unwrap();
+        marching
+            .min_render_depth(3)
+            .max_render_depth(5)
+            .deterministic(7)
+            .progress(Progress::silent());
+        marching.build(&sdf).unwrap()
+    };
+    let mesh = build();
+    for _ in 0..3 {
+        assert_eq!(build(), mesh);
+    }
+    assert!(
+        mesh.vertices()
+            .windows(2)
+            .all(|pair| pair[0] != pair[1])
+    );
+    assert!(mesh.triangles().is_sorted());
+}
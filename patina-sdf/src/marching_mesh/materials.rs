@@ -0,0 +1,436 @@
+use crate::marching_mesh::{MarchingMesh, MarchingOctree, SdfState};
+use crate::octree::{OctreePath, OctreeView};
+use crate::sdf::{AsSdf, Sdf, Sdf3};
+use crate::transvoxel::cube_tetr::CubeTetrMesh;
+use crate::transvoxel::cube_vertex::{CubeVertex, cube_points};
+use anyhow::{Context, ensure};
+use inari::DecInterval;
+use itertools::Itertools;
+use ordered_float::NotNan;
+use parking_lot::Mutex;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::sphere::Sphere;
+use patina_mesh::mesh::Mesh;
+use patina_mesh::mesh_triangle::MeshTriangle;
+use patina_progress::{Cancelled, Progress};
+use patina_vec::vec3::{Vec3, Vector3};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::{HashMap, HashSet};
+
+/// Identifies a vertex by the sample points it is derived from: the two ends of a tetrahedron
+/// edge, the three corners of a face or the four corners of a tetrahedron.
+type VertexKey = Vec<Vector3<NotNan<f64>>>;
+
+enum MaterialVertex {
+    /// Where the material at `start` ends on the way to `end`.
+    Crossing {
+        start: Vec3,
+        end: Vec3,
+        material: usize,
+    },
+    /// The average of the crossings of a face or tetrahedron.
+    Average(Vec<usize>),
+}
+
+#[derive(Default)]
+struct MaterialBuilder {
+    vertex_table: HashMap<VertexKey, usize>,
+    vertices: Vec<(VertexKey, MaterialVertex)>,
+    triangles: Vec<Vec<MeshTriangle>>,
+}
+
+impl MaterialBuilder {
+    fn vertex(
+        &mut self,
+        key: VertexKey,
+        vertex: impl FnOnce(&mut Self) -> MaterialVertex,
+    ) -> usize {
+        if let Some(&index) = self.vertex_table.get(&key) {
+            return index;
+        }
+        let vertex = vertex(self);
+        self.vertices.push((key.clone(), vertex));
+        self.vertex_table.insert(key, self.vertices.len() - 1);
+        self.vertices.len() - 1
+    }
+    /// Adds the vertices and triangles of a single cell, reusing the vertices already known.
+    fn merge(&mut self, cell: MaterialBuilder) {
+        let mut remap = Vec::with_capacity(cell.vertices.len());
+        for (key, vertex) in cell.vertices {
+            let vertex = match vertex {
+                MaterialVertex::Average(indices) => {
+                    MaterialVertex::Average(indices.iter().map(|&i| remap[i]).collect())
+                }
+                crossing => crossing,
+            };
+            remap.push(self.vertex(key, |_| vertex));
+        }
+        for (triangles, cell) in self.triangles.iter_mut().zip(cell.triangles) {
+            triangles.extend(
+                cell.into_iter()
+                    .map(|triangle| MeshTriangle::from(triangle.vertices().map(|v| remap[v]))),
+            );
+        }
+    }
+}
+
+impl MarchingMesh {
+    /// Meshes several material regions so that they can be printed as separate parts of one
+    /// object. Where regions overlap, the earlier region wins. Each material gets its own closed
+    /// mesh, and wherever two materials touch, their meshes share exactly the same vertices and
+    /// triangles with opposite orientations, so slicers see neither gaps nor overlaps.
+    ///
+    /// The cells are split into tetrahedra as in [Self::build], and each tetrahedron is divided
+    /// between the materials at its corners by surfaces through the crossings on its edges, the
+    /// centers of its faces and its center. The meshes are in a canonical order.
+    pub fn build_materials(self, regions: &[Sdf3]) -> anyhow::Result<Vec<Mesh>> {
+        ensure!(
+            !regions.is_empty(),
+            "cannot mesh an empty list of materials"
+        );
+        let materials = regions
+            .iter()
+            .enumerate()
+            .map(|(index, region)| match index {
+                0 => region.clone(),
+                _ => region.difference(&Sdf::union_all(regions[..index].iter().cloned())),
+            })
+            .collect::<Vec<_>>();
+        // Zero exactly on the surface of some material, and positive everywhere else.
+        let interfaces = Sdf::union_all(
+            materials
+                .iter()
+                .map(|material| material.intersection(&material.invert())),
+        );
+        let meshing = self.progress.stage("meshing", 4);
+        // Each material is refined like a single solid.
+        let octree = self.build_tree(
+            &interfaces,
+            &|path, aabb, _| {
+                materials
+                    .iter()
+                    .any(|material| self.should_subdivide(path, aabb, material))
+            },
+            &meshing,
+        )?;
+
+        let mut leaves = vec![];
+        collect_leaves(&octree, &mut leaves);
+        let builder = Mutex::new(MaterialBuilder {
+            triangles: vec![vec![]; regions.len()],
+            ..MaterialBuilder::default()
+        });
+        let progress = meshing.stage("building mesh", leaves.len() as u64);
+        leaves.par_iter().try_for_each(|&path| {
+            progress.check_cancelled()?;
+            self.add_material_cell(&octree, path, regions, &builder);
+            progress.inc(1);
+            Ok::<_, Cancelled>(())
+        })?;
+        progress.finish();
+        meshing.inc(1);
+
+        let meshes = self.collect_materials(builder.into_inner(), &materials, &meshing)?;
+        meshing.inc(1);
+        Ok(meshes)
+    }
+
+    /// The index of the first region containing each sample point of a cell. Each region is
+    /// simplified over the cell first, and regions that cover or miss the whole cell are not
+    /// evaluated at all.
+    fn material_labels(
+        &self,
+        path: &OctreePath,
+        samples: &[CubeVertex],
+        regions: &[Sdf3],
+    ) -> HashMap<CubeVertex, Option<usize>> {
+        let positions = samples
+            .iter()
+            .map(|&cv| self.path_position(path, cv))
+            .collect::<Vec<_>>();
+        let aabb = path.aabb_inside(&self.aabb);
+        let aabb_intervals: Vector3<DecInterval> = (0..3)
+            .map(|axis| DecInterval::try_from((aabb.min()[axis], aabb.max()[axis])).unwrap())
+            .collect();
+        let mut labels = vec![None; samples.len()];
+        let mut values = vec![0.0; samples.len()];
+        for (index, region) in regions.iter().enumerate() {
+            let (constrained, range) = region.evaluate_constrain(aabb_intervals);
+            if range.inf() >= 0.0 {
+                continue;
+            }
+            if range.sup() < 0.0 {
+                for label in labels.iter_mut().filter(|label| label.is_none()) {
+                    *label = Some(index);
+                }
+                break;
+            }
+            constrained
+                .as_ref()
+                .unwrap_or(region)
+                .evaluate_batch(&positions, &mut values);
+            for (label, &value) in labels.iter_mut().zip(&values) {
+                if label.is_none() && value < 0.0 {
+                    *label = Some(index);
+                }
+            }
+        }
+        samples.iter().copied().zip(labels).collect()
+    }
+
+    fn add_material_cell(
+        &self,
+        root: &MarchingOctree,
+        octree: &MarchingOctree,
+        regions: &[Sdf3],
+        shared: &Mutex<MaterialBuilder>,
+    ) {
+        let path = octree.path();
+        let (faces, edges) = self.transitions(root, octree);
+        let tetrs = CubeTetrMesh::divided_cube(&faces, &edges);
+        let samples = cube_points()
+            .filter(|cv| {
+                tetrs
+                    .tetrs()
+                    .iter()
+                    .any(|tetr| tetr.vertices().contains(cv))
+            })
+            .collect::<Vec<_>>();
+        let labels = self.material_labels(path, &samples, regions);
+        let key = |cvs: &[CubeVertex]| -> VertexKey {
+            cvs.iter()
+                .map(|&cv| {
+                    self.path_position(path, cv)
+                        .map(|x| NotNan::new(x).unwrap())
+                })
+                .sorted()
+                .collect()
+        };
+        // The cell is built on its own and merged in once at the end, so that cells do not wait
+        // for each other while they build.
+        let mut cell = MaterialBuilder {
+            triangles: vec![vec![]; regions.len()],
+            ..MaterialBuilder::default()
+        };
+        let builder = &mut cell;
+        for tetr in tetrs.tetrs() {
+            let corners = *tetr.vertices();
+            let tetr_labels = corners.map(|cv| labels[&cv]);
+            if tetr_labels.iter().all_equal() {
+                continue;
+            }
+            let crossing = |builder: &mut MaterialBuilder, i: usize, j: usize| {
+                builder.vertex(key(&[corners[i], corners[j]]), |_| {
+                    // Solve from the end with the lower key, so every cell sharing the edge
+                    // solves the same problem.
+                    let (mut start, mut end) = (i, j);
+                    if key(&[corners[j]]) < key(&[corners[i]]) {
+                        (start, end) = (j, i);
+                    }
+                    if tetr_labels[start].is_none() {
+                        (start, end) = (end, start);
+                    }
+                    MaterialVertex::Crossing {
+                        start: self.path_position(path, corners[start]),
+                        end: self.path_position(path, corners[end]),
+                        material: tetr_labels[start].unwrap(),
+                    }
+                })
+            };
+            let average = |builder: &mut MaterialBuilder, indices: &[usize]| {
+                let cvs = indices.iter().map(|&i| corners[i]).collect::<Vec<_>>();
+                builder.vertex(key(&cvs), |builder| {
+                    MaterialVertex::Average(
+                        indices
+                            .iter()
+                            .tuple_combinations()
+                            .filter(|&(&i, &j)| tetr_labels[i] != tetr_labels[j])
+                            .map(|(&i, &j)| crossing(builder, i, j))
+                            .collect(),
+                    )
+                })
+            };
+            let center = average(builder, &[0, 1, 2, 3]);
+            for (i, j) in (0..4).tuple_combinations() {
+                if tetr_labels[i] == tetr_labels[j] {
+                    continue;
+                }
+                let [mut c, mut d] = (0..4)
+                    .filter(|&k| k != i && k != j)
+                    .collect_array()
+                    .unwrap();
+                // Face the surface from corner `i` towards corner `j`, judged on the lattice of
+                // the cell scaled so that every point involved has integer coordinates.
+                let lattice = |ks: &[usize]| {
+                    ks.iter()
+                        .map(|&k| corners[k].map(|x| x as isize))
+                        .fold(Vector3::splat(0), |sum, v| sum + v)
+                        * (12 / ks.len() as isize)
+                };
+                let edge = lattice(&[i, j]);
+                let normal = (lattice(&[i, j, c]) - edge).cross(lattice(&[i, j, c, d]) - edge);
+                if normal.dot(lattice(&[j]) - lattice(&[i])) < 0 {
+                    (c, d) = (d, c);
+                }
+                let e = crossing(builder, i, j);
+                let f1 = average(builder, &[i, j, c]);
+                let f2 = average(builder, &[i, j, d]);
+                if let Some(material) = tetr_labels[i] {
+                    builder.triangles[material].push(MeshTriangle::new(e, f1, center));
+                    builder.triangles[material].push(MeshTriangle::new(e, center, f2));
+                }
+                if let Some(material) = tetr_labels[j] {
+                    builder.triangles[material].push(MeshTriangle::new(e, center, f1));
+                    builder.triangles[material].push(MeshTriangle::new(e, f2, center));
+                }
+            }
+        }
+        shared.lock().merge(cell);
+    }
+
+    fn collect_materials(
+        &self,
+        builder: MaterialBuilder,
+        materials: &[Sdf3],
+        meshing: &patina_progress::Stage,
+    ) -> anyhow::Result<Vec<Mesh>> {
+        let progress = meshing.stage("collecting mesh", builder.vertices.len() as u64);
+        let mut order = (0..builder.vertices.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| builder.vertices[a].0.cmp(&builder.vertices[b].0));
+        let mut remap = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            remap[old] = new;
+        }
+        let crossings = builder
+            .vertices
+            .par_iter()
+            .map(|(_, vertex)| {
+                progress.check_cancelled()?;
+                progress.inc(1);
+                Ok(match vertex {
+                    &MaterialVertex::Crossing {
+                        start,
+                        end,
+                        material,
+                    } => {
+                        let t = self
+                            .solve_segment(&materials[material], start, end)
+                            .with_context(|| {
+                                format!(
+                                    "material {material} does not cross zero between {start:?} \
+                                     and {end:?}"
+                                )
+                            })?;
+                        // Kept off the sample points as in [MarchingMesh::build], so that no
+                        // triangle collapses.
+                        Some(start + (end - start) * t.clamp(0.00001, 0.99999))
+                    }
+                    MaterialVertex::Average(_) => None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let positions = builder
+            .vertices
+            .iter()
+            .zip(&crossings)
+            .map(|((_, vertex), crossing)| match vertex {
+                MaterialVertex::Crossing { .. } => crossing.unwrap(),
+                MaterialVertex::Average(indices) => {
+                    indices.iter().map(|&i| crossings[i].unwrap()).sum::<Vec3>()
+                        / indices.len() as f64
+                }
+            })
+            .collect::<Vec<_>>();
+        let vertices = order.iter().map(|&old| positions[old]).collect::<Vec<_>>();
+        let mut meshes = vec![];
+        for triangles in builder.triangles {
+            let mut triangles = triangles
+                .into_iter()
+                .map(|triangle| {
+                    let mut vs = triangle.vertices().map(|v| remap[v]);
+                    let lowest = (0..3).min_by_key(|&i| vs[i]).unwrap();
+                    vs.rotate_left(lowest);
+                    MeshTriangle::from(vs)
+                })
+                .collect::<Vec<_>>();
+            triangles.sort();
+            let mesh = Mesh::new(vertices.clone(), triangles).without_dead_vertices();
+            mesh.check_manifold()?;
+            meshes.push(mesh);
+        }
+        Ok(meshes)
+    }
+}
+
+fn collect_leaves<'a>(tree: &'a MarchingOctree, leaves: &mut Vec<&'a MarchingOctree>) {
+    match tree.view() {
+        OctreeView::Leaf(key, _) => {
+            if let SdfState::Sdf(_) = key.sdf {
+                leaves.push(tree);
+            }
+        }
+        OctreeView::Branch(branch) => {
+            for child in branch.children_flat() {
+                collect_leaves(child, leaves);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_build_materials() {
+    let volume = |mesh: &Mesh| {
+        mesh.triangles()
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.vertices().map(|v| mesh.vertices()[v]);
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum::<f64>()
+    };
+    // A sphere with its side cut off by a block of another color.
+    let sphere = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let block = Aabb3::new(Vec3::new(0.5, -1.5, -1.5), Vec3::new(1.5, 1.5, 1.5)).as_sdf();
+    let mut marching = MarchingMesh::for_sdf(&block.union(&sphere)).unwrap();
+    marching
+        .min_render_depth(4)
+        .max_render_depth(5)
+        .deterministic(0)
+        .progress(Progress::silent());
+    let meshes = marching
+        .build_materials(&[block.clone(), sphere.clone()])
+        .unwrap();
+    assert_eq!(meshes.len(), 2);
+    let block_volume = volume(&meshes[0]);
+    let sphere_volume = volume(&meshes[1]);
+    assert!((block_volume - 9.0).abs() < 0.1, "{block_volume}");
+    let cap = std::f64::consts::PI * 0.25 * (3.0 - 0.5) / 3.0;
+    let expected = 4.0 / 3.0 * std::f64::consts::PI - cap;
+    assert!(
+        (sphere_volume - expected).abs() < 0.1,
+        "{sphere_volume} {expected}"
+    );
+
+    // The interface is made of the same triangles in both meshes, facing opposite ways.
+    let triangles = |mesh: &Mesh| {
+        mesh.triangles()
+            .iter()
+            .map(|triangle| {
+                triangle
+                    .vertices()
+                    .map(|v| mesh.vertices()[v].map(|x| NotNan::new(x).unwrap()))
+            })
+            .collect::<Vec<_>>()
+    };
+    let block_triangles = triangles(&meshes[0]).into_iter().collect::<HashSet<_>>();
+    let shared = triangles(&meshes[1])
+        .into_iter()
+        .filter(|&[a, b, c]| block_triangles.contains(&[a, c, b]))
+        .count();
+    assert!(shared > 100, "{shared}");
+    assert!(
+        triangles(&meshes[1])
+            .iter()
+            .all(|triangle| !block_triangles.contains(triangle))
+    );
+}
@@ -0,0 +1,282 @@
+use crate::sdf::Sdf2;
+use crate::sdf::bounds::{is_empty, padded_bounds};
+use anyhow::{Context, ensure};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo2::aabb2::Aabb2;
+use patina_geo::geo2::polygon2::Polygon2;
+use patina_mesh::directed_mesh_edge::DirectedMeshEdge;
+use patina_mesh::edge_mesh2::EdgeMesh2;
+use patina_scalar::deriv::Deriv;
+use patina_scalar::newton::Newton;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use std::collections::{HashMap, HashSet};
+
+/// The deepest the grid is subdivided, however small the tolerance.
+const MAX_DEPTH: usize = 20;
+
+/// The offsets of the corners of a grid cell, counterclockwise from the minimum.
+const CORNERS: [(usize, usize); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
+/// Extracts the contours of an [Sdf2] as polygons by marching squares. The grid is regular, but
+/// only cells that [evaluate_constrain](crate::sdf::Sdf::evaluate_constrain) cannot prove are
+/// inside or outside the solid are visited. The solid is clipped to the grid, so contours that
+/// would leave it run along its edge instead.
+pub struct MarchingSquares {
+    aabb: Aabb2,
+    tolerance: f64,
+}
+
+/// Identifies the grid edge from the grid point `(x, y)` along `axis`.
+type GridEdge = (usize, usize, usize);
+
+impl MarchingSquares {
+    pub fn new(aabb: &Aabb2) -> Self {
+        MarchingSquares {
+            aabb: *aabb,
+            tolerance: aabb.dimensions().into_iter().fold(0.0, f64::max) / 256.0,
+        }
+    }
+    /// Contours all of `sdf`, within its [bounds](crate::sdf::Sdf::bounds) padded so that the
+    /// contours stay clear of the edges of the grid.
+    pub fn for_sdf(sdf: &Sdf2) -> anyhow::Result<Self> {
+        Ok(Self::new(&padded_bounds(sdf, "contour")?))
+    }
+    /// The largest size of a grid cell. Vertices lie on the contour, so this bounds how far the
+    /// contour strays from the polygons between vertices.
+    pub fn tolerance(&mut self, tolerance: f64) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+    /// The number of grid cells along each axis.
+    fn resolution(&self) -> usize {
+        let size = self.aabb.dimensions().into_iter().fold(0.0, f64::max);
+        let mut depth = 0;
+        while depth < MAX_DEPTH && size / (1 << depth) as f64 > self.tolerance {
+            depth += 1;
+        }
+        1 << depth
+    }
+    fn on_boundary(resolution: usize, (x, y): (usize, usize)) -> bool {
+        x == 0 || y == 0 || x == resolution || y == resolution
+    }
+    fn position(&self, resolution: usize, (x, y): (usize, usize)) -> Vec2 {
+        let fraction = Vec2::new(x as f64, y as f64) / resolution as f64;
+        self.aabb.min() + fraction.mul_elements(self.aabb.dimensions())
+    }
+    /// Adds to `cells` the cells of the square of `size` cells at `(x, y)` that may contain part
+    /// of the contour.
+    fn find_cells(
+        &self,
+        sdf: &Sdf2,
+        resolution: usize,
+        (x, y): (usize, usize),
+        size: usize,
+        cells: &mut Vec<(usize, usize)>,
+    ) {
+        let min = self.position(resolution, (x, y));
+        let max = self.position(resolution, (x + size, y + size));
+        let p = Vector::from_fn(|axis| DecInterval::try_from((min[axis], max[axis])).unwrap());
+        let (simplified, range) = sdf.evaluate_constrain(p);
+        // Squares inside the solid still hold part of the contour where they touch the boundary.
+        let boundary = x == 0 || y == 0 || x + size == resolution || y + size == resolution;
+        if !range.is_empty() && (range.inf() > 0.0 || (range.sup() < 0.0 && !boundary)) {
+            return;
+        }
+        if size == 1 {
+            cells.push((x, y));
+            return;
+        }
+        let sdf = simplified.as_ref().unwrap_or(sdf);
+        let half = size / 2;
+        for (dx, dy) in CORNERS {
+            self.find_cells(sdf, resolution, (x + dx * half, y + dy * half), half, cells);
+        }
+    }
+    /// The point where the contour crosses the segment from `start` to `end`, whose ends have
+    /// opposite signs.
+    fn find_vertex(&self, sdf: &Sdf2, start: Vec2, end: Vec2) -> Vec2 {
+        let range = end - start;
+        let t = Newton::new().solve(0.0..1.0, |t| {
+            sdf.evaluate_deriv1(
+                start.map(Deriv::constant) + range.map(Deriv::constant) * Deriv::variable(t, 0),
+            )
+        });
+        let t = match t {
+            Some(t) => t.into_inner(),
+            None => {
+                let (d1, d2) = (sdf.evaluate(start), sdf.evaluate(end));
+                d1 / (d1 - d2)
+            }
+        };
+        start + range * t.clamp(0.0, 1.0)
+    }
+    /// The contours of `sdf` as closed loops, counterclockwise around the solid and clockwise
+    /// around holes. Points on the edge of the grid count as outside the solid, so that loops
+    /// close along it.
+    pub fn build(&self, sdf: &Sdf2) -> EdgeMesh2 {
+        let resolution = self.resolution();
+        let mut cells = vec![];
+        self.find_cells(sdf, resolution, (0, 0), resolution, &mut cells);
+
+        let mut points = cells
+            .iter()
+            .flat_map(|&(x, y)| CORNERS.map(|(dx, dy)| (x + dx, y + dy)))
+            .collect::<Vec<_>>();
+        points.sort();
+        points.dedup();
+        let positions = points
+            .iter()
+            .map(|&point| self.position(resolution, point))
+            .collect::<Vec<_>>();
+        let mut values = vec![0.0; points.len()];
+        sdf.evaluate_batch(&positions, &mut values);
+        let mut clipped = HashSet::new();
+        let values: HashMap<(usize, usize), f64> = points
+            .into_iter()
+            .zip(values)
+            .map(|(point, value)| {
+                if value < 0.0 && Self::on_boundary(resolution, point) {
+                    clipped.insert(point);
+                    (point, 0.0)
+                } else {
+                    (point, value)
+                }
+            })
+            .collect();
+
+        let mut vertex_table: HashMap<GridEdge, usize> = HashMap::new();
+        let mut vertices = vec![];
+        let mut edges = vec![];
+        let mut vertex = |edge: GridEdge| {
+            *vertex_table.entry(edge).or_insert_with(|| {
+                let (x, y, axis) = edge;
+                let end = if axis == 0 { (x + 1, y) } else { (x, y + 1) };
+                // The contour of the clipped solid crosses at the edge of the grid.
+                let vertex =
+                    if let Some(&point) = [(x, y), end].iter().find(|p| clipped.contains(p)) {
+                        self.position(resolution, point)
+                    } else {
+                        self.find_vertex(
+                            sdf,
+                            self.position(resolution, (x, y)),
+                            self.position(resolution, end),
+                        )
+                    };
+                vertices.push(vertex);
+                vertices.len() - 1
+            })
+        };
+        for (x, y) in cells {
+            let inside = CORNERS.map(|(dx, dy)| values[&(x + dx, y + dy)] < 0.0);
+            let grid_edges: [GridEdge; 4] = [(x, y, 0), (x + 1, y, 1), (x, y + 1, 0), (x, y, 1)];
+            // The contour leaves the solid across the side from corner `k` to corner `k + 1`
+            // where only `k` is inside, and enters it where only `k + 1` is.
+            let exits = (0..4).filter(|&k| inside[k] && !inside[(k + 1) % 4]);
+            let entries = (0..4)
+                .filter(|&k| !inside[k] && inside[(k + 1) % 4])
+                .collect::<Vec<_>>();
+            // When two corners are inside, the contour joins them through the center if it is
+            // inside too, or else cuts each of them off.
+            let joined = entries.len() == 2 && {
+                let center = (self.position(resolution, (x, y))
+                    + self.position(resolution, (x + 1, y + 1)))
+                    / 2.0;
+                sdf.evaluate(center) < 0.0
+            };
+            for exit in exits {
+                let entry = (1..4)
+                    .map(|step| (if joined { exit + step } else { exit + 4 - step }) % 4)
+                    .find(|side| entries.contains(side))
+                    .unwrap();
+                edges.push(DirectedMeshEdge::new(
+                    vertex(grid_edges[exit]),
+                    vertex(grid_edges[entry]),
+                ));
+            }
+        }
+        EdgeMesh2::from_vecs(vertices, edges)
+    }
+    pub fn build_polygons(&self, sdf: &Sdf2) -> Vec<Polygon2> {
+        self.build(sdf).as_polygons()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sdf::AsSdf;
+    use patina_geo::geo3::aabb3::Aabb3;
+    use patina_geo::geo3::plane::Plane;
+    use patina_geo::sphere::{Circle, Sphere};
+    use patina_mesh::ser::encode_test_file;
+    use patina_vec::vec3::Vec3;
+
+    #[tokio::test]
+    async fn test_marching_squares() -> anyhow::Result<()> {
+        let circle = Circle::new(Vec2::new(1.0, 2.0), 1.5).as_sdf();
+        let mut marching = MarchingSquares::for_sdf(&circle)?;
+        marching.tolerance(0.05);
+        let polygons = marching.build_polygons(&circle);
+        assert_eq!(polygons.len(), 1);
+        let area = polygons[0].signed_area();
+        assert!(area > 0.0 && (area - std::f64::consts::PI * 2.25).abs() < 0.02);
+        assert!(
+            polygons[0]
+                .points()
+                .iter()
+                .all(|p| circle.evaluate(*p).abs() < 1e-6)
+        );
+
+        // A box with a hole through it, sliced across the hole and along its axis.
+        let part = Aabb3::new(Vec3::splat(-1.0), Vec3::splat(1.0))
+            .as_sdf()
+            .difference(&Sphere::new(Vec3::zero(), 0.5).as_sdf());
+        let slice = part.slice_z(0.0);
+        let mesh = MarchingSquares::for_sdf(&slice)?.build(&slice);
+        let mut areas = mesh
+            .as_polygons()
+            .iter()
+            .map(|polygon| polygon.signed_area())
+            .collect::<Vec<_>>();
+        areas.sort_by(f64::total_cmp);
+        assert_eq!(areas.len(), 2);
+        assert!((areas[0] + std::f64::consts::PI * 0.25).abs() < 0.01);
+        assert!((areas[1] - 4.0).abs() < 0.01);
+        encode_test_file(&mesh, "hole.svg").await?;
+
+        let slice = part.slice(&Plane::new(Vec3::new(0.0, 0.9, 0.0), Vec3::axis_y()));
+        assert_eq!(
+            MarchingSquares::for_sdf(&slice)?
+                .build_polygons(&slice)
+                .len(),
+            1
+        );
+        assert!(MarchingSquares::for_sdf(&part.slice_z(2.0)).is_err());
+
+        // A grid that cuts the circle in half closes the contour along its edge.
+        let mut clipped =
+            MarchingSquares::new(&Aabb::new(Vec2::new(1.0, 0.0), Vec2::new(3.0, 4.0)));
+        clipped.tolerance(0.05);
+        let polygons = clipped.build_polygons(&circle);
+        assert_eq!(polygons.len(), 1);
+        let area = polygons[0].signed_area();
+        assert!((area - std::f64::consts::PI * 2.25 / 2.0).abs() < 0.02);
+        assert!(
+            polygons[0]
+                .points()
+                .iter()
+                .all(|p| p.x() >= 1.0 && circle.evaluate(*p) < 1e-6)
+        );
+        // Clipping the slice through the hole leaves a notch instead of a hole.
+        let slice = part.slice_z(0.0);
+        let mut clipped =
+            MarchingSquares::new(&Aabb::new(Vec2::new(0.0, -2.0), Vec2::new(2.0, 2.0)));
+        clipped.tolerance(0.02);
+        let polygons = clipped.build_polygons(&slice);
+        assert_eq!(polygons.len(), 1);
+        assert!((polygons[0].signed_area() - (2.0 - std::f64::consts::PI * 0.125)).abs() < 0.01);
+        Ok(())
+    }
+}
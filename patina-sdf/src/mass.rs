@@ -0,0 +1,273 @@
+use crate::octree::{OctreeIndex, OctreePath};
+use crate::sdf::bounds::padded_bounds;
+use crate::sdf::{AsSdf, Sdf3};
+use anyhow::{Context, bail, ensure};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::sphere::Sphere;
+use patina_vec::mat3::Mat3;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::Vec3;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::ops::Add;
+
+/// Boundary cells are sampled on a grid with this many points along each axis.
+const SAMPLES: usize = 4;
+/// The shallowest depth at which successive estimates are compared.
+const MIN_DEPTH: usize = 3;
+/// The deepest depth the octree is subdivided to before giving up.
+const MAX_DEPTH: usize = 9;
+
+/// The volume, surface area, centroid and inertia tensor of a solid of unit density.
+#[derive(Debug, Copy, Clone)]
+pub struct MassProperties {
+    volume: f64,
+    area: f64,
+    centroid: Vec3,
+    inertia: Mat3,
+}
+
+/// Integrals of `1`, `x` and `x xᵀ` over part of a solid, and the area of its surface.
+#[derive(Copy, Clone)]
+struct Moments {
+    volume: f64,
+    area: f64,
+    first: Vec3,
+    second: Mat3,
+}
+
+enum Cell {
+    Inside(Aabb3),
+    Outside,
+    Boundary(OctreePath, Sdf3),
+}
+
+impl Moments {
+    fn zero() -> Self {
+        Moments {
+            volume: 0.0,
+            area: 0.0,
+            first: Vec3::zero(),
+            second: Mat3::from_fn(|_, _| 0.0),
+        }
+    }
+    /// The moments of `fraction` of the volume of `aabb`, treated as spread evenly over it.
+    fn of_box(aabb: &Aabb3, fraction: f64) -> Self {
+        let center = aabb.center();
+        let size = aabb.dimensions();
+        let volume = size.x() * size.y() * size.z() * fraction;
+        Moments {
+            volume,
+            area: 0.0,
+            first: center * volume,
+            second: Mat3::from_fn(|i, j| {
+                let spread = if i == j {
+                    size[i] * size[i] / 12.0
+                } else {
+                    0.0
+                };
+                volume * (center[i] * center[j] + spread)
+            }),
+        }
+    }
+}
+
+impl Add for Moments {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Moments {
+            volume: self.volume + rhs.volume,
+            area: self.area + rhs.area,
+            first: self.first + rhs.first,
+            second: self.second + rhs.second,
+        }
+    }
+}
+
+/// Estimates the moments of the part of the solid inside a boundary cell. Each sample treats the
+/// surface as the plane given by the SDF and its gradient, and takes the fraction of its subcell
+/// below the plane and the area of the plane inside it from the width of the subcell along the
+/// normal. Both are exact on average over the position of the plane.
+fn sample_cell(sdf: &Sdf3, aabb: &Aabb3) -> Moments {
+    let step = aabb.dimensions() / (SAMPLES as f64);
+    let subcells: Vec<Aabb3> = (0..SAMPLES * SAMPLES * SAMPLES)
+        .map(|index| {
+            let offset = Vec3::new(
+                (index % SAMPLES) as f64,
+                (index / SAMPLES % SAMPLES) as f64,
+                (index / SAMPLES / SAMPLES) as f64,
+            );
+            let min = aabb.min() + offset.mul_elements(step);
+            Aabb::new(min, min + step)
+        })
+        .collect();
+    let points: Vec<Vec3> = subcells.iter().map(|cell| cell.center()).collect();
+    let mut values = vec![0.0; points.len()];
+    let mut gradients = vec![Vec3::zero(); points.len()];
+    sdf.evaluate_gradient_batch(&points, &mut values, &mut gradients);
+    let subvolume = step.x() * step.y() * step.z();
+    let mut total = Moments::zero();
+    for ((subcell, value), gradient) in subcells.iter().zip(values).zip(gradients) {
+        let length = gradient.length();
+        if length == 0.0 || !length.is_finite() {
+            if value <= 0.0 {
+                total = total + Moments::of_box(subcell, 1.0);
+            }
+            continue;
+        }
+        let normal = gradient / length;
+        let distance = value / length;
+        let width = (0..3)
+            .map(|axis| normal[axis].abs() * step[axis])
+            .sum::<f64>();
+        let fraction = (0.5 - distance / width).clamp(0.0, 1.0);
+        let mut moments = Moments::of_box(subcell, fraction);
+        if distance.abs() < width / 2.0 {
+            moments.area = subvolume / width;
+        }
+        total = total + moments;
+    }
+    total
+}
+
+/// Classifies the child of a boundary cell at `path` with interval arithmetic.
+fn classify(sdf: &Sdf3, root: &Aabb3, path: OctreePath) -> Cell {
+    let aabb = path.aabb_inside(root);
+    let p = Vector::from_fn(|axis| {
+        DecInterval::try_from((aabb.min()[axis], aabb.max()[axis])).unwrap()
+    });
+    let (simplified, range) = sdf.evaluate_constrain(p);
+    if range.is_empty() {
+        Cell::Boundary(path, sdf.clone())
+    } else if range.inf() > 0.0 {
+        Cell::Outside
+    } else if range.sup() < 0.0 {
+        Cell::Inside(aabb)
+    } else {
+        Cell::Boundary(path, simplified.unwrap_or_else(|| sdf.clone()))
+    }
+}
+
+impl MassProperties {
+    /// Integrates over an octree covering [Sdf::bounds](crate::sdf::Sdf::bounds). Cells that
+    /// interval arithmetic proves inside the solid are integrated exactly, and the remaining
+    /// boundary cells are sampled and subdivided until the volume and area change by less than
+    /// `tolerance`, relative to their size, from one depth to the next.
+    pub fn for_sdf(sdf: &Sdf3, tolerance: f64) -> anyhow::Result<Self> {
+        ensure!(tolerance > 0.0, "tolerance must be positive");
+        // Padding keeps surfaces on the sides of the bounds inside the boundary cells.
+        let root = padded_bounds(sdf, "integrate")?;
+        let mut inside = Moments::zero();
+        let mut boundary = vec![(OctreePath::new_root(), sdf.clone())];
+        let mut previous: Option<Moments> = None;
+        for depth in 0..=MAX_DEPTH {
+            let estimate = boundary
+                .par_iter()
+                .map(|(path, sdf)| sample_cell(sdf, &path.aabb_inside(&root)))
+                .reduce(Moments::zero, Moments::add)
+                + inside;
+            let converged = previous.is_some_and(|previous| {
+                (estimate.volume - previous.volume).abs() <= tolerance * estimate.volume
+                    && (estimate.area - previous.area).abs() <= tolerance * estimate.area
+            });
+            if boundary.is_empty() || (depth >= MIN_DEPTH && converged) {
+                ensure!(estimate.volume > 0.0, "cannot integrate an empty solid");
+                return Ok(Self::from_moments(&estimate));
+            }
+            previous = Some(estimate);
+            let cells: Vec<Cell> = boundary
+                .par_iter()
+                .flat_map_iter(|(path, sdf)| {
+                    (0..8).map(move |child| {
+                        let index =
+                            OctreeIndex::from([child & 1 != 0, child & 2 != 0, child & 4 != 0]);
+                        classify(sdf, &root, path.push_back(index))
+                    })
+                })
+                .collect();
+            boundary.clear();
+            for cell in cells {
+                match cell {
+                    Cell::Inside(aabb) => inside = inside + Moments::of_box(&aabb, 1.0),
+                    Cell::Outside => {}
+                    Cell::Boundary(path, sdf) => boundary.push((path, sdf)),
+                }
+            }
+        }
+        bail!("mass properties did not converge to a tolerance of {tolerance}")
+    }
+    fn from_moments(moments: &Moments) -> Self {
+        let volume = moments.volume;
+        let centroid = moments.first / volume;
+        let spread = moments.second - Mat3::from_fn(|i, j| volume * centroid[i] * centroid[j]);
+        let trace = spread[(0, 0)] + spread[(1, 1)] + spread[(2, 2)];
+        MassProperties {
+            volume,
+            area: moments.area,
+            centroid,
+            inertia: Mat3::id() * trace - spread,
+        }
+    }
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+    /// The center of mass.
+    pub fn centroid(&self) -> Vec3 {
+        self.centroid
+    }
+    /// The inertia tensor about [MassProperties::centroid] for unit density.
+    pub fn inertia(&self) -> Mat3 {
+        self.inertia
+    }
+    /// The mass of the solid when made of a material of `density`.
+    pub fn mass(&self, density: f64) -> f64 {
+        self.volume * density
+    }
+}
+
+#[test]
+fn test_mass() {
+    let close = |a: f64, b: f64, eps: f64| (a - b).abs() <= eps * b.abs().max(1.0);
+    let center = Vec3::new(1.0, 2.0, 3.0);
+    let sphere = Sphere::new(center, 2.0).as_sdf();
+    let props = MassProperties::for_sdf(&sphere, 1e-2).unwrap();
+    let volume = 4.0 / 3.0 * std::f64::consts::PI * 8.0;
+    assert!(close(props.volume(), volume, 1e-2), "{}", props.volume());
+    assert!(
+        close(props.area(), 16.0 * std::f64::consts::PI, 1e-2),
+        "{}",
+        props.area()
+    );
+    assert!((props.centroid() - center).length() < 1e-3);
+    for i in 0..3 {
+        for j in 0..3 {
+            let expected = if i == j { 0.4 * volume * 4.0 } else { 0.0 };
+            assert!(close(props.inertia()[(i, j)], expected, 2e-2));
+        }
+    }
+    assert!(close(props.mass(1.25), volume * 1.25, 1e-2));
+
+    // A hole moves the centroid of a box away from it.
+    let cube = Aabb3::new(Vec3::zero(), Vec3::new(4.0, 2.0, 1.0)).as_sdf();
+    let props = MassProperties::for_sdf(&cube, 1e-2).unwrap();
+    assert!(close(props.volume(), 8.0, 1e-3), "{}", props.volume());
+    assert!(close(props.area(), 28.0, 1e-2), "{}", props.area());
+    assert!((props.centroid() - Vec3::new(2.0, 1.0, 0.5)).length() < 1e-3);
+    assert!(close(
+        props.inertia()[(2, 2)],
+        8.0 * (16.0 + 4.0) / 12.0,
+        1e-2
+    ));
+    let holed = cube.difference(&Sphere::new(Vec3::new(1.0, 1.0, 0.5), 0.4).as_sdf());
+    let props = MassProperties::for_sdf(&holed, 1e-2).unwrap();
+    let hole = 4.0 / 3.0 * std::f64::consts::PI * 0.064;
+    assert!(close(props.volume(), 8.0 - hole, 1e-2));
+    let x = (8.0 * 2.0 - hole * 1.0) / (8.0 - hole);
+    assert!((props.centroid().x() - x).abs() < 1e-3);
+
+    assert!(MassProperties::for_sdf(&cube.invert(), 1e-2).is_err());
+}
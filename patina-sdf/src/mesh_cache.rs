@@ -0,0 +1,176 @@
+use crate::marching_mesh::MarchingMesh;
+use crate::sdf::{AsSdf, Sdf3};
+use anyhow::{Context, ensure};
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::sphere::Sphere;
+use patina_mesh::mesh::Mesh;
+use patina_mesh::mesh_triangle::MeshTriangle;
+use patina_vec::vec3::Vec3;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"PMSH";
+const VERSION: u32 = 1;
+
+/// Stores meshes on disk keyed by [MarchingMesh::cache_key], so that unchanged parts of a model
+/// are not remeshed on every run.
+#[derive(Debug, Clone)]
+pub struct MeshCache {
+    dir: PathBuf,
+}
+
+fn read_u32(r: &mut impl Read) -> anyhow::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> anyhow::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64(r: &mut impl Read) -> anyhow::Result<f64> {
+    Ok(f64::from_bits(read_u64(r)?))
+}
+
+fn read_mesh(r: &mut impl Read) -> anyhow::Result<Mesh> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    ensure!(&magic == MAGIC, "not a mesh cache file");
+    let version = read_u32(r)?;
+    ensure!(
+        version == VERSION,
+        "unsupported mesh cache version {}",
+        version
+    );
+    let vertex_count = read_u64(r)? as usize;
+    let triangle_count = read_u64(r)? as usize;
+    let mut vertices = vec![];
+    for _ in 0..vertex_count {
+        vertices.push(Vec3::new(read_f64(r)?, read_f64(r)?, read_f64(r)?));
+    }
+    let mut triangles = vec![];
+    for _ in 0..triangle_count {
+        let triangle = [read_u64(r)?, read_u64(r)?, read_u64(r)?].map(|v| v as usize);
+        ensure!(
+            triangle.iter().all(|&v| v < vertex_count),
+            "vertex index out of range"
+        );
+        triangles.push(MeshTriangle::from(triangle));
+    }
+    let mut rest = [0; 1];
+    ensure!(r.read(&mut rest)? == 0, "trailing data after mesh");
+    Ok(Mesh::new(vertices, triangles))
+}
+
+fn write_mesh(w: &mut impl Write, mesh: &Mesh) -> anyhow::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(mesh.vertices().len() as u64).to_le_bytes())?;
+    w.write_all(&(mesh.triangles().len() as u64).to_le_bytes())?;
+    for v in mesh.vertices() {
+        for x in [v.x(), v.y(), v.z()] {
+            w.write_all(&x.to_le_bytes())?;
+        }
+    }
+    for t in mesh.triangles() {
+        for v in t.vertices() {
+            w.write_all(&(v as u64).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+impl MeshCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        MeshCache { dir: dir.into() }
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.mesh", key))
+    }
+    /// Returns the mesh stored under `key`, or `None` if there is none.
+    pub fn get(&self, key: u64) -> anyhow::Result<Option<Mesh>> {
+        let path = self.path(key);
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("while opening {:?}", path)),
+        };
+        let mesh = read_mesh(&mut BufReader::new(file))
+            .with_context(|| format!("while reading {:?}", path))?;
+        Ok(Some(mesh))
+    }
+    /// Stores `mesh` under `key`. The file is written under a temporary name and then renamed,
+    /// so concurrent readers never see a partial mesh.
+    pub fn insert(&self, key: u64, mesh: &Mesh) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir).with_context(|| format!("while creating {:?}", self.dir))?;
+        let path = self.path(key);
+        let temp = self
+            .dir
+            .join(format!("{:016x}.{}.tmp", key, std::process::id()));
+        let result = (|| -> anyhow::Result<()> {
+            let mut w = BufWriter::new(fs::File::create(&temp)?);
+            write_mesh(&mut w, mesh)?;
+            w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&temp, &path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            fs::remove_file(&temp).ok();
+        }
+        result.with_context(|| format!("while saving {:?}", path))
+    }
+    /// Builds the mesh for `sdf`, or loads it if an identical build was cached. Unreadable cache
+    /// entries are rebuilt and overwritten.
+    pub fn build(&self, marching: MarchingMesh, sdf: &Sdf3) -> anyhow::Result<Mesh> {
+        let key = marching.cache_key(sdf);
+        if let Ok(Some(mesh)) = self.get(key) {
+            return Ok(mesh);
+        }
+        let mesh = marching.build(sdf)?;
+        self.insert(key, &mesh)?;
+        Ok(mesh)
+    }
+}
+
+#[test]
+fn test_mesh_cache() {
+    let dir = std::env::temp_dir().join(format!("patina-mesh-cache-{}", std::process::id()));
+    let cache = MeshCache::new(&dir);
+    let sdf = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let aabb = Aabb3::new(Vec3::splat(-2.0), Vec3::splat(2.0));
+    let marching = || {
+        let mut marching = MarchingMesh::new(&aabb);
+        marching.min_render_depth(2).max_render_depth(4);
+        marching
+    };
+    let key = marching().cache_key(&sdf);
+    assert_eq!(
+        key,
+        marching().cache_key(&Sphere::new(Vec3::zero(), 1.0).as_sdf())
+    );
+    assert_ne!(key, marching().max_render_depth(5).cache_key(&sdf));
+    assert_ne!(
+        key,
+        marching().cache_key(&Sphere::new(Vec3::zero(), 1.0 + 1e-12).as_sdf())
+    );
+
+    assert!(cache.get(key).unwrap().is_none());
+    let mesh = cache.build(marching(), &sdf).unwrap();
+    assert!(!mesh.triangles().is_empty());
+    assert_eq!(cache.get(key).unwrap().as_ref(), Some(&mesh));
+    assert_eq!(cache.build(marching(), &sdf).unwrap(), mesh);
+
+    // A corrupt entry is rebuilt.
+    fs::write(dir.join(format!("{:016x}.mesh", key)), b"PMSH").unwrap();
+    assert!(cache.get(key).is_err());
+    let rebuilt = cache.build(marching(), &sdf).unwrap();
+    assert_eq!(cache.get(key).unwrap(), Some(rebuilt));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,264 @@
+use crate::sdf::bounds::is_empty;
+use crate::sdf::{AsSdf, Sdf3};
+use anyhow::Context;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::geo3::ray3::Ray3;
+use patina_mesh::ser::Encode;
+use patina_vec::vec3::Vec3;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::f64::consts::PI;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// How far rays travel through unbounded solids, in multiples of the distance to the target.
+const FAR: f64 = 100.0;
+const AMBIENT: f64 = 0.2;
+const SURFACE_COLOR: [f64; 3] = [0.9, 0.6, 0.3];
+const BACKGROUND_COLOR: [f64; 3] = [0.15, 0.15, 0.2];
+
+/// A pinhole camera looking from `eye` towards `target`.
+#[derive(Debug, Copy, Clone)]
+pub struct Camera {
+    eye: Vec3,
+    target: Vec3,
+    up: Vec3,
+    fov: f64,
+}
+
+/// Renders previews of an [Sdf3] by sphere tracing, shaded by the angle between the surface
+/// normal and a light above and behind the camera.
+#[derive(Debug, Clone)]
+pub struct Render {
+    width: usize,
+    height: usize,
+    camera: Camera,
+}
+
+/// An 8-bit RGB image, which encodes as a PNG.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Camera {
+    /// A camera with a vertical field of view of 30 degrees, and `up` pointing towards the top of
+    /// the image.
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Camera {
+            eye,
+            target,
+            up,
+            fov: PI / 6.0,
+        }
+    }
+    /// A camera looking at the center of `aabb` from close enough that all of it is in view.
+    /// `azimuth` is the angle around the Z axis from the X axis, and `elevation` is the angle
+    /// above the XY plane.
+    pub fn framing(aabb: &Aabb3, azimuth: f64, elevation: f64) -> Self {
+        let mut camera = Camera::look_at(Vec3::zero(), aabb.center(), Vec3::axis_z());
+        let radius = aabb.dimensions().length() / 2.0;
+        let distance = radius / (camera.fov / 2.0).sin();
+        let dir = Vec3::new(
+            azimuth.cos() * elevation.cos(),
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+        );
+        camera.eye = aabb.center() + dir * distance;
+        if elevation.cos().abs() < 1e-9 {
+            camera.up = Vec3::new(-azimuth.cos(), -azimuth.sin(), 0.0) * elevation.sin().signum();
+        }
+        camera
+    }
+    /// Sets the vertical field of view in radians.
+    pub fn fov(&mut self, fov: f64) -> &mut Self {
+        self.fov = fov;
+        self
+    }
+    pub fn eye(&self) -> Vec3 {
+        self.eye
+    }
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+}
+
+/// The times at which `ray` enters and leaves `aabb`, if it does.
+fn clip(ray: &Ray3, aabb: &Aabb3) -> Option<(f64, f64)> {
+    let mut start = 0.0f64;
+    let mut end = f64::INFINITY;
+    for axis in 0..3 {
+        let t1 = (aabb.min()[axis] - ray.origin()[axis]) / ray.dir()[axis];
+        let t2 = (aabb.max()[axis] - ray.origin()[axis]) / ray.dir()[axis];
+        if t1.is_nan() || t2.is_nan() {
+            continue;
+        }
+        start = start.max(t1.min(t2));
+        end = end.min(t1.max(t2));
+    }
+    (start <= end).then_some((start, end))
+}
+
+fn to_rgb(color: Vec3) -> [u8; 3] {
+    [0, 1, 2].map(|axis| (color[axis].clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+impl Render {
+    pub fn new(width: usize, height: usize, camera: Camera) -> Self {
+        Render {
+            width,
+            height,
+            camera,
+        }
+    }
+    /// A render of the bounds of `sdf` seen from `azimuth` and `elevation`, as in
+    /// [Camera::framing].
+    pub fn for_sdf(
+        sdf: &Sdf3,
+        width: usize,
+        height: usize,
+        azimuth: f64,
+        elevation: f64,
+    ) -> anyhow::Result<Self> {
+        let bounds = sdf.bounds().context("cannot frame an unbounded solid")?;
+        anyhow::ensure!(!is_empty(&bounds), "cannot frame an empty solid");
+        Ok(Render::new(
+            width,
+            height,
+            Camera::framing(&bounds, azimuth, elevation),
+        ))
+    }
+    pub fn camera(&mut self, camera: Camera) -> &mut Self {
+        self.camera = camera;
+        self
+    }
+    /// The ray from the eye through the center of the pixel at `x`, `y`, with a unit direction.
+    fn pixel_ray(&self, x: usize, y: usize) -> Ray3 {
+        let camera = &self.camera;
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        let scale = (camera.fov / 2.0).tan();
+        let u = (2.0 * (x as f64 + 0.5) / self.width as f64 - 1.0) * scale * self.width as f64
+            / self.height as f64;
+        let v = (1.0 - 2.0 * (y as f64 + 0.5) / self.height as f64) * scale;
+        Ray3::new(camera.eye, (forward + right * u + up * v).normalize())
+    }
+    fn shade(&self, sdf: &Sdf3, bounds: Option<&Aabb3>, ray: &Ray3) -> Vec3 {
+        let (start, end) = match bounds {
+            Some(bounds) => match clip(ray, bounds) {
+                Some(range) => range,
+                None => return Vec3::from(BACKGROUND_COLOR),
+            },
+            None => (0.0, FAR * (self.camera.target - self.camera.eye).length()),
+        };
+        let clipped = Ray3::new(ray.at_time(start), ray.dir());
+        let Some(t) = sdf.raycast(&clipped, end - start) else {
+            return Vec3::from(BACKGROUND_COLOR);
+        };
+        let mut normal = sdf.normal(clipped.at_time(t));
+        if !normal.length().is_finite() {
+            normal = -ray.dir();
+        }
+        let forward = (self.camera.target - self.camera.eye).normalize();
+        let right = forward.cross(self.camera.up).normalize();
+        let light = (-forward + right.cross(forward) * 0.5 - right * 0.5).normalize();
+        Vec3::from(SURFACE_COLOR) * (AMBIENT + (1.0 - AMBIENT) * normal.dot(light).max(0.0))
+    }
+    pub fn render(&self, sdf: &Sdf3) -> Image {
+        let bounds = sdf.bounds();
+        let pixels = (0..self.height)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                let bounds = bounds.as_ref();
+                (0..self.width).map(move |x| to_rgb(self.shade(sdf, bounds, &self.pixel_ray(x, y))))
+            })
+            .collect();
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+impl Image {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+impl Encode for Image {
+    fn extension() -> &'static str {
+        "png"
+    }
+
+    fn encode<W: Unpin + Send + AsyncWrite>(
+        &self,
+        w: &mut W,
+    ) -> impl Send + Future<Output = anyhow::Result<()>> {
+        async move {
+            let bytes = self.to_png()?;
+            w.write_all(&bytes).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use patina_geo::sphere::Sphere;
+    use patina_mesh::ser::encode_test_file;
+
+    #[tokio::test]
+    async fn test_render() -> anyhow::Result<()> {
+        let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 1.0).as_sdf();
+        let image = Render::new(
+            32,
+            24,
+            Camera::look_at(
+                Vec3::new(1.0, -3.0, 3.0),
+                Vec3::new(1.0, 2.0, 3.0),
+                Vec3::axis_z(),
+            ),
+        )
+        .render(&sphere);
+        let background = to_rgb(Vec3::from(BACKGROUND_COLOR));
+        assert_eq!(image.pixel(0, 0), background);
+        let center = image.pixel(16, 12);
+        assert_ne!(center, background);
+        // The light is above the camera, so the top of the sphere is brighter than the bottom.
+        assert!(image.pixel(16, 8)[0] > image.pixel(16, 16)[0]);
+        assert_eq!(&image.to_png()?[..4], b"\x89PNG");
+        encode_test_file(&image, "sphere.png").await?;
+
+        let part = Aabb3::new(Vec3::splat(-1.0), Vec3::splat(1.0))
+            .as_sdf()
+            .difference(&Sphere::new(Vec3::new(1.0, 1.0, 1.0), 1.0).as_sdf());
+        for (index, elevation) in [-PI / 2.0, 0.0, PI / 6.0, PI / 2.0].into_iter().enumerate() {
+            let image = Render::for_sdf(&part, 32, 32, PI / 4.0, elevation)?.render(&part);
+            assert_ne!(image.pixel(16, 16), background);
+            encode_test_file(&image, &format!("part{index}.png")).await?;
+        }
+        assert!(Render::for_sdf(&part.invert(), 32, 32, 0.0, 0.0).is_err());
+        Ok(())
+    }
+}
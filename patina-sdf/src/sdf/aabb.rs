@@ -1,5 +1,6 @@
 use crate::sdf::{AsSdf, Sdf};
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use patina_geo::aabb::Aabb;
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
@@ -40,4 +41,13 @@ impl SdfLeafImpl<3> for Aabb<3> {
             ),
         )
     }
+    fn bounds(&self) -> Option<Aabb<3>> {
+        Some(*self)
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Aabb {
+            min: self.min().into_iter().collect(),
+            max: self.max().into_iter().collect(),
+        }
+    }
 }
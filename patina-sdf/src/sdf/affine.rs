@@ -0,0 +1,129 @@
+use crate::sdf::bounds::map_corners;
+use crate::sdf::node::SdfNode;
+use crate::sdf::transform::{Transform, TransformImpl};
+use crate::sdf::{AsSdf, Sdf, Sdf3};
+use inari::DecInterval;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::geo3::plane::Plane;
+use patina_scalar::Scalar;
+use patina_vec::mat3::Mat3;
+use patina_vec::mat4::Mat4;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+use std::f64::consts::PI;
+
+/// An invertible affine map applied to a 3D SDF. Points are pulled back through the inverse map,
+/// and the result is scaled by the smallest singular value of the forward map so that stretching
+/// never overestimates the distance.
+#[derive(Debug, Clone)]
+pub struct Affine {
+    forward: Mat4,
+    inverse_linear: Mat3,
+    inverse_translation: Vec3,
+    lipschitz: f64,
+}
+
+impl Affine {
+    pub fn new(forward: Mat4) -> Self {
+        assert!(
+            forward.as_affine().is_some(),
+            "transform must be affine: {:?}",
+            forward
+        );
+        let linear = forward.linear();
+        assert!(linear.determinant() != 0.0, "transform must be invertible");
+        let inverse_linear = linear.invert3();
+        let inverse_translation = -(inverse_linear * forward.translation());
+        let [_, _, min_eigenvalue] = (linear.transpose() * linear).symmetric_eigenvalues();
+        Affine {
+            forward,
+            inverse_linear,
+            inverse_translation,
+            lipschitz: min_eigenvalue.max(0.0).sqrt(),
+        }
+    }
+    pub fn forward(&self) -> &Mat4 {
+        &self.forward
+    }
+}
+
+impl TransformImpl<3, 3> for Affine {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, 3>, inner: impl FnOnce(Vector<T, 3>) -> T) -> T {
+        let q = Vector3::from_fn(|axis| {
+            self.inverse_linear
+                .row(axis)
+                .into_scalars::<T>()
+                .dot(p.clone())
+                + T::from_f64(self.inverse_translation[axis])
+        });
+        inner(q) * T::from_f64(self.lipschitz)
+    }
+    fn bounds(&self, inner: &Aabb3) -> Option<Aabb3> {
+        let linear = self.forward.linear();
+        let translation = self.forward.translation();
+        Some(map_corners(inner, |p| linear * p + translation))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Affine {
+            inner: Box::new(inner),
+            forward: std::array::from_fn(|row| self.forward.row(row).into_inner()),
+        }
+    }
+}
+
+impl Sdf3 {
+    /// Applies an affine transform, mapping the coordinates of this SDF to the coordinates of the
+    /// result. Consecutive transforms are merged into a single node.
+    pub fn transform(&self, m: Mat4) -> Sdf3 {
+        if let Some(inner) = self.downcast_ref::<Transform<3, 3, Affine>>() {
+            return inner
+                .inner()
+                .transform(m * *inner.transform_impl().forward());
+        }
+        Sdf::new(Transform::new(Affine::new(m), self.clone()))
+    }
+    pub fn translate(&self, v: Vec3) -> Sdf3 {
+        self.transform(Mat4::translate(v))
+    }
+    /// Rotates by `angle` radians around `axis` through the origin.
+    pub fn rotate_axis(&self, axis: Vec3, angle: f64) -> Sdf3 {
+        self.transform(Mat4::rotate(axis.normalize(), angle))
+    }
+    /// Scales each axis independently around the origin.
+    pub fn scale(&self, scale: Vec3) -> Sdf3 {
+        self.transform(Mat4::scale(scale))
+    }
+    /// Reflects across a plane.
+    pub fn mirror(&self, plane: &Plane) -> Sdf3 {
+        let n = plane.normal();
+        let reflect = Mat3::from_fn(|r, c| (if r == c { 1.0 } else { 0.0 }) - 2.0 * n[r] * n[c]);
+        self.translate(-plane.origin())
+            .transform(Mat4::from_mat3(reflect))
+            .translate(plane.origin())
+    }
+}
+
+#[test]
+fn test_affine() {
+    let cube = Aabb3::new(Vec3::splat(-1.0), Vec3::splat(1.0)).as_sdf();
+    let moved = cube
+        .rotate_axis(Vec3::axis_z(), PI / 4.0)
+        .translate(Vec3::new(10.0, 0.0, 0.0));
+    assert_eq!(moved.complexity(), 2);
+    let corner = Vec3::new(10.0 + 2.0f64.sqrt(), 0.0, 0.0);
+    assert!(moved.evaluate(corner).abs() < 1e-12);
+    let stretched = cube.scale(Vec3::new(1.0, 1.0, 4.0));
+    assert!((stretched.evaluate(Vec3::new(0.0, 0.0, 6.0)) - 0.5).abs() < 1e-12);
+    assert!((stretched.evaluate(Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-12);
+    let mirrored = cube
+        .translate(Vec3::new(3.0, 0.0, 0.0))
+        .mirror(&Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::axis_x()));
+    assert!(mirrored.evaluate(Vec3::new(-1.0, 0.0, 0.0)) < 0.0);
+    let region = Vector3::new(
+        DecInterval::try_from((5.0, 6.0)).unwrap(),
+        DecInterval::try_from((5.0, 6.0)).unwrap(),
+        DecInterval::try_from((5.0, 6.0)).unwrap(),
+    );
+    let (_, range) = moved.evaluate_constrain(region);
+    assert!(!range.contains(0.0));
+}
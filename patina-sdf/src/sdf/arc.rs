@@ -0,0 +1,98 @@
+use crate::sdf::bounds::expand;
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_scalar::Scalar;
+use patina_vec::vec2::{Vec2, Vector2};
+use std::f64::consts::PI;
+use std::ops::Range;
+
+/// A circular arc of the given radius around `center` from `angle_range.start` to
+/// `angle_range.end` (counterclockwise from +x), stroked with the given width and round ends.
+#[derive(Debug, Clone)]
+pub struct CircularArc {
+    center: Vec2,
+    radius: f64,
+    angle_range: Range<f64>,
+    width: f64,
+    middle: Vec2,
+    ends: [Vec2; 2],
+}
+
+impl CircularArc {
+    pub fn new(center: Vec2, radius: f64, angle_range: Range<f64>, width: f64) -> Self {
+        assert!(
+            angle_range.start <= angle_range.end && angle_range.end - angle_range.start <= 2.0 * PI,
+            "arc must span between zero and one full turn"
+        );
+        let direction = |angle: f64| Vec2::new(angle.cos(), angle.sin());
+        CircularArc {
+            center,
+            radius,
+            middle: direction((angle_range.start + angle_range.end) / 2.0),
+            ends: [
+                center + direction(angle_range.start) * radius,
+                center + direction(angle_range.end) * radius,
+            ],
+            angle_range,
+            width,
+        }
+    }
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+    pub fn angle_range(&self) -> &Range<f64> {
+        &self.angle_range
+    }
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+}
+
+impl SdfLeafImpl<2> for CircularArc {
+    fn evaluate<T: Scalar>(&self, p: Vector2<T>) -> T {
+        let rel = p.clone() - self.center.into_scalars();
+        let rho = rel.clone().length();
+        // Positive outside the sector spanned by the arc, where an end is the nearest point.
+        let half_angle = (self.angle_range.end - self.angle_range.start) / 2.0;
+        let outside =
+            rho.clone() * T::from_f64(half_angle.cos()) - rel.dot(self.middle.into_scalars());
+        let ring = (rho - T::from_f64(self.radius)).abs();
+        let [start, end] = self.ends.map(|e| (p.clone() - e.into_scalars()).length());
+        outside.piecewise(ring, start.minimum(end)) - T::from_f64(self.width / 2.0)
+    }
+    fn bounds(&self) -> Option<Aabb<2>> {
+        Some(expand(
+            &Aabb::from_point(self.center),
+            self.radius + self.width / 2.0,
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Arc {
+            center: self.center,
+            radius: self.radius,
+            angle_range: self.angle_range.clone(),
+            width: self.width,
+        }
+    }
+}
+
+impl AsSdf<2> for CircularArc {
+    fn as_sdf(&self) -> Sdf<2> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+#[test]
+fn test_arc() {
+    let arc = CircularArc::new(Vec2::zero(), 5.0, 0.0..PI / 2.0, 2.0).as_sdf();
+    assert!((arc.evaluate(Vec2::new(0.0, 5.0)) + 1.0).abs() < 1e-12);
+    assert!((arc.evaluate(Vec2::new(3.0, 3.0)) - (5.0 - 18.0f64.sqrt() - 1.0)).abs() < 1e-12);
+    assert_eq!(arc.evaluate(Vec2::new(5.0, -3.0)), 2.0);
+    assert!((arc.evaluate(Vec2::new(-4.0, 5.0)) - 3.0).abs() < 1e-12);
+    assert_eq!(arc.evaluate(Vec2::zero()), 4.0);
+}
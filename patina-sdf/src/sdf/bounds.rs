@@ -0,0 +1,204 @@
+use crate::marching_mesh::MarchingMesh;
+use crate::sdf::{AsSdf, Sdf};
+use anyhow::{Context, ensure};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::geo3::plane::Plane;
+use patina_geo::sphere::{Circle, Sphere};
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+use std::ops::Range;
+
+/// How many times [Sdf::bounds] subdivides the structural bounds.
+const REFINE_DEPTH: usize = 5;
+
+pub(crate) fn is_empty<const N: usize>(aabb: &Aabb<N>) -> bool {
+    (0..N).any(|axis| aabb.min()[axis] > aabb.max()[axis])
+}
+
+pub(crate) fn corners<const N: usize>(aabb: &Aabb<N>) -> impl Iterator<Item = Vector<f64, N>> {
+    (0..1 << N).map(|mask| {
+        Vector::from_fn(|axis| {
+            if mask & (1 << axis) != 0 {
+                aabb.max()[axis]
+            } else {
+                aabb.min()[axis]
+            }
+        })
+    })
+}
+
+/// The bounds of the corners of `aabb` mapped through `f`. This bounds the image of the whole box
+/// when `f` is affine.
+pub(crate) fn map_corners<const N: usize, const M: usize>(
+    aabb: &Aabb<N>,
+    f: impl Fn(Vector<f64, N>) -> Vector<f64, M>,
+) -> Aabb<M> {
+    if is_empty(aabb) {
+        return Aabb::empty();
+    }
+    corners(aabb).map(f).collect()
+}
+
+/// Moves every side of `aabb` outwards by `distance`.
+pub(crate) fn expand<const N: usize>(aabb: &Aabb<N>, distance: f64) -> Aabb<N> {
+    Aabb::new(
+        aabb.min() - Vector::splat(distance),
+        aabb.max() + Vector::splat(distance),
+    )
+}
+
+/// The [bounds](Sdf::bounds) of `sdf` padded by a 32nd of their largest dimension, so that the
+/// surface stays clear of the edges of a grid covering them. `action` names the operation in the
+/// errors for unbounded and empty solids.
+pub(crate) fn padded_bounds<const N: usize>(sdf: &Sdf<N>, action: &str) -> anyhow::Result<Aabb<N>> {
+    let bounds = sdf
+        .bounds()
+        .with_context(|| format!("cannot {action} an unbounded solid"))?;
+    ensure!(!is_empty(&bounds), "cannot {action} an empty solid");
+    Ok(expand(
+        &bounds,
+        bounds.dimensions().into_iter().fold(0.0, f64::max) / 32.0,
+    ))
+}
+
+/// The bounds of the solid cylinder of `radius` around the unit vector `axis` through `origin`,
+/// between `heights` along `axis`.
+pub(crate) fn cylinder_bounds(origin: Vec3, axis: Vec3, radius: f64, heights: Range<f64>) -> Aabb3 {
+    if radius < 0.0 || heights.start > heights.end {
+        return Aabb::empty();
+    }
+    let ends: Aabb3 = [origin + axis * heights.start, origin + axis * heights.end]
+        .into_iter()
+        .collect();
+    let disk = Vec3::from_fn(|k| radius * (1.0 - axis[k] * axis[k]).max(0.0).sqrt());
+    Aabb::new(ends.min() - disk, ends.max() + disk)
+}
+
+/// The largest distance of the nonempty `aabb` from the unit vector `axis` through `origin`, and
+/// its range of heights along `axis`.
+pub(crate) fn axial_extent(origin: Vec3, axis: Vec3, aabb: &Aabb3) -> (f64, Range<f64>) {
+    let mut radius = 0.0f64;
+    let mut heights = f64::INFINITY..-f64::INFINITY;
+    for corner in corners(aabb) {
+        let rel = corner - origin;
+        let h = rel.dot(axis);
+        radius = radius.max((rel - axis * h).length());
+        heights = heights.start.min(h)..heights.end.max(h);
+    }
+    (radius, heights)
+}
+
+/// The bounds of every rotation of `aabb` around the unit vector `axis` through `origin`.
+pub(crate) fn revolved_bounds(origin: Vec3, axis: Vec3, aabb: &Aabb3) -> Aabb3 {
+    if is_empty(aabb) {
+        return Aabb::empty();
+    }
+    let (radius, heights) = axial_extent(origin, axis, aabb);
+    cylinder_bounds(origin, axis, radius, heights)
+}
+
+/// Adds to `bounds` the cells of a regular subdivision of `cell` that interval arithmetic cannot
+/// rule out. Cells that cannot grow `bounds` are skipped.
+fn refine<const N: usize>(sdf: &Sdf<N>, cell: &Aabb<N>, depth: usize, bounds: &mut Aabb<N>) {
+    if (0..N).all(|axis| {
+        bounds.min()[axis] <= cell.min()[axis] && cell.max()[axis] <= bounds.max()[axis]
+    }) {
+        return;
+    }
+    let p = Vector::from_fn(|axis| {
+        DecInterval::try_from((cell.min()[axis], cell.max()[axis])).unwrap()
+    });
+    let (simplified, range) = sdf.evaluate_constrain(p);
+    if !range.is_empty() && range.inf() > 0.0 {
+        return;
+    }
+    if depth == 0 || range.sup() <= 0.0 {
+        *bounds = bounds.union(cell);
+        return;
+    }
+    let sdf = simplified.as_ref().unwrap_or(sdf);
+    let center = cell.center();
+    for corner in corners(cell) {
+        let child = [corner, center].into_iter().collect::<Aabb<N>>();
+        refine(sdf, &child, depth - 1, bounds);
+    }
+}
+
+impl<const N: usize> Sdf<N> {
+    /// A box containing the solid, or `None` if it is unbounded. This starts from
+    /// [Sdf::structural_bounds] and drops the parts of it where
+    /// [evaluate_constrain](Sdf::evaluate_constrain) proves the SDF is positive, so it is tight to
+    /// within a small fraction of the structural bounds.
+    pub fn bounds(&self) -> Option<Aabb<N>> {
+        let bounds = self.structural_bounds()?;
+        if is_empty(&bounds) {
+            return Some(bounds);
+        }
+        let mut refined = Aabb::empty();
+        refine(self, &bounds, REFINE_DEPTH, &mut refined);
+        Some(refined)
+    }
+}
+
+#[test]
+fn test_bounds() {
+    let close = |a: Aabb3, b: Aabb3, eps: f64| {
+        (a.min() - b.min()).length() < eps && (a.max() - b.max()).length() < eps
+    };
+    let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 1.0).as_sdf();
+    let expected = Aabb::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(2.0, 3.0, 4.0));
+    assert!(close(sphere.structural_bounds().unwrap(), expected, 1e-12));
+    assert!(close(sphere.bounds().unwrap(), expected, 1e-12));
+
+    // A half space does not bound an intersection, but refinement finds the cut.
+    let cube = Aabb3::new(Vec3::splat(-1.0), Vec3::splat(1.0)).as_sdf();
+    let half = cube.intersection(&Plane::new(Vec3::zero(), Vec3::axis_z()).as_sdf());
+    let bounds = half.structural_bounds().unwrap();
+    assert!(close(
+        bounds,
+        Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0)),
+        1e-12
+    ));
+    let bounds = half.bounds().unwrap();
+    assert!(bounds.max().z() < 0.1 && bounds.max().z() >= 0.0);
+    assert!(bounds.min().x() <= -1.0 && bounds.max().x() >= 1.0);
+
+    // Refinement can only shrink the structural bounds, and never clips the solid.
+    let lens = sphere.intersection(&Sphere::new(Vec3::new(2.0, 2.0, 3.0), 1.0).as_sdf());
+    let bounds = lens.bounds().unwrap();
+    assert!(bounds.min().x() <= 1.0 && bounds.max().x() >= 1.0);
+    assert!(bounds.max().y() - bounds.min().y() < 2.0);
+    assert!(bounds.max().y() >= 2.0 + 0.75f64.sqrt());
+
+    let ring = Circle::new(Vec2::new(3.0, 0.0), 0.5)
+        .as_sdf()
+        .rotate(Vec3::zero(), Vec3::axis_z())
+        .translate(Vec3::new(0.0, 0.0, 1.0));
+    assert!(close(
+        ring.structural_bounds().unwrap(),
+        Aabb::new(Vec3::new(-3.5, -3.5, 0.5), Vec3::new(3.5, 3.5, 1.5)),
+        1e-9
+    ));
+    assert_eq!(cube.invert().bounds().map(|_| ()), None);
+    assert!(MarchingMesh::for_sdf(&cube.invert()).is_err());
+    assert!(is_empty(&Sdf::<3>::empty().bounds().unwrap()));
+    assert!(is_empty(
+        &cube
+            .intersection(&sphere.translate(Vec3::splat(5.0)))
+            .bounds()
+            .unwrap()
+    ));
+
+    let mut marching = MarchingMesh::for_sdf(&ring).unwrap();
+    marching.min_render_depth(3).max_render_depth(5);
+    let mesh = marching.build(&ring).unwrap();
+    assert!(!mesh.triangles().is_empty());
+    assert!(
+        mesh.vertices()
+            .iter()
+            .all(|v| ring.evaluate(*v).abs() < 0.1)
+    );
+}
@@ -0,0 +1,84 @@
+use crate::sdf::bounds::expand;
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_scalar::Scalar;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+
+/// The points within `radius` of the segment from `start` to `end`.
+#[derive(Debug, Clone)]
+pub struct NCapsule<const N: usize> {
+    start: Vector<f64, N>,
+    end: Vector<f64, N>,
+    radius: f64,
+}
+
+pub type Capsule = NCapsule<3>;
+/// A rectangle with semicircular ends.
+pub type Slot = NCapsule<2>;
+
+impl<const N: usize> NCapsule<N> {
+    pub fn new(start: Vector<f64, N>, end: Vector<f64, N>, radius: f64) -> Self {
+        NCapsule { start, end, radius }
+    }
+    pub fn start(&self) -> Vector<f64, N> {
+        self.start
+    }
+    pub fn end(&self) -> Vector<f64, N> {
+        self.end
+    }
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+impl<const N: usize> SdfLeafImpl<N> for NCapsule<N> {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
+        let axis = self.end - self.start;
+        let rel = p - self.start.into_scalars();
+        let length2 = axis.length_squared();
+        let disp = if length2 == 0.0 {
+            rel
+        } else {
+            let t = (rel.clone().dot(axis.into_scalars()) / T::from_f64(length2))
+                .maximum(T::from_f64(0.0))
+                .minimum(T::from_f64(1.0));
+            rel - axis.into_scalars() * t
+        };
+        disp.length() - T::from_f64(self.radius)
+    }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        Some(expand(
+            &[self.start, self.end].into_iter().collect(),
+            self.radius,
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Capsule {
+            start: self.start.into_iter().collect(),
+            end: self.end.into_iter().collect(),
+            radius: self.radius,
+        }
+    }
+}
+
+impl<const N: usize> AsSdf<N> for NCapsule<N> {
+    fn as_sdf(&self) -> Sdf<N> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+#[test]
+fn test_capsule() {
+    let capsule = Capsule::new(Vec3::zero(), Vec3::new(0.0, 0.0, 10.0), 2.0).as_sdf();
+    assert_eq!(capsule.evaluate(Vec3::new(0.0, 0.0, 5.0)), -2.0);
+    assert_eq!(capsule.evaluate(Vec3::new(3.0, 0.0, 7.0)), 1.0);
+    assert_eq!(capsule.evaluate(Vec3::new(0.0, 0.0, 13.0)), 1.0);
+    assert_eq!(capsule.evaluate(Vec3::new(0.0, 0.0, -1.0)), -1.0);
+    let slot = Slot::new(Vec2::new(-3.0, 0.0), Vec2::new(3.0, 0.0), 1.0).as_sdf();
+    assert_eq!(slot.evaluate(Vec2::new(2.0, 0.5)), -0.5);
+    assert_eq!(slot.evaluate(Vec2::new(-7.0, 3.0)), 4.0);
+}
@@ -1,16 +1,26 @@
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use crate::sdf::rotate::Rotate;
 use crate::sdf::transform::Transform;
 use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
 use patina_geo::geo3::cylinder::Cylinder;
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
 
 #[derive(Debug)]
-struct CylinderCrossSection {
+pub(crate) struct CylinderCrossSection {
     radius: f64,
     height: f64,
 }
+
+impl CylinderCrossSection {
+    pub(crate) fn new(radius: f64, height: f64) -> Self {
+        CylinderCrossSection { radius, height }
+    }
+}
+
 impl SdfLeafImpl<2> for CylinderCrossSection {
     fn evaluate<T: Scalar>(&self, p: Vector<T, 2>) -> T {
         let radial = p.x() - T::from_f64(self.radius);
@@ -29,16 +39,29 @@ impl SdfLeafImpl<2> for CylinderCrossSection {
             ),
         )
     }
+    /// Only the half with a nonnegative radius is revolved.
+    fn bounds(&self) -> Option<Aabb<2>> {
+        Some(Aabb::new(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(self.radius, self.height),
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::CylinderSection {
+            radius: self.radius,
+            height: self.height,
+        }
+    }
 }
 
 impl AsSdf<3> for Cylinder {
     fn as_sdf(&self) -> Sdf<3> {
         Sdf::new(Transform::new(
             Rotate::new(self.origin(), self.axis()),
-            Sdf::new(SdfLeaf::new(CylinderCrossSection {
-                radius: self.radius(),
-                height: self.axis().length(),
-            })),
+            Sdf::new(SdfLeaf::new(CylinderCrossSection::new(
+                self.radius(),
+                self.axis().length(),
+            ))),
         ))
     }
 }
@@ -0,0 +1,355 @@
+use crate::sdf::bounds::{axial_extent, cylinder_bounds, is_empty, map_corners, revolved_bounds};
+use crate::sdf::node::SdfNode;
+use crate::sdf::transform::{Transform, TransformImpl};
+use crate::sdf::{AsSdf, Sdf, Sdf3};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+use std::f64::consts::PI;
+use std::ops::Range;
+
+/// The bounds of the nonempty solid, or `None` if it is unbounded or empty.
+fn solid_bounds(inner: &Sdf3) -> Option<Aabb3> {
+    inner.structural_bounds().filter(|aabb| !is_empty(aabb))
+}
+
+/// Rotates each cross section perpendicular to `axis` by an angle proportional to its height
+/// along `axis`. The result is divided by the largest singular value of the warp, which grows
+/// with the distance from the axis. The segment from a point to the nearest point of the solid
+/// stays within the larger of their distances from the axis, so the singular value is taken
+/// there rather than at the point alone. The solid must be bounded, since far from the axis the
+/// warp has no bound.
+#[derive(Debug, Clone)]
+pub struct Twist {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    rate: f64,
+    /// The largest distance of the inner solid from the axis, or zero if it is empty.
+    radius: f64,
+}
+
+impl Twist {
+    pub fn new(axis: Vec3, radians_per_mm: f64, inner: &Sdf3) -> Self {
+        assert!(
+            inner.structural_bounds().is_some(),
+            "a twisted solid must be bounded"
+        );
+        let w = axis.normalize();
+        let u = w.perpendicular();
+        let radius = solid_bounds(inner).map_or(0.0, |aabb| axial_extent(Vec3::zero(), w, &aabb).0);
+        Twist {
+            u,
+            v: w.cross(u),
+            w,
+            rate: radians_per_mm,
+            radius,
+        }
+    }
+}
+
+impl TransformImpl<3, 3> for Twist {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, 3>, inner: impl FnOnce(Vector<T, 3>) -> T) -> T {
+        let pu = p.clone().dot(self.u.into_scalars());
+        let pv = p.clone().dot(self.v.into_scalars());
+        let pw = p.dot(self.w.into_scalars());
+        let angle = pw.clone() * T::from_f64(self.rate);
+        let (sin, cos) = (angle.clone().sin(), angle.cos());
+        let q = self.u.into_scalars() * (pu.clone() * cos.clone() + pv.clone() * sin.clone())
+            + self.v.into_scalars() * (pv.clone() * cos - pu.clone() * sin)
+            + self.w.into_scalars() * pw;
+        // The warp is a rotation composed with a shear of magnitude `rate * radius`.
+        let shear = (pu.clone() * pu + pv.clone() * pv)
+            .sqrt()
+            .maximum(T::from_f64(self.radius))
+            * T::from_f64(self.rate.abs());
+        let lipschitz =
+            (shear.clone() + (shear.clone() * shear + T::from_f64(4.0)).sqrt()) / T::from_f64(2.0);
+        inner(q) / lipschitz
+    }
+    fn bounds(&self, inner: &Aabb3) -> Option<Aabb3> {
+        Some(revolved_bounds(Vec3::zero(), self.w, inner))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Twist {
+            inner: Box::new(inner),
+            axis: self.w,
+            rate: self.rate,
+        }
+    }
+}
+
+/// Bends the line through the origin along `axis` into a circular arc of the given radius,
+/// curving towards [Vec3::perpendicular] of `axis` (or away from it if `radius` is negative).
+/// Arc length along the bent solid equals height along `axis` in the original solid, so the
+/// original solid should stay within half a turn of the origin and must not reach the center of
+/// the bend. Arcs closer to the center are stretched the most, so the result is scaled by the
+/// stretch at the innermost arc of the solid (or of the point, if that is closer).
+#[derive(Debug, Clone)]
+pub struct Bend {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    radius: f64,
+    /// The smallest distance of the bent solid from the center, at most `radius`.
+    inner_radius: f64,
+}
+
+impl Bend {
+    pub fn new(axis: Vec3, radius: f64, inner: &Sdf3) -> Self {
+        assert!(radius != 0.0, "bend radius must be nonzero");
+        let w = axis.normalize();
+        let u = w.perpendicular() * radius.signum();
+        let inner_radius = Self::inner_radius(axis, radius, inner);
+        let radius = radius.abs();
+        assert!(
+            inner_radius > 0.0,
+            "the solid must not reach the center of the bend"
+        );
+        Bend {
+            u,
+            v: w.cross(u),
+            w,
+            radius,
+            inner_radius: inner_radius.min(radius),
+        }
+    }
+    /// How far the solid stays from the center of the bend, which must be positive.
+    pub(crate) fn inner_radius(axis: Vec3, radius: f64, inner: &Sdf3) -> f64 {
+        let u = axis.normalize().perpendicular() * radius.signum();
+        solid_bounds(inner).map_or(radius.abs(), |aabb| {
+            radius.abs()
+                - map_corners(&aabb, |p| Vec3::new(p.dot(u), 0.0, 0.0))
+                    .max()
+                    .x()
+        })
+    }
+}
+
+impl TransformImpl<3, 3> for Bend {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, 3>, inner: impl FnOnce(Vector<T, 3>) -> T) -> T {
+        let radius = T::from_f64(self.radius);
+        let x = radius.clone() - p.clone().dot(self.u.into_scalars());
+        let y = p.clone().dot(self.w.into_scalars());
+        let rho = (x.clone() * x.clone() + y.clone() * y.clone()).sqrt();
+        // The angle is undefined at the center, and the derivatives there would be NaN.
+        let center = rho.clone() - T::from_f64(f64::MIN_POSITIVE);
+        let x = center.clone().piecewise(T::from_f64(1.0), x);
+        let rho = center.piecewise(T::from_f64(0.0), rho);
+        let q = self.u.into_scalars() * (radius.clone() - rho.clone())
+            + self.v.into_scalars() * p.dot(self.v.into_scalars())
+            + self.w.into_scalars() * (y.atan2(x) * radius.clone());
+        // Arcs closer to the center than `radius` are stretched by `radius / rho`.
+        inner(q) * rho.minimum(T::from_f64(self.inner_radius)) / radius
+    }
+    /// The inner box is bent into part of an annulus, whose bounds are found from its corners and
+    /// the extreme points of its arcs.
+    fn bounds(&self, inner: &Aabb3) -> Option<Aabb3> {
+        let q = map_corners(inner, |p| {
+            Vec3::new(p.dot(self.u), p.dot(self.v), p.dot(self.w))
+        });
+        // Only points with a nonnegative distance from the center and an angle within half a turn
+        // are ever pulled back.
+        let rho = [
+            (self.radius - q.max().x()).max(0.0),
+            self.radius - q.min().x(),
+        ];
+        let angles = (q.min().z() / self.radius).max(-PI)..(q.max().z() / self.radius).min(PI);
+        if rho[1] < 0.0 || angles.start > angles.end {
+            return Some(Aabb::empty());
+        }
+        let arc: Aabb3 = [angles.start, angles.end, -PI / 2.0, 0.0, PI / 2.0]
+            .into_iter()
+            .filter(|angle| angles.contains(angle) || *angle == angles.end)
+            .flat_map(|angle| {
+                rho.map(|rho| Vec3::new(self.radius - rho * angle.cos(), 0.0, rho * angle.sin()))
+            })
+            .collect();
+        let local = Aabb::new(
+            Vec3::new(arc.min().x(), q.min().y(), arc.min().z()),
+            Vec3::new(arc.max().x(), q.max().y(), arc.max().z()),
+        );
+        Some(map_corners(&local, |p| {
+            self.u * p.x() + self.v * p.y() + self.w * p.z()
+        }))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Bend {
+            inner: Box::new(inner),
+            axis: self.w,
+            radius: self.radius * self.u.dot(self.w.perpendicular()).signum(),
+        }
+    }
+}
+
+/// Scales each cross section perpendicular to `axis` around the axis. The scale interpolates
+/// linearly from `scale_range.start` at the origin to `scale_range.end` at the tip of `axis`, and
+/// is constant beyond either end. The result is divided by the largest singular value of the warp
+/// over the cylinder spanned by the point and the tapered solid, which contains the segment to
+/// the nearest point of the solid. The solid must be bounded, for the same reason as in [Twist].
+#[derive(Debug, Clone)]
+pub struct Taper {
+    w: Vec3,
+    length: f64,
+    scale_range: Range<f64>,
+    /// The largest distance of the tapered solid from the axis, or zero if it is empty.
+    radius: f64,
+    /// The heights of the solid along the axis, or an empty range if it is empty.
+    heights: Range<f64>,
+}
+
+impl Taper {
+    pub fn new(axis: Vec3, scale_range: Range<f64>, inner: &Sdf3) -> Self {
+        assert!(
+            scale_range.start > 0.0 && scale_range.end > 0.0,
+            "taper scales must be positive"
+        );
+        let length = axis.length();
+        assert!(length > 0.0, "taper axis must be nonzero");
+        assert!(
+            inner.structural_bounds().is_some(),
+            "a tapered solid must be bounded"
+        );
+        let w = axis / length;
+        let (radius, heights) = solid_bounds(inner)
+            .map_or((0.0, f64::INFINITY..-f64::INFINITY), |aabb| {
+                axial_extent(Vec3::zero(), w, &aabb)
+            });
+        Taper {
+            w,
+            length,
+            radius: radius * scale_range.start.max(scale_range.end),
+            heights,
+            scale_range,
+        }
+    }
+    fn scale<T: Scalar>(&self, h: T) -> T {
+        let Range { start, end } = self.scale_range;
+        let t = (h / T::from_f64(self.length))
+            .maximum(T::from_f64(0.0))
+            .minimum(T::from_f64(1.0));
+        T::from_f64(start) + t * T::from_f64(end - start)
+    }
+}
+
+impl TransformImpl<3, 3> for Taper {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, 3>, inner: impl FnOnce(Vector<T, 3>) -> T) -> T {
+        let Range { start, end } = self.scale_range;
+        let h = p.clone().dot(self.w.into_scalars());
+        let scale = self.scale(h.clone());
+        let radial = p - self.w.into_scalars() * h.clone();
+        let r = radial.clone().length();
+        // The scale is monotonic in the height, so its smallest value over the cylinder is at one
+        // of its ends.
+        let min_scale = self
+            .scale(h.clone().minimum(T::from_f64(self.heights.start)))
+            .minimum(self.scale(h.clone().maximum(T::from_f64(self.heights.end))));
+        let q = radial / scale + self.w.into_scalars() * h;
+        // The largest singular value of [[a, c], [0, 1]] where a scales the cross section and c is
+        // the shear caused by the changing scale.
+        let a = min_scale.clone().recip();
+        let c = r.maximum(T::from_f64(self.radius))
+            * T::from_f64((end - start).abs() / self.length)
+            / (min_scale.clone() * min_scale);
+        let trace = a.clone() * a.clone() + c.clone() * c + T::from_f64(1.0);
+        let lipschitz = ((trace.clone()
+            + (trace.clone() * trace - T::from_f64(4.0) * a.clone() * a).sqrt())
+            / T::from_f64(2.0))
+        .sqrt();
+        inner(q) / lipschitz
+    }
+    fn bounds(&self, inner: &Aabb3) -> Option<Aabb3> {
+        let (radius, heights) = axial_extent(Vec3::zero(), self.w, inner);
+        let scale = self.scale_range.start.max(self.scale_range.end);
+        Some(cylinder_bounds(
+            Vec3::zero(),
+            self.w,
+            radius * scale,
+            heights,
+        ))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Taper {
+            inner: Box::new(inner),
+            axis: self.w * self.length,
+            scale_range: self.scale_range.clone(),
+        }
+    }
+}
+
+impl Sdf3 {
+    /// Twists around `axis` through the origin, counterclockwise when looking down `axis`.
+    pub fn twist(&self, axis: Vec3, radians_per_mm: f64) -> Sdf3 {
+        Sdf::new(Transform::new(
+            Twist::new(axis, radians_per_mm, self),
+            self.clone(),
+        ))
+    }
+    /// See [Bend].
+    pub fn bend(&self, axis: Vec3, radius: f64) -> Sdf3 {
+        Sdf::new(Transform::new(Bend::new(axis, radius, self), self.clone()))
+    }
+    /// See [Taper].
+    pub fn taper(&self, axis: Vec3, scale_range: Range<f64>) -> Sdf3 {
+        Sdf::new(Transform::new(
+            Taper::new(axis, scale_range, self),
+            self.clone(),
+        ))
+    }
+}
+
+#[test]
+fn test_deform() {
+    let post = Aabb3::new(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 20.0)).as_sdf();
+
+    let twisted = post.twist(Vec3::axis_z(), PI / 40.0);
+    // On the axis the warp is a rotation, but the shear at the corners of the post still counts.
+    let d = twisted.evaluate(Vec3::new(0.0, 0.0, 5.0));
+    assert!(d > -1.0 && d < -0.9);
+    // The top has turned by a quarter turn onto itself, and halfway up by an eighth turn.
+    assert!(twisted.evaluate(Vec3::new(0.9, 0.9, 19.9)) < 0.0);
+    assert!(twisted.evaluate(Vec3::new(1.3, 0.0, 10.0)) < 0.0);
+    assert!(twisted.evaluate(Vec3::new(0.95, 0.95, 10.0)) > 0.0);
+    let d = twisted.evaluate_deriv3(Vector3::new(
+        Deriv::variable(1.2, 0),
+        Deriv::variable(0.3, 1),
+        Deriv::variable(7.0, 2),
+    ));
+    assert!(Vec3::from(*d.deriv()).length() <= 1.0);
+
+    let bent = post.bend(Vec3::axis_z(), 10.0);
+    let u = Vec3::axis_z().perpendicular();
+    let spine =
+        |h: f64| u * (10.0 - 10.0 * (h / 10.0).cos()) + Vec3::axis_z() * (10.0 * (h / 10.0).sin());
+    // The inner side of the post is stretched by 10 / 9, which scales the whole result.
+    assert!((bent.evaluate(spine(5.0)) + 0.9).abs() < 1e-12);
+    assert!((bent.evaluate(spine(15.0)) + 0.9).abs() < 1e-12);
+    // Moving away from the center of the bend by 3mm leaves the post 2mm behind.
+    let center = u * 10.0;
+    let outside = center + (spine(10.0) - center) * 1.3;
+    assert!((bent.evaluate(outside) - 1.8).abs() < 1e-9);
+    let d = bent.evaluate_deriv1(center.map(|x| Deriv::variable(x, 0)));
+    assert!(d.value() == 0.0 && !d.deriv()[0].is_nan());
+
+    let tapered = post.taper(Vec3::new(0.0, 0.0, 20.0), 1.0..0.5);
+    assert!(tapered.evaluate(Vec3::new(0.7, 0.0, 18.0)) > 0.0);
+    assert!(tapered.evaluate(Vec3::new(0.7, 0.0, 2.0)) < 0.0);
+    // The surface is at x = 0.55 with a slope of 0.025, so the true distance is just under 0.2.
+    // The result is scaled by the narrowest part of the post, at a scale of 0.5.
+    let d = tapered.evaluate(Vec3::new(0.75, 0.0, 18.0));
+    assert!(d > 0.18 && d <= 0.2 * (1.0 / (1.0 + 0.025f64.powi(2)).sqrt()));
+
+    let region = Vector3::new(
+        DecInterval::try_from((3.0, 4.0)).unwrap(),
+        DecInterval::try_from((3.0, 4.0)).unwrap(),
+        DecInterval::try_from((5.0, 6.0)).unwrap(),
+    );
+    for sdf in [twisted, bent, tapered] {
+        let (_, range) = sdf.evaluate_constrain(region);
+        assert!(!range.contains(0.0));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_scalar::Scalar;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+
+/// An axis-aligned ellipsoid with the given semi-axes. The exact distance has no closed form, so
+/// this evaluates `(|(p - center) / radii| - 1) * min(radii)`. The scaled norm changes by at most
+/// `1 / min(radii)` per unit of distance, so this is a bound everywhere and exact on spheres.
+#[derive(Debug, Clone)]
+pub struct NEllipsoid<const N: usize> {
+    center: Vector<f64, N>,
+    radii: Vector<f64, N>,
+}
+
+pub type Ellipsoid = NEllipsoid<3>;
+pub type Ellipse = NEllipsoid<2>;
+
+impl<const N: usize> NEllipsoid<N> {
+    pub fn new(center: Vector<f64, N>, radii: Vector<f64, N>) -> Self {
+        assert!(
+            radii.into_iter().all(|r| r > 0.0),
+            "ellipsoid radii must be positive"
+        );
+        NEllipsoid { center, radii }
+    }
+    pub fn center(&self) -> Vector<f64, N> {
+        self.center
+    }
+    pub fn radii(&self) -> Vector<f64, N> {
+        self.radii
+    }
+}
+
+impl<const N: usize> SdfLeafImpl<N> for NEllipsoid<N> {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
+        let min_radius = self.radii.into_iter().fold(f64::INFINITY, f64::min);
+        let scaled =
+            (p - self.center.into_scalars()).zip_with(self.radii.into_scalars::<T>(), |x, r| x / r);
+        (scaled.length() - T::from_f64(1.0)) * T::from_f64(min_radius)
+    }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        Some(Aabb::new(
+            self.center - self.radii,
+            self.center + self.radii,
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Ellipsoid {
+            center: self.center.into_iter().collect(),
+            radii: self.radii.into_iter().collect(),
+        }
+    }
+}
+
+impl<const N: usize> AsSdf<N> for NEllipsoid<N> {
+    fn as_sdf(&self) -> Sdf<N> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+#[test]
+fn test_ellipsoid() {
+    let ellipsoid = Ellipsoid::new(Vec3::zero(), Vec3::new(4.0, 2.0, 1.0)).as_sdf();
+    assert_eq!(ellipsoid.evaluate(Vec3::zero()), -1.0);
+    assert_eq!(ellipsoid.evaluate(Vec3::new(4.0, 0.0, 0.0)), 0.0);
+    assert_eq!(ellipsoid.evaluate(Vec3::new(0.0, 0.0, 3.0)), 2.0);
+    // Along the long axis the bound is well below the true distance of 4.
+    let d = ellipsoid.evaluate(Vec3::new(8.0, 0.0, 0.0));
+    assert!(d > 0.0 && d <= 4.0);
+    let ellipse = Ellipse::new(Vec2::zero(), Vec2::new(3.0, 1.0)).as_sdf();
+    assert!(ellipse.evaluate(Vec2::new(2.5, 0.5)) < 0.0);
+    assert!(ellipse.evaluate(Vec2::new(2.5, 0.6)) > 0.0);
+}
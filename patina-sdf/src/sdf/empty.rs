@@ -1,4 +1,6 @@
 use crate::sdf::leaf::SdfLeafImpl;
+use crate::sdf::node::SdfNode;
+use patina_geo::aabb::Aabb;
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
 
@@ -9,6 +11,12 @@ impl<const N: usize> SdfLeafImpl<N> for SdfEmpty<N> {
     fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
         T::from_f64(f64::INFINITY)
     }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        Some(Aabb::empty())
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Empty
+    }
 }
 
 #[derive(Debug)]
@@ -18,4 +26,10 @@ impl<const N: usize> SdfLeafImpl<N> for SdfFull<N> {
     fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
         T::from_f64(-f64::INFINITY)
     }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        None
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Full
+    }
 }
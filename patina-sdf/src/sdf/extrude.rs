@@ -1,5 +1,8 @@
 use crate::sdf::AsSdf;
+use crate::sdf::bounds::map_corners;
+use crate::sdf::node::SdfNode;
 use crate::sdf::transform::TransformImpl;
+use patina_geo::aabb::Aabb;
 use patina_geo::sphere::Circle;
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
@@ -47,6 +50,25 @@ impl TransformImpl<2, 3> for Extrude {
         );
         d3
     }
+    fn bounds(&self, inner: &Aabb<2>) -> Option<Aabb<3>> {
+        let normal = self.axis1.cross(self.axis2);
+        let prism = Aabb::new(
+            Vec3::new(inner.min().x(), inner.min().y(), 0.0),
+            Vec3::new(inner.max().x(), inner.max().y(), self.extrude),
+        );
+        Some(map_corners(&prism, |p| {
+            self.origin + self.axis1 * p.x() + self.axis2 * p.y() + normal * p.z()
+        }))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Extrude {
+            inner: Box::new(inner),
+            origin: self.origin,
+            axis1: self.axis1,
+            axis2: self.axis2,
+            distance: self.extrude,
+        }
+    }
 }
 
 #[test]
@@ -0,0 +1,173 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::union::combine_batch;
+use crate::sdf::{AsSdf, Sdf, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Sphere;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+
+/// The intersection of any number of solids. Nested intersections are flattened into a single
+/// node so that [SdfImpl::evaluate_constrain] can drop every irrelevant child in one step.
+#[derive(Debug)]
+pub struct SdfIntersection<const N: usize> {
+    children: Vec<Sdf<N>>,
+}
+
+impl<const N: usize> SdfIntersection<N> {
+    pub fn new(a: Sdf<N>, b: Sdf<N>) -> Self {
+        Self::from_children([a, b])
+    }
+    pub fn from_children(children: impl IntoIterator<Item = Sdf<N>>) -> Self {
+        let mut flat = vec![];
+        for child in children {
+            if let Some(intersection) = child.downcast_ref::<SdfIntersection<N>>() {
+                flat.extend(intersection.children.iter().cloned());
+            } else {
+                flat.push(child);
+            }
+        }
+        assert!(!flat.is_empty());
+        Self { children: flat }
+    }
+    pub fn children(&self) -> &[Sdf<N>] {
+        &self.children
+    }
+    pub fn into_sdf(self) -> Sdf<N> {
+        Sdf::new(self)
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfIntersection<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.children
+            .iter()
+            .map(|x| x.evaluate(p))
+            .reduce(f64::maximum)
+            .unwrap()
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv1(p.clone()))
+            .reduce(Deriv::maximum)
+            .unwrap()
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv2(p.clone()))
+            .reduce(Deriv::maximum)
+            .unwrap()
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv3(p.clone()))
+            .reduce(Deriv::maximum)
+            .unwrap()
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let constrained: Vec<_> = self
+            .children
+            .iter()
+            .map(|x| x.evaluate_constrain(p))
+            .collect();
+        // The child with the highest lower bound is always kept. Any other child whose upper
+        // bound is below that can never be the maximum.
+        let (best, bound) = constrained
+            .iter()
+            .enumerate()
+            .map(|(i, (_, int))| (i, int.inf()))
+            .reduce(|x, y| if y.1 > x.1 { y } else { x })
+            .unwrap();
+        let mut changed = false;
+        let mut children = vec![];
+        let mut range: Option<DecInterval> = None;
+        for (i, ((c, int), child)) in constrained.into_iter().zip(&self.children).enumerate() {
+            if i != best && int.sup() <= bound {
+                changed = true;
+                continue;
+            }
+            changed |= c.is_some();
+            children.push(c.unwrap_or(child.clone()));
+            range = Some(range.map_or(int, |range| range.maximum(int)));
+        }
+        let range = range.unwrap();
+        if children.len() == 1 {
+            (children.pop(), range)
+        } else if changed {
+            (
+                Some(SdfIntersection::from_children(children).into_sdf()),
+                range,
+            )
+        } else {
+            (None, range)
+        }
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        self.children
+            .iter()
+            .map(|x| x.evaluate_tape(p.clone()))
+            .reduce(TapeValue::maximum)
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        combine_batch(&self.children, ps, out, f64::maximum);
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        self.children
+            .iter()
+            .filter_map(|x| x.structural_bounds())
+            .reduce(|x, y| x.intersect(&y))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.children.iter().map(|x| x.complexity()).sum::<usize>()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Intersection(self.children.iter().map(|child| child.node()).collect())
+    }
+}
+
+#[test]
+fn test_intersection() {
+    let mut sdf = Sphere::new(Vec3::zero(), 10.0).as_sdf();
+    for x in 0..8 {
+        sdf = sdf.difference(&Sphere::new(Vec3::new(x as f64 * 2.0, 0.0, 0.0), 0.5).as_sdf());
+    }
+    assert_eq!(sdf.complexity(), 1 + 1 + 8 * 2);
+    assert_eq!(sdf.evaluate(Vec3::new(4.0, 0.0, 0.0)), 0.5);
+    assert_eq!(sdf.evaluate(Vec3::new(-9.0, 0.0, 0.0)), -1.0);
+    let region = Vector3::new(
+        DecInterval::try_from((-6.0, -5.0)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+    );
+    let (pruned, range) = sdf.evaluate_constrain(region);
+    assert_eq!(pruned.unwrap().complexity(), 1);
+    assert!(range.sup() < 0.0);
+    // Batches longer than the scratch space are split up.
+    let ps: Vec<Vec3> = (0..1000)
+        .map(|i| Vec3::new(i as f64 * 0.02 - 5.0, 0.1, 0.0))
+        .collect();
+    let mut ds = vec![0.0; ps.len()];
+    sdf.evaluate_batch(&ps, &mut ds);
+    for (p, d) in ps.iter().zip(ds) {
+        assert_eq!(d, sdf.evaluate(*p));
+    }
+    // Combining no solids gives nothing for a union and everything for an intersection.
+    let p = Vec3::new(1.0, 2.0, 3.0);
+    assert_eq!(Sdf::<3>::union_all([]).evaluate(p), f64::INFINITY);
+    assert_eq!(Sdf::<3>::intersection_all([]).evaluate(p), -f64::INFINITY);
+}
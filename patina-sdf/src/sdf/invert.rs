@@ -1,5 +1,8 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
 use crate::sdf::{Sdf, SdfImpl};
 use inari::DecInterval;
+use patina_geo::aabb::Aabb;
 use patina_scalar::deriv::Deriv;
 use patina_vec::vec::Vector;
 use patina_vec::vec3::{Vec3, Vector3};
@@ -13,6 +16,9 @@ impl<const N: usize> SdfInvert<N> {
     pub fn new(inner: Sdf<N>) -> Self {
         SdfInvert { inner }
     }
+    pub fn inner(&self) -> &Sdf<N> {
+        &self.inner
+    }
 }
 
 impl<const N: usize> SdfImpl<N> for SdfInvert<N> {
@@ -37,7 +43,39 @@ impl<const N: usize> SdfImpl<N> for SdfInvert<N> {
         (inner.map(|x| x.invert()), -range)
     }
 
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        Some(-self.inner.evaluate_tape(p))
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        self.inner.evaluate_batch(ps, out);
+        for out in out {
+            *out = -*out;
+        }
+    }
+
+    fn evaluate_gradient_batch(
+        &self,
+        ps: &[Vector<f64, N>],
+        out: &mut [f64],
+        gradients: &mut [Vector<f64, N>],
+    ) {
+        self.inner.evaluate_gradient_batch(ps, out, gradients);
+        for (out, gradient) in out.iter_mut().zip(gradients) {
+            *out = -*out;
+            *gradient = -*gradient;
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        None
+    }
+
     fn complexity(&self) -> usize {
         1 + self.inner.complexity()
     }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Invert(Box::new(self.inner.node()))
+    }
 }
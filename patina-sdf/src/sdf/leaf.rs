@@ -1,3 +1,5 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
 use crate::sdf::{Sdf, SdfImpl};
 use inari::{DecInterval, dec_interval};
 use patina_geo::aabb::Aabb;
@@ -13,6 +15,10 @@ use std::fmt::{Debug, Formatter};
 
 pub trait SdfLeafImpl<const N: usize>: 'static + Sync + Send + Sized + Debug {
     fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T;
+    /// See [SdfImpl::bounds].
+    fn bounds(&self) -> Option<Aabb<N>>;
+    /// See [SdfImpl::node]. Leaves defined outside this crate return [SdfNode::custom].
+    fn node(&self) -> SdfNode;
 }
 
 pub struct SdfLeaf<const N: usize, T> {
@@ -52,9 +58,27 @@ impl<const N: usize, T: SdfLeafImpl<N>> SdfImpl<N> for SdfLeaf<N, T> {
         }
     }
 
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        Some(self.inner.evaluate(p))
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        for (p, out) in ps.iter().zip(out) {
+            *out = self.inner.evaluate(*p);
+        }
+    }
+
     fn complexity(&self) -> usize {
         1
     }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        self.inner.bounds()
+    }
+
+    fn node(&self) -> SdfNode {
+        self.inner.node()
+    }
 }
 
 impl<const N: usize, T: Debug> Debug for SdfLeaf<N, T> {
@@ -0,0 +1,238 @@
+use crate::sdf::bounds::is_empty;
+use crate::sdf::node::SdfNode;
+use crate::sdf::sweep::cap;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, Sdf2, Sdf3, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::geo2::polygon2::Polygon2;
+use patina_geo::sphere::Circle;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::{Vec2, Vector2};
+use patina_vec::vec3::{Vec3, Vector3};
+use std::ops::Range;
+
+/// How many cells along each axis [SdfLoft::new] splits the profiles' bounds into to bound
+/// `|top - bottom|`.
+const RATE_CELLS: usize = 16;
+
+/// A solid between two z planes whose cross section blends linearly from the `bottom` profile to
+/// the `top` profile. The blended field changes along z at a rate of `(top - bottom) / height`,
+/// so it is divided by `sqrt(1 + rate²)`. The rate is bounded over the profiles' bounds, and only
+/// taken at the point itself where it is larger than that bound.
+#[derive(Debug)]
+pub struct SdfLoft {
+    bottom: Sdf2,
+    top: Sdf2,
+    z_range: Range<f64>,
+    /// An upper bound of `|top - bottom| / height` over the profiles' bounds.
+    max_rate: f64,
+}
+
+impl SdfLoft {
+    pub fn new(bottom: Sdf2, top: Sdf2, z_range: Range<f64>) -> Self {
+        assert!(z_range.start < z_range.end, "loft range must be nonempty");
+        let max_rate = Self::max_difference(&bottom, &top) / (z_range.end - z_range.start);
+        SdfLoft {
+            bottom,
+            top,
+            z_range,
+            max_rate,
+        }
+    }
+    /// An upper bound of `|top - bottom|` over the profiles' bounds, or zero if they are
+    /// unbounded. Both profiles are 1-Lipschitz, so their difference changes by at most the
+    /// diameter of a cell within it.
+    fn max_difference(bottom: &Sdf2, top: &Sdf2) -> f64 {
+        let (Some(a), Some(b)) = (bottom.structural_bounds(), top.structural_bounds()) else {
+            return 0.0;
+        };
+        let profile = a.union(&b);
+        if is_empty(&profile) {
+            return 0.0;
+        }
+        let size = profile.dimensions() / RATE_CELLS as f64;
+        let mut max = 0.0f64;
+        for i in 0..RATE_CELLS {
+            for j in 0..RATE_CELLS {
+                let center = profile.min()
+                    + Vec2::new((i as f64 + 0.5) * size.x(), (j as f64 + 0.5) * size.y());
+                max = max.max((top.evaluate(center) - bottom.evaluate(center)).abs());
+            }
+        }
+        max + size.length()
+    }
+    fn evaluate_with<T: Scalar>(
+        &self,
+        p: Vector3<T>,
+        bottom: impl FnOnce(Vector2<T>) -> T,
+        top: impl FnOnce(Vector2<T>) -> T,
+    ) -> T {
+        let height = self.z_range.end - self.z_range.start;
+        let z = p.z() - T::from_f64(self.z_range.start);
+        let t = (z.clone() / T::from_f64(height))
+            .maximum(T::from_f64(0.0))
+            .minimum(T::from_f64(1.0));
+        let q = Vector2::new(p.x(), p.y());
+        let bottom = bottom(q.clone());
+        let rate = (top(q) - bottom.clone()) / T::from_f64(height);
+        let max_rate = rate.clone().abs().maximum(T::from_f64(self.max_rate));
+        let lipschitz = (max_rate.clone() * max_rate + T::from_f64(1.0)).sqrt();
+        let d2 = (bottom + rate * t * T::from_f64(height)) / lipschitz;
+        cap(cap(d2, -z.clone()), z - T::from_f64(height))
+    }
+}
+
+impl SdfImpl<3> for SdfLoft {
+    fn evaluate(&self, p: Vector<f64, 3>) -> f64 {
+        self.evaluate_with(p, |q| self.bottom.evaluate(q), |q| self.top.evaluate(q))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, 3>) -> Deriv<1> {
+        self.evaluate_with(
+            p,
+            |q| self.bottom.evaluate_deriv1(q),
+            |q| self.top.evaluate_deriv1(q),
+        )
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, 3>) -> Deriv<2> {
+        self.evaluate_with(
+            p,
+            |q| self.bottom.evaluate_deriv2(q),
+            |q| self.top.evaluate_deriv2(q),
+        )
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, 3>) -> Deriv<3> {
+        self.evaluate_with(
+            p,
+            |q| self.bottom.evaluate_deriv3(q),
+            |q| self.top.evaluate_deriv3(q),
+        )
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, 3>) -> (Option<Sdf3>, DecInterval) {
+        let q = Vector2::new(p.x(), p.y());
+        let (bottom, bottom_range) = self.bottom.evaluate_constrain(q);
+        let (top, top_range) = self.top.evaluate_constrain(q);
+        let bounded = |x: DecInterval| f64::MIN < x.inf() && x.sup() < f64::MAX;
+        if !bounded(bottom_range) || !bounded(top_range) {
+            // A simplified profile only has the right sign, which is not enough to blend it.
+            let z = p.z();
+            if z.sup() < self.z_range.start
+                || self.z_range.end < z.inf()
+                || (bottom_range.inf() > 0.0 && top_range.inf() > 0.0)
+            {
+                return (Some(Sdf::empty()), DecInterval::from_f64(f64::MAX));
+            } else if bottom_range.sup() < 0.0
+                && top_range.sup() < 0.0
+                && self.z_range.start < z.inf()
+                && z.sup() < self.z_range.end
+            {
+                return (Some(Sdf::full()), DecInterval::from_f64(f64::MIN));
+            }
+            return (None, DecInterval::ENTIRE);
+        }
+        let range = self.evaluate_with(p, |_| bottom_range, |_| top_range);
+        if bottom.is_none() && top.is_none() {
+            return (None, range);
+        }
+        (
+            Some(Sdf::new(SdfLoft {
+                bottom: bottom.unwrap_or(self.bottom.clone()),
+                top: top.unwrap_or(self.top.clone()),
+                z_range: self.z_range.clone(),
+                max_rate: self.max_rate,
+            })),
+            range,
+        )
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, 3>) -> Option<TapeValue> {
+        Some(self.evaluate_with(
+            p,
+            |q| self.bottom.evaluate_tape(q),
+            |q| self.top.evaluate_tape(q),
+        ))
+    }
+
+    /// The blended profile is only negative where one of the profiles is.
+    fn bounds(&self) -> Option<Aabb<3>> {
+        let profile = self
+            .bottom
+            .structural_bounds()?
+            .union(&self.top.structural_bounds()?);
+        if is_empty(&profile) {
+            return Some(Aabb::empty());
+        }
+        Some(Aabb::new(
+            Vec3::new(profile.min().x(), profile.min().y(), self.z_range.start),
+            Vec3::new(profile.max().x(), profile.max().y(), self.z_range.end),
+        ))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.bottom.complexity() + self.top.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Loft {
+            bottom: Box::new(self.bottom.node()),
+            top: Box::new(self.top.node()),
+            z_range: self.z_range.clone(),
+        }
+    }
+}
+
+impl Sdf2 {
+    /// Blends from this profile at `z_range.start` to `other` at `z_range.end`.
+    pub fn loft(&self, other: &Sdf2, z_range: Range<f64>) -> Sdf3 {
+        Sdf::new(SdfLoft::new(self.clone(), other.clone(), z_range))
+    }
+}
+
+#[test]
+fn test_loft() {
+    let square = Polygon2::new(vec![
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+    ])
+    .as_sdf();
+    let circle = Circle::new(Vec2::zero(), 2.0).as_sdf();
+    let adapter = square.loft(&circle, 0.0..10.0);
+    // The rate is 0.1 here, but is bounded by a slightly larger value over the whole profile.
+    let d = adapter.evaluate(Vec3::new(0.0, 0.0, 5.0));
+    assert!(d > -1.5 / 1.01f64.sqrt() && d < -1.45);
+    assert!(adapter.evaluate(Vec3::new(1.2, 0.0, 1.0)) > 0.0);
+    assert!(adapter.evaluate(Vec3::new(1.2, 0.0, 9.0)) < 0.0);
+    assert_eq!(adapter.evaluate(Vec3::new(0.0, 0.0, -3.0)), 3.0);
+    assert_eq!(adapter.evaluate(Vec3::new(0.0, 0.0, 12.0)), 2.0);
+    let d = adapter.evaluate_deriv3(Vector3::new(
+        Deriv::variable(1.5, 0),
+        Deriv::variable(0.2, 1),
+        Deriv::variable(5.0, 2),
+    ));
+    assert!(Vec3::from(*d.deriv()).length() <= 1.0 + 1e-12);
+    let region = Vector3::new(
+        DecInterval::try_from((5.0, 6.0)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((4.0, 5.0)).unwrap(),
+    );
+    let (_, range) = adapter.evaluate_constrain(region);
+    assert!(range.inf() > 0.0);
+    // Both profiles are pruned here, to opposite signs, but the blend crosses zero.
+    let region = Vector3::new(
+        DecInterval::try_from((-0.1, 0.1)).unwrap(),
+        DecInterval::try_from((1.7, 1.9)).unwrap(),
+        DecInterval::try_from((7.9, 8.1)).unwrap(),
+    );
+    assert!(adapter.evaluate(Vec3::new(0.0, 1.8, 7.9)) > 0.0);
+    assert!(adapter.evaluate(Vec3::new(0.0, 1.8, 8.1)) < 0.0);
+    let (_, range) = adapter.evaluate_constrain(region);
+    assert!(range.contains(0.0));
+}
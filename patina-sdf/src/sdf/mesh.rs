@@ -0,0 +1,149 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, Sdf3, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_mesh::bvh::mesh_bvh3::WindingBvh;
+use patina_mesh::mesh::Mesh;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+use std::sync::Arc;
+
+/// A solid bounded by a triangle mesh. The distance is the distance to the nearest triangle, and
+/// points with a generalized winding number above one half are inside. Small holes in the mesh
+/// only perturb the sign near the hole.
+#[derive(Debug)]
+pub struct SdfMesh {
+    winding: WindingBvh,
+}
+
+impl SdfMesh {
+    pub fn new(mesh: Arc<Mesh>) -> Self {
+        assert!(!mesh.triangles().is_empty(), "mesh must have triangles");
+        SdfMesh {
+            winding: WindingBvh::new(mesh),
+        }
+    }
+    pub fn mesh(&self) -> &Arc<Mesh> {
+        self.winding.bvh().mesh()
+    }
+    /// The signed distance and its gradient.
+    fn evaluate_gradient(&self, p: Vec3) -> (f64, Vec3) {
+        let nearest = self.winding.bvh().nearest_triangle(p).unwrap();
+        let sign = if self.winding.winding_number(p) > 0.5 {
+            -1.0
+        } else {
+            1.0
+        };
+        let gradient = if nearest.distance > 0.0 {
+            (p - nearest.pos) / nearest.distance * sign
+        } else {
+            self.mesh().triangles()[nearest.triangle]
+                .for_vertices(self.mesh().vertices())
+                .normal()
+        };
+        (nearest.distance * sign, gradient)
+    }
+    /// Distance to a mesh is not smooth, so derivatives use the linearization at `p`.
+    fn evaluate_deriv<const D: usize>(&self, p: Vector3<Deriv<D>>) -> Deriv<D> {
+        let value = p.clone().map(|x| x.value());
+        let (d, gradient) = self.evaluate_gradient(value);
+        (p - value.into_scalars()).dot(gradient.into_scalars()) + Deriv::from_f64(d)
+    }
+}
+
+impl AsSdf<3> for Mesh {
+    fn as_sdf(&self) -> Sdf3 {
+        Sdf::new(SdfMesh::new(Arc::new(self.clone())))
+    }
+}
+
+impl SdfImpl<3> for SdfMesh {
+    fn evaluate(&self, p: Vector<f64, 3>) -> f64 {
+        self.evaluate_gradient(p).0
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, 3>) -> Deriv<1> {
+        self.evaluate_deriv(p)
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, 3>) -> Deriv<2> {
+        self.evaluate_deriv(p)
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, 3>) -> Deriv<3> {
+        self.evaluate_deriv(p)
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, 3>) -> (Option<Sdf3>, DecInterval) {
+        // The unsigned distance is 1-Lipschitz, so the value at the center bounds the region.
+        let center = p.map(|x| x.mid());
+        let range = p.map(|x| x.sup() - x.inf()).length() / 2.0;
+        let d = self.evaluate(center);
+        if d < -range {
+            (Some(Sdf::full()), DecInterval::from_f64(f64::MIN))
+        } else if d > range {
+            (Some(Sdf::empty()), DecInterval::from_f64(f64::MAX))
+        } else {
+            (None, DecInterval::try_from((d - range, d + range)).unwrap())
+        }
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, 3>) -> Option<TapeValue> {
+        // The nearest triangle is found by searching the BVH, which a tape cannot express.
+        None
+    }
+
+    fn bounds(&self) -> Option<Aabb<3>> {
+        Some(self.mesh().vertices().iter().copied().collect())
+    }
+
+    fn complexity(&self) -> usize {
+        1
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Mesh {
+            vertices: self.mesh().vertices().to_vec(),
+            triangles: self
+                .mesh()
+                .triangles()
+                .iter()
+                .map(|triangle| triangle.vertices())
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_mesh() {
+    let mesh = Mesh::from_aabb(Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0)));
+    let sdf = mesh.as_sdf();
+    assert!((sdf.evaluate(Vec3::zero()) + 1.0).abs() < 1e-12);
+    assert!((sdf.evaluate(Vec3::new(0.5, 0.25, 0.0)) + 0.5).abs() < 1e-12);
+    assert!((sdf.evaluate(Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-12);
+    assert!((sdf.evaluate(Vec3::new(2.0, 2.0, 0.0)) - 2.0f64.sqrt()).abs() < 1e-12);
+    let d = sdf.evaluate_deriv1(Vector3::new(
+        Deriv::variable(3.0, 0),
+        Deriv::constant(0.0),
+        Deriv::constant(0.0),
+    ));
+    assert!((d.deriv()[0] - 1.0).abs() < 1e-12);
+
+    // Removing a face leaves the sign intact away from the hole.
+    let mut triangles = mesh.triangles().to_vec();
+    triangles.truncate(10);
+    let open = Mesh::new(mesh.vertices().to_vec(), triangles).as_sdf();
+    assert!(open.evaluate(Vec3::new(-0.5, 0.0, 0.0)) < 0.0);
+    assert!(open.evaluate(Vec3::new(-3.0, 0.0, 0.0)) > 0.0);
+
+    let region = Vector3::new(
+        DecInterval::try_from((4.0, 5.0)).unwrap(),
+        DecInterval::try_from((4.0, 5.0)).unwrap(),
+        DecInterval::try_from((4.0, 5.0)).unwrap(),
+    );
+    let (_, range) = sdf.evaluate_constrain(region);
+    assert!(!range.contains(0.0));
+}
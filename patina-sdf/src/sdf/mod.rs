@@ -13,17 +13,46 @@ mod triangle;
 pub mod truncated_cone;
 pub mod union;
 mod circle;
+pub mod smooth_union;
+pub mod intersection;
+pub mod offset;
+pub mod affine;
+pub mod repeat;
+pub mod mesh;
+pub mod deform;
+pub mod sweep;
+pub mod loft;
+pub mod revolve;
+pub mod node;
+pub mod torus;
+pub mod capsule;
+pub mod ellipsoid;
+pub mod rounded_box;
+pub mod regular_polygon;
+pub mod arc;
+pub mod tape;
+pub mod query;
+pub mod slice;
+pub(crate) mod bounds;
 
 use crate::sdf::empty::{SdfEmpty, SdfFull};
 use crate::sdf::extrude::Extrude;
+use crate::sdf::intersection::SdfIntersection;
 use crate::sdf::invert::SdfInvert;
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::{SdfNode, SdfRegistry};
+use crate::sdf::offset::{SdfOffset, SdfShell};
+use crate::sdf::repeat::{SdfRepeatLinear, SdfRepeatPolar};
 use crate::sdf::rotate::Rotate;
+use crate::sdf::slice::Slice;
+use crate::sdf::smooth_union::SdfSmoothUnion;
+use crate::sdf::tape::TapeValue;
 use crate::sdf::transform::Transform;
 use crate::sdf::union::SdfUnion;
 use inari::DecInterval;
 use patina_geo::aabb::Aabb;
 use patina_geo::geo3::cylinder::Cylinder;
+use patina_geo::geo3::plane::Plane;
 use patina_scalar::Scalar;
 use patina_scalar::deriv::Deriv;
 use patina_vec::vec::Vector;
@@ -77,14 +106,93 @@ impl<const N: usize> Sdf<N> {
     pub fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
         self.0.imp.evaluate_constrain(p)
     }
+    /// Evaluates every point of `ps` into the matching element of `out`.
+    pub fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        assert_eq!(ps.len(), out.len());
+        self.0.imp.evaluate_batch(ps, out)
+    }
+    /// Evaluates every point of `ps` into the matching elements of `out` and `gradients`.
+    pub fn evaluate_gradient_batch(
+        &self,
+        ps: &[Vector<f64, N>],
+        out: &mut [f64],
+        gradients: &mut [Vector<f64, N>],
+    ) {
+        assert_eq!(ps.len(), out.len());
+        assert_eq!(ps.len(), gradients.len());
+        self.0.imp.evaluate_gradient_batch(ps, out, gradients)
+    }
+    pub fn downcast_ref<T: SdfImpl<N>>(&self) -> Option<&T> {
+        (&self.0.imp as &dyn Any).downcast_ref::<T>()
+    }
     pub fn union(&self, other: &Sdf<N>) -> Sdf<N> {
         SdfUnion::new(self.clone(), other.clone()).into_sdf()
     }
+    /// The union of every solid in `sdfs`, which is empty if there are none.
+    pub fn union_all(sdfs: impl IntoIterator<Item = Sdf<N>>) -> Sdf<N> {
+        let mut sdfs = sdfs.into_iter().peekable();
+        if sdfs.peek().is_none() {
+            return Sdf::empty();
+        }
+        let union = SdfUnion::from_children(sdfs);
+        if union.children().len() == 1 {
+            union.children()[0].clone()
+        } else {
+            union.into_sdf()
+        }
+    }
+    pub fn intersection(&self, other: &Sdf<N>) -> Sdf<N> {
+        SdfIntersection::new(self.clone(), other.clone()).into_sdf()
+    }
+    /// The intersection of every solid in `sdfs`, which is full if there are none.
+    pub fn intersection_all(sdfs: impl IntoIterator<Item = Sdf<N>>) -> Sdf<N> {
+        let mut sdfs = sdfs.into_iter().peekable();
+        if sdfs.peek().is_none() {
+            return Sdf::full();
+        }
+        let intersection = SdfIntersection::from_children(sdfs);
+        if intersection.children().len() == 1 {
+            intersection.children()[0].clone()
+        } else {
+            intersection.into_sdf()
+        }
+    }
     pub fn invert(&self) -> Sdf<N> {
+        if let Some(invert) = self.downcast_ref::<SdfInvert<N>>() {
+            return invert.inner().clone();
+        }
         Sdf::new(SdfInvert::new(self.clone()))
     }
     pub fn difference(&self, other: &Sdf<N>) -> Sdf<N> {
-        self.invert().union(other).invert()
+        self.intersection(&other.invert())
+    }
+    /// Union with a fillet of radius roughly `k` along the intersection curve.
+    pub fn smooth_union(&self, other: &Sdf<N>, k: f64) -> Sdf<N> {
+        SdfSmoothUnion::new(self.clone(), other.clone(), k).into_sdf()
+    }
+    pub fn smooth_intersection(&self, other: &Sdf<N>, k: f64) -> Sdf<N> {
+        self.invert().smooth_union(&other.invert(), k).invert()
+    }
+    pub fn smooth_difference(&self, other: &Sdf<N>, k: f64) -> Sdf<N> {
+        self.invert().smooth_union(other, k).invert()
+    }
+    /// Grows the solid by `distance` in every direction (or shrinks it if `distance` is negative).
+    pub fn offset(&self, distance: f64) -> Sdf<N> {
+        Sdf::new(SdfOffset::new(self.clone(), distance))
+    }
+    /// Hollows out the solid, leaving a wall of the given thickness inside the original surface.
+    pub fn shell(&self, thickness: f64) -> Sdf<N> {
+        Sdf::new(SdfShell::new(self.clone(), thickness))
+    }
+    /// `count` copies spaced `spacing` apart along `direction`. The solid must lie within a spacing
+    /// of the origin along `direction`.
+    pub fn repeat_linear(&self, direction: Vector<f64, N>, spacing: f64, count: usize) -> Sdf<N> {
+        Sdf::new(SdfRepeatLinear::new(
+            self.clone(),
+            direction,
+            spacing,
+            count,
+        ))
     }
     pub fn empty() -> Sdf<N> {
         Sdf::new(SdfLeaf::new(SdfEmpty))
@@ -95,9 +203,32 @@ impl<const N: usize> Sdf<N> {
     pub fn complexity(&self) -> usize {
         self.0.imp.complexity()
     }
+    /// A box containing the solid, computed from the structure of the tree alone, or `None` if
+    /// the solid is unbounded. See [Sdf::bounds] for a tighter box.
+    pub fn structural_bounds(&self) -> Option<Aabb<N>> {
+        self.0.imp.bounds()
+    }
+    /// A plain-data description of this tree, which can be rebuilt with [SdfRegistry::build].
+    pub fn node(&self) -> SdfNode {
+        self.0.imp.node()
+    }
 }
 
 impl Sdf<3> {
+    /// `count` copies rotated evenly around `axis` through the origin. The solid must lie within
+    /// the sector centered on the direction `radial` from the axis and the sectors either side of
+    /// it.
+    pub fn repeat_polar(&self, axis: Vec3, radial: Vec3, count: usize) -> Sdf<3> {
+        Sdf::new(SdfRepeatPolar::new(self.clone(), axis, radial, count))
+    }
+    /// The cross-section by `plane`, in the coordinates of [Slice::from_plane].
+    pub fn slice(&self, plane: &Plane) -> Sdf<2> {
+        Sdf::new(Transform::new(Slice::from_plane(plane), self.clone()))
+    }
+    /// The cross-section at height `z`, in X and Y coordinates.
+    pub fn slice_z(&self, z: f64) -> Sdf<2> {
+        self.slice(&Plane::new(Vec3::new(0.0, 0.0, z), Vec3::axis_z()))
+    }
     pub fn normal(&self, position: Vec3) -> Vec3 {
         Vector::from(
             self.evaluate_deriv3(position.into_variable())
@@ -152,17 +283,51 @@ impl Sdf<2> {
     }
 }
 
+/// `p` as a point of [Deriv] variables, so that the result of evaluating it holds the gradient.
+pub(crate) fn gradient_variables<const N: usize>(p: Vector<f64, N>) -> Vector<Deriv<3>, N> {
+    assert!(N <= 3);
+    Vector::from_fn(|axis| Deriv::variable(p[axis], axis))
+}
+
 struct SdfInner<S: ?Sized> {
     imp: S,
 }
 
-pub trait SdfImpl<const N: usize>: 'static + Sync + Send + Debug {
+pub trait SdfImpl<const N: usize>: 'static + Sync + Send + Debug + Any {
     fn evaluate(&self, p: Vector<f64, N>) -> f64;
     fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1>;
     fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2>;
     fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3>;
     fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval);
+    /// Records this node onto a tape (see [Sdf::tape]), or returns `None` if the tape should call
+    /// this node instead.
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue>;
+    /// Evaluates every point of `ps` into the matching element of `out`. Nodes should override
+    /// this with loops over the whole batch where they can.
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        for (p, out) in ps.iter().zip(out) {
+            *out = self.evaluate(*p);
+        }
+    }
+    /// Like [SdfImpl::evaluate_batch], but also writes the gradient at every point.
+    fn evaluate_gradient_batch(
+        &self,
+        ps: &[Vector<f64, N>],
+        out: &mut [f64],
+        gradients: &mut [Vector<f64, N>],
+    ) {
+        for ((p, out), gradient) in ps.iter().zip(out).zip(gradients) {
+            let d = self.evaluate_deriv3(gradient_variables(*p));
+            *out = d.value();
+            *gradient = Vector::from_fn(|axis| d.deriv()[axis]);
+        }
+    }
     fn complexity(&self) -> usize;
+    /// A box containing every point where this SDF is not positive, or `None` if there is no such
+    /// box. The box may be loose, but must never clip the solid.
+    fn bounds(&self) -> Option<Aabb<N>>;
+    /// A plain-data description of this node and its children.
+    fn node(&self) -> SdfNode;
 }
 
 impl<const N: usize> Debug for Sdf<N> {
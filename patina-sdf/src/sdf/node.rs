@@ -0,0 +1,705 @@
+use crate::sdf::affine::Affine;
+use crate::sdf::arc::CircularArc;
+use crate::sdf::capsule::NCapsule;
+use crate::sdf::cylinder::CylinderCrossSection;
+use crate::sdf::deform::Bend;
+use crate::sdf::ellipsoid::NEllipsoid;
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::regular_polygon::RegularPolygon;
+use crate::sdf::revolve::SdfRevolve;
+use crate::sdf::rounded_box::RoundedAabb;
+use crate::sdf::slice::Slice;
+use crate::sdf::sweep::{SdfSweep, SweepPiece};
+use crate::sdf::torus::Torus;
+use crate::sdf::transform::Transform;
+use crate::sdf::truncated_cone::TruncatedConeCrossSection;
+use crate::sdf::{AsSdf, Sdf, Sdf2, Sdf3};
+use anyhow::{Context, anyhow, bail, ensure};
+use patina_geo::aabb::Aabb;
+use patina_geo::geo2::polygon2::Polygon2;
+use patina_geo::geo3::plane::Plane;
+use patina_geo::geo3::triangle3::Triangle3;
+use patina_geo::sphere::{NSphere, Sphere};
+use patina_mesh::mesh::Mesh;
+use patina_mesh::mesh_triangle::MeshTriangle;
+use patina_vec::mat4::Mat4;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+/// A plain-data description of an [Sdf] tree, as returned by [Sdf::node]. Every built-in node and
+/// leaf has a variant holding the arguments of its constructor. Vectors whose length depends on
+/// the dimension of the node are stored as `Vec<f64>`.
+///
+/// Nodes are rebuilt by passing the arguments back to the constructors. Arguments that the
+/// constructors would reject are reported as errors by [SdfRegistry::build] instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SdfNode {
+    Empty,
+    Full,
+    Union(Vec<SdfNode>),
+    Intersection(Vec<SdfNode>),
+    Invert(Box<SdfNode>),
+    SmoothUnion {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        k: f64,
+    },
+    Offset {
+        inner: Box<SdfNode>,
+        distance: f64,
+    },
+    Shell {
+        inner: Box<SdfNode>,
+        thickness: f64,
+    },
+    RepeatLinear {
+        inner: Box<SdfNode>,
+        direction: Vec<f64>,
+        spacing: f64,
+        count: usize,
+    },
+    RepeatPolar {
+        inner: Box<SdfNode>,
+        axis: Vec3,
+        radial: Vec3,
+        count: usize,
+    },
+    /// The rows of the forward map.
+    Affine {
+        inner: Box<SdfNode>,
+        forward: [[f64; 4]; 4],
+    },
+    Twist {
+        inner: Box<SdfNode>,
+        axis: Vec3,
+        rate: f64,
+    },
+    Bend {
+        inner: Box<SdfNode>,
+        axis: Vec3,
+        radius: f64,
+    },
+    Taper {
+        inner: Box<SdfNode>,
+        axis: Vec3,
+        scale_range: Range<f64>,
+    },
+    Rotate {
+        inner: Box<SdfNode>,
+        origin: Vec3,
+        axis: Vec3,
+    },
+    Extrude {
+        inner: Box<SdfNode>,
+        origin: Vec3,
+        axis1: Vec3,
+        axis2: Vec3,
+        distance: f64,
+    },
+    Slice {
+        inner: Box<SdfNode>,
+        origin: Vec3,
+        axis1: Vec3,
+        axis2: Vec3,
+    },
+    Sweep {
+        pieces: Vec<(SweepPiece, SdfNode)>,
+    },
+    Loft {
+        bottom: Box<SdfNode>,
+        top: Box<SdfNode>,
+        z_range: Range<f64>,
+    },
+    Revolve {
+        profile: Box<SdfNode>,
+        origin: Vec3,
+        axis: Vec3,
+        angle_range: Range<f64>,
+    },
+    Mesh {
+        vertices: Vec<Vec3>,
+        triangles: Vec<[usize; 3]>,
+    },
+    Sphere {
+        center: Vec<f64>,
+        radius: f64,
+    },
+    Aabb {
+        min: Vec<f64>,
+        max: Vec<f64>,
+    },
+    Plane {
+        origin: Vec3,
+        normal: Vec3,
+    },
+    CylinderSection {
+        radius: f64,
+        height: f64,
+    },
+    TruncatedConeSection {
+        height: f64,
+        r1: f64,
+        r2: f64,
+    },
+    Polygon {
+        points: Vec<Vec2>,
+    },
+    Torus {
+        origin: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    Capsule {
+        start: Vec<f64>,
+        end: Vec<f64>,
+        radius: f64,
+    },
+    Ellipsoid {
+        center: Vec<f64>,
+        radii: Vec<f64>,
+    },
+    RoundedBox {
+        min: Vec<f64>,
+        max: Vec<f64>,
+        radius: f64,
+    },
+    RegularPolygon {
+        center: Vec2,
+        radius: f64,
+        sides: usize,
+    },
+    Arc {
+        center: Vec2,
+        radius: f64,
+        angle_range: Range<f64>,
+        width: f64,
+    },
+    Triangle {
+        points: [Vec3; 3],
+    },
+    /// A leaf defined outside this crate. See [SdfRegistry::register].
+    Custom {
+        name: String,
+        params: serde_json::Value,
+    },
+}
+
+impl SdfNode {
+    /// Describes a leaf defined outside this crate by its serialized fields.
+    pub fn custom(name: &str, leaf: &impl Serialize) -> SdfNode {
+        SdfNode::Custom {
+            name: name.to_string(),
+            params: serde_json::to_value(leaf).expect("leaf parameters must serialize"),
+        }
+    }
+    /// A hash of the node types and exact parameters of this tree. Unlike [std::hash::Hash], it
+    /// is stable across runs, so it can key data stored on disk.
+    pub fn structural_hash(&self) -> u64 {
+        structural_hash(self)
+    }
+}
+
+/// Feeds serialized bytes through 64-bit FNV-1a.
+struct Fnv1a(u64);
+
+impl Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &b in buf {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A hash of the compact JSON encoding of `value`. Floats are written in their shortest exact
+/// form and maps are sorted, so equal values always hash equally, across runs and platforms.
+pub(crate) fn structural_hash(value: &impl Serialize) -> u64 {
+    let mut hasher = Fnv1a(0xcbf29ce484222325);
+    serde_json::to_writer(&mut hasher, value).expect("sdf nodes must serialize");
+    hasher.0
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SdfFormat {
+    Json,
+    Ron,
+}
+
+/// The version of the file format written by [Sdf::to_writer].
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SdfDocument {
+    version: u32,
+    dimension: usize,
+    root: SdfNode,
+}
+
+type LeafBuilder = Box<dyn Send + Sync + Fn(serde_json::Value) -> anyhow::Result<Box<dyn Any>>>;
+
+/// Rebuilds [Sdf] trees from [SdfNode]s. Leaves defined outside this crate must be registered
+/// under the name they pass to [SdfNode::custom].
+#[derive(Default)]
+pub struct SdfRegistry {
+    leaves: HashMap<(String, usize), LeafBuilder>,
+}
+
+/// Converts between dimensions that are known to be equal at runtime.
+fn cast<const N: usize, const M: usize>(sdf: Sdf<M>) -> anyhow::Result<Sdf<N>> {
+    (Box::new(sdf) as Box<dyn Any>)
+        .downcast::<Sdf<N>>()
+        .map(|sdf| *sdf)
+        .map_err(|_| anyhow!("expected a {}D node, found a {}D node", N, M))
+}
+
+fn vector<const N: usize>(xs: &[f64]) -> anyhow::Result<Vector<f64, N>> {
+    ensure!(
+        xs.len() == N,
+        "expected a vector of length {}, found {}",
+        N,
+        xs.len()
+    );
+    Ok(xs.iter().copied().collect())
+}
+
+impl SdfRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Registers a leaf that describes itself as `SdfNode::custom(name, self)`.
+    pub fn register<const N: usize, T: SdfLeafImpl<N> + DeserializeOwned>(&mut self, name: &str) {
+        self.leaves.insert(
+            (name.to_string(), N),
+            Box::new(|params| {
+                let leaf: T = serde_json::from_value(params)?;
+                Ok(Box::new(Sdf::<N>::new(SdfLeaf::new(leaf))) as Box<dyn Any>)
+            }),
+        );
+    }
+    fn build_all<const N: usize>(&self, nodes: &[SdfNode]) -> anyhow::Result<Vec<Sdf<N>>> {
+        ensure!(!nodes.is_empty(), "expected at least one child");
+        nodes.iter().map(|node| self.build(node)).collect()
+    }
+    pub fn build<const N: usize>(&self, node: &SdfNode) -> anyhow::Result<Sdf<N>> {
+        Ok(match node {
+            SdfNode::Empty => Sdf::empty(),
+            SdfNode::Full => Sdf::full(),
+            SdfNode::Union(children) => Sdf::union_all(self.build_all(children)?),
+            SdfNode::Intersection(children) => Sdf::intersection_all(self.build_all(children)?),
+            SdfNode::Invert(inner) => self.build::<N>(inner)?.invert(),
+            SdfNode::SmoothUnion { a, b, k } => {
+                ensure!(*k > 0.0, "smoothing radius must be positive");
+                self.build::<N>(a)?.smooth_union(&self.build(b)?, *k)
+            }
+            SdfNode::Offset { inner, distance } => self.build::<N>(inner)?.offset(*distance),
+            SdfNode::Shell { inner, thickness } => {
+                ensure!(*thickness > 0.0, "shell thickness must be positive");
+                self.build::<N>(inner)?.shell(*thickness)
+            }
+            SdfNode::RepeatLinear {
+                inner,
+                direction,
+                spacing,
+                count,
+            } => {
+                ensure!(*spacing > 0.0, "repeat spacing must be positive");
+                ensure!(*count > 0, "repeat count must be positive");
+                self.build::<N>(inner)?
+                    .repeat_linear(vector(direction)?, *spacing, *count)
+            }
+            SdfNode::RepeatPolar {
+                inner,
+                axis,
+                radial,
+                count,
+            } => {
+                ensure!(*count > 0, "repeat count must be positive");
+                ensure!(
+                    axis.normalize().cross(*radial).length() > 1e-9,
+                    "radial direction must not be parallel to the axis"
+                );
+                cast(self.build::<3>(inner)?.repeat_polar(*axis, *radial, *count))?
+            }
+            SdfNode::Affine { inner, forward } => {
+                let forward = Mat4::from_rows(forward.map(Vector::from));
+                ensure!(forward.as_affine().is_some(), "transform must be affine");
+                ensure!(
+                    forward.linear().determinant() != 0.0,
+                    "transform must be invertible"
+                );
+                cast(Sdf3::new(Transform::new(
+                    Affine::new(forward),
+                    self.build::<3>(inner)?,
+                )))?
+            }
+            SdfNode::Twist { inner, axis, rate } => {
+                let inner = self.build::<3>(inner)?;
+                ensure!(
+                    inner.structural_bounds().is_some(),
+                    "a twisted solid must be bounded"
+                );
+                cast(inner.twist(*axis, *rate))?
+            }
+            SdfNode::Bend {
+                inner,
+                axis,
+                radius,
+            } => {
+                ensure!(*radius != 0.0, "bend radius must be nonzero");
+                let inner = self.build::<3>(inner)?;
+                ensure!(
+                    Bend::inner_radius(*axis, *radius, &inner) > 0.0,
+                    "the solid must not reach the center of the bend"
+                );
+                cast(inner.bend(*axis, *radius))?
+            }
+            SdfNode::Taper {
+                inner,
+                axis,
+                scale_range,
+            } => {
+                ensure!(
+                    scale_range.start > 0.0 && scale_range.end > 0.0,
+                    "taper scales must be positive"
+                );
+                ensure!(axis.length() > 0.0, "taper axis must be nonzero");
+                let inner = self.build::<3>(inner)?;
+                ensure!(
+                    inner.structural_bounds().is_some(),
+                    "a tapered solid must be bounded"
+                );
+                cast(inner.taper(*axis, scale_range.clone()))?
+            }
+            SdfNode::Rotate {
+                inner,
+                origin,
+                axis,
+            } => cast(self.build::<2>(inner)?.rotate(*origin, *axis))?,
+            SdfNode::Extrude {
+                inner,
+                origin,
+                axis1,
+                axis2,
+                distance,
+            } => cast(
+                self.build::<2>(inner)?
+                    .extrude(*origin, *axis1, *axis2, *distance),
+            )?,
+            SdfNode::Slice {
+                inner,
+                origin,
+                axis1,
+                axis2,
+            } => cast(Sdf2::new(Transform::new(
+                Slice::new(*origin, *axis1, *axis2),
+                self.build::<3>(inner)?,
+            )))?,
+            SdfNode::Sweep { pieces } => {
+                ensure!(!pieces.is_empty(), "expected at least one sweep piece");
+                cast(Sdf3::new(SdfSweep::from_pieces(
+                    pieces
+                        .iter()
+                        .map(|(piece, profile)| Ok((piece.clone(), self.build::<2>(profile)?)))
+                        .collect::<anyhow::Result<_>>()?,
+                )))?
+            }
+            SdfNode::Loft {
+                bottom,
+                top,
+                z_range,
+            } => {
+                ensure!(z_range.start < z_range.end, "loft range must be nonempty");
+                cast(
+                    self.build::<2>(bottom)?
+                        .loft(&self.build(top)?, z_range.clone()),
+                )?
+            }
+            SdfNode::Revolve {
+                profile,
+                origin,
+                axis,
+                angle_range,
+            } => {
+                ensure!(
+                    angle_range.start < angle_range.end,
+                    "revolve range must be nonempty"
+                );
+                ensure!(
+                    angle_range.end - angle_range.start < 2.0 * PI,
+                    "revolve range must be less than a full turn"
+                );
+                cast(Sdf3::new(SdfRevolve::new(
+                    self.build(profile)?,
+                    *origin,
+                    *axis,
+                    angle_range.clone(),
+                )))?
+            }
+            SdfNode::Mesh {
+                vertices,
+                triangles,
+            } => {
+                ensure!(!triangles.is_empty(), "mesh must have triangles");
+                ensure!(
+                    triangles.iter().flatten().all(|&v| v < vertices.len()),
+                    "mesh triangle refers to a missing vertex"
+                );
+                cast(
+                    Mesh::new(
+                        vertices.clone(),
+                        triangles
+                            .iter()
+                            .map(|&[v1, v2, v3]| MeshTriangle::new(v1, v2, v3))
+                            .collect(),
+                    )
+                    .as_sdf(),
+                )?
+            }
+            SdfNode::Sphere { center, radius } => NSphere::new(vector(center)?, *radius).as_sdf(),
+            SdfNode::Aabb { min, max } => {
+                cast(Aabb::<3>::new(vector(min)?, vector(max)?).as_sdf())?
+            }
+            SdfNode::Plane { origin, normal } => cast(Plane::new(*origin, *normal).as_sdf())?,
+            SdfNode::CylinderSection { radius, height } => cast(Sdf2::new(SdfLeaf::new(
+                CylinderCrossSection::new(*radius, *height),
+            )))?,
+            SdfNode::TruncatedConeSection { height, r1, r2 } => cast(Sdf2::new(SdfLeaf::new(
+                TruncatedConeCrossSection::new(*height, *r1, *r2),
+            )))?,
+            SdfNode::Polygon { points } => cast(Polygon2::new(points.clone()).as_sdf())?,
+            SdfNode::Torus {
+                origin,
+                axis,
+                major_radius,
+                minor_radius,
+            } => cast(Torus::new(*origin, *axis, *major_radius, *minor_radius).as_sdf())?,
+            SdfNode::Capsule { start, end, radius } => {
+                NCapsule::new(vector(start)?, vector(end)?, *radius).as_sdf()
+            }
+            SdfNode::Ellipsoid { center, radii } => {
+                ensure!(
+                    radii.iter().all(|&r| r > 0.0),
+                    "ellipsoid radii must be positive"
+                );
+                NEllipsoid::new(vector(center)?, vector(radii)?).as_sdf()
+            }
+            SdfNode::RoundedBox { min, max, radius } => {
+                let aabb = Aabb::new(vector(min)?, vector(max)?);
+                ensure!(
+                    aabb.dimensions().into_iter().all(|d| d >= 2.0 * radius),
+                    "rounding radius must fit in the box"
+                );
+                RoundedAabb::new(aabb, *radius).as_sdf()
+            }
+            SdfNode::RegularPolygon {
+                center,
+                radius,
+                sides,
+            } => {
+                ensure!(*sides >= 3, "a polygon needs at least three sides");
+                cast(RegularPolygon::new(*center, *radius, *sides).as_sdf())?
+            }
+            SdfNode::Arc {
+                center,
+                radius,
+                angle_range,
+                width,
+            } => {
+                ensure!(
+                    angle_range.start <= angle_range.end
+                        && angle_range.end - angle_range.start <= 2.0 * PI,
+                    "arc must span between zero and one full turn"
+                );
+                cast(CircularArc::new(*center, *radius, angle_range.clone(), *width).as_sdf())?
+            }
+            SdfNode::Triangle { points } => cast(Triangle3::new(*points).as_sdf())?,
+            SdfNode::Custom { name, params } => {
+                let builder = self
+                    .leaves
+                    .get(&(name.clone(), N))
+                    .with_context(|| format!("no {}D leaf registered as {:?}", N, name))?;
+                *builder(params.clone())?
+                    .downcast::<Sdf<N>>()
+                    .map_err(|_| anyhow!("leaf {:?} built the wrong type", name))?
+            }
+        })
+    }
+    /// Reads a tree written by [Sdf::to_writer].
+    pub fn from_reader<const N: usize>(
+        &self,
+        reader: impl Read,
+        format: SdfFormat,
+    ) -> anyhow::Result<Sdf<N>> {
+        let document: SdfDocument = match format {
+            SdfFormat::Json => serde_json::from_reader(reader)?,
+            SdfFormat::Ron => ron::de::from_reader(reader)?,
+        };
+        if document.version != VERSION {
+            bail!("unsupported sdf format version {}", document.version);
+        }
+        ensure!(
+            document.dimension == N,
+            "expected a {}D sdf, found a {}D sdf",
+            N,
+            document.dimension
+        );
+        self.build(&document.root)
+    }
+}
+
+impl<const N: usize> Sdf<N> {
+    pub fn to_writer(&self, mut writer: impl Write, format: SdfFormat) -> anyhow::Result<()> {
+        let document = SdfDocument {
+            version: VERSION,
+            dimension: N,
+            root: self.node(),
+        };
+        match format {
+            SdfFormat::Json => {
+                // JSON has no NaN or infinity, which serde_json writes as null. Check that the
+                // document reads back rather than writing a file that cannot be loaded.
+                let json = serde_json::to_vec_pretty(&document)?;
+                serde_json::from_slice::<SdfDocument>(&json)
+                    .context("the sdf has a parameter that is not finite")?;
+                writer.write_all(&json)?;
+            }
+            SdfFormat::Ron => ron::Options::default().to_io_writer_pretty(
+                writer,
+                &document,
+                ron::ser::PrettyConfig::default(),
+            )?,
+        }
+        Ok(())
+    }
+    /// See [SdfNode::structural_hash].
+    pub fn structural_hash(&self) -> u64 {
+        self.node().structural_hash()
+    }
+    /// Reads a tree written by [Sdf::to_writer] that only uses built-in leaves.
+    pub fn from_reader(reader: impl Read, format: SdfFormat) -> anyhow::Result<Self> {
+        SdfRegistry::new().from_reader(reader, format)
+    }
+}
+
+#[test]
+fn test_node() {
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Slab {
+        thickness: f64,
+    }
+    impl SdfLeafImpl<3> for Slab {
+        fn evaluate<T: patina_scalar::Scalar>(&self, p: Vector<T, 3>) -> T {
+            p.z().abs() - T::from_f64(self.thickness / 2.0)
+        }
+        fn bounds(&self) -> Option<Aabb<3>> {
+            None
+        }
+        fn node(&self) -> SdfNode {
+            SdfNode::custom("slab", self)
+        }
+    }
+
+    let bracket = Aabb::new(Vec3::splat(-2.0), Vec3::splat(2.0))
+        .as_sdf()
+        .difference(&Sphere::new(Vec3::new(2.0, 2.0, 2.0), 1.5).as_sdf())
+        .union(&Torus::new(Vec3::zero(), Vec3::axis_x(), 3.0, 0.5).as_sdf())
+        .union(&Sdf3::new(SdfLeaf::new(Slab { thickness: 0.5 })))
+        .rotate_axis(Vec3::axis_z(), PI / 6.0)
+        .smooth_union(
+            &Polygon2::new(vec![Vec2::zero(), Vec2::new(3.0, 0.0), Vec2::new(0.0, 3.0)])
+                .as_sdf()
+                .revolve(Vec3::zero(), Vec3::axis_y(), 0.0..PI)
+                .twist(Vec3::axis_z(), 0.1),
+            0.5,
+        )
+        .offset(0.25);
+    let mut registry = SdfRegistry::new();
+    registry.register::<3, Slab>("slab");
+    let samples = [
+        Vec3::zero(),
+        Vec3::new(1.5, 1.0, -0.5),
+        Vec3::new(-3.0, 0.5, 2.0),
+        Vec3::new(0.2, 2.5, 0.3),
+    ];
+    for format in [SdfFormat::Json, SdfFormat::Ron] {
+        let mut buffer = vec![];
+        bracket.to_writer(&mut buffer, format).unwrap();
+        let copy: Sdf3 = registry.from_reader(buffer.as_slice(), format).unwrap();
+        assert_eq!(copy.node(), bracket.node());
+        for p in samples {
+            assert_eq!(copy.evaluate(p), bracket.evaluate(p));
+        }
+        // The custom leaf needs the registry, and the dimension must match.
+        assert!(Sdf3::from_reader(buffer.as_slice(), format).is_err());
+        assert!(
+            registry
+                .from_reader::<2>(buffer.as_slice(), format)
+                .is_err()
+        );
+    }
+
+    // Arguments that the constructors would reject are errors rather than panics.
+    let sphere = Box::new(SdfNode::Sphere {
+        center: vec![0.0; 3],
+        radius: 1.0,
+    });
+    let singular = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    for node in [
+        SdfNode::Shell {
+            inner: sphere.clone(),
+            thickness: 0.0,
+        },
+        SdfNode::Taper {
+            inner: sphere.clone(),
+            axis: Vec3::axis_z(),
+            scale_range: 1.0..0.0,
+        },
+        SdfNode::Affine {
+            inner: sphere.clone(),
+            forward: singular,
+        },
+        SdfNode::Bend {
+            inner: sphere.clone(),
+            axis: Vec3::axis_z(),
+            radius: 0.5,
+        },
+        SdfNode::Twist {
+            inner: Box::new(SdfNode::Plane {
+                origin: Vec3::zero(),
+                normal: Vec3::axis_x(),
+            }),
+            axis: Vec3::axis_z(),
+            rate: 0.1,
+        },
+    ] {
+        assert!(registry.build::<3>(&node).is_err());
+    }
+    let polygon = SdfNode::RegularPolygon {
+        center: Vec2::zero(),
+        radius: 1.0,
+        sides: 2,
+    };
+    assert!(registry.build::<2>(&polygon).is_err());
+    // JSON cannot hold NaN, so it is refused before anything is written.
+    let mut buffer = vec![];
+    let nan = Sphere::new(Vec3::zero(), f64::NAN).as_sdf();
+    assert!(nan.to_writer(&mut buffer, SdfFormat::Json).is_err());
+    assert!(buffer.is_empty());
+}
@@ -0,0 +1,188 @@
+use crate::sdf::bounds;
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Sphere;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+
+/// Expand `p` by `distance` in every direction. Simplifications of the inner SDF (e.g. replacing
+/// a leaf with [Sdf::full]) only preserve its sign, so nodes that move the surface must constrain
+/// the inner SDF over every point that could end up on the new surface.
+fn expand<const N: usize>(p: Vector<DecInterval, N>, distance: f64) -> Vector<DecInterval, N> {
+    let delta = DecInterval::try_from((-distance.abs(), distance.abs())).unwrap();
+    p.map(|x| x + delta)
+}
+
+/// Moves the surface outwards by `distance` (or inwards if `distance` is negative).
+#[derive(Debug)]
+pub struct SdfOffset<const N: usize> {
+    inner: Sdf<N>,
+    distance: f64,
+}
+
+impl<const N: usize> SdfOffset<N> {
+    pub fn new(inner: Sdf<N>, distance: f64) -> Self {
+        SdfOffset { inner, distance }
+    }
+    fn offset<T: Scalar>(&self, d: T) -> T {
+        d - T::from_f64(self.distance)
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfOffset<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.offset(self.inner.evaluate(p))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.offset(self.inner.evaluate_deriv1(p))
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.offset(self.inner.evaluate_deriv2(p))
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.offset(self.inner.evaluate_deriv3(p))
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let (inner, range) = self.inner.evaluate_constrain(expand(p, self.distance));
+        (inner.map(|x| x.offset(self.distance)), self.offset(range))
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        Some(self.offset(self.inner.evaluate_tape(p)))
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        self.inner.evaluate_batch(ps, out);
+        for out in out {
+            *out = self.offset(*out);
+        }
+    }
+
+    fn evaluate_gradient_batch(
+        &self,
+        ps: &[Vector<f64, N>],
+        out: &mut [f64],
+        gradients: &mut [Vector<f64, N>],
+    ) {
+        self.inner.evaluate_gradient_batch(ps, out, gradients);
+        for out in out {
+            *out = self.offset(*out);
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        let bounds = self.inner.structural_bounds()?;
+        Some(bounds::expand(&bounds, self.distance.max(0.0)))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.inner.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Offset {
+            inner: Box::new(self.inner.node()),
+            distance: self.distance,
+        }
+    }
+}
+
+/// A hollow wall of the given thickness whose outer surface is the surface of the inner SDF.
+#[derive(Debug)]
+pub struct SdfShell<const N: usize> {
+    inner: Sdf<N>,
+    thickness: f64,
+}
+
+impl<const N: usize> SdfShell<N> {
+    pub fn new(inner: Sdf<N>, thickness: f64) -> Self {
+        assert!(thickness > 0.0, "shell thickness must be positive");
+        SdfShell { inner, thickness }
+    }
+    fn shell<T: Scalar>(&self, d: T) -> T {
+        let half = T::from_f64(self.thickness / 2.0);
+        (d + half.clone()).abs() - half
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfShell<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.shell(self.inner.evaluate(p))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.shell(self.inner.evaluate_deriv1(p))
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.shell(self.inner.evaluate_deriv2(p))
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.shell(self.inner.evaluate_deriv3(p))
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let (inner, range) = self.inner.evaluate_constrain(expand(p, self.thickness));
+        (inner.map(|x| x.shell(self.thickness)), self.shell(range))
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        Some(self.shell(self.inner.evaluate_tape(p)))
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        self.inner.evaluate_batch(ps, out);
+        for out in out {
+            *out = self.shell(*out);
+        }
+    }
+
+    /// The wall is inside the inner solid.
+    fn bounds(&self) -> Option<Aabb<N>> {
+        self.inner.structural_bounds()
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.inner.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Shell {
+            inner: Box::new(self.inner.node()),
+            thickness: self.thickness,
+        }
+    }
+}
+
+#[test]
+fn test_offset() {
+    let sphere = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let p = Vec3::new(2.0, 0.0, 0.0);
+    assert_eq!(sphere.offset(0.5).evaluate(p), 0.5);
+    assert_eq!(sphere.offset(-0.5).evaluate(p), 1.5);
+    assert_eq!(sphere.shell(0.25).evaluate(Vec3::zero()), 0.75);
+    assert_eq!(
+        sphere.shell(0.25).evaluate(Vec3::new(0.875, 0.0, 0.0)),
+        -0.125
+    );
+    // The cell is entirely inside the sphere but straddles the offset surface.
+    let cell = Vector3::new(
+        DecInterval::try_from((0.6, 0.7)).unwrap(),
+        DecInterval::try_from((-0.05, 0.05)).unwrap(),
+        DecInterval::try_from((-0.05, 0.05)).unwrap(),
+    );
+    let (_, range) = sphere.offset(-0.35).evaluate_constrain(cell);
+    assert!(range.contains(0.0));
+    let (_, range) = sphere.shell(0.35).evaluate_constrain(cell);
+    assert!(range.contains(0.0));
+}
@@ -1,5 +1,7 @@
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
 use patina_geo::geo3::plane::Plane;
 use patina_scalar::Scalar;
 use patina_vec::vec3::Vector3;
@@ -14,4 +16,13 @@ impl SdfLeafImpl<3> for Plane {
     fn evaluate<T: Scalar>(&self, p: Vector3<T>) -> T {
         (p - self.origin().into_scalars::<T>()).dot(self.normal().into_scalars::<T>())
     }
+    fn bounds(&self) -> Option<Aabb<3>> {
+        None
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Plane {
+            origin: self.origin(),
+            normal: self.normal(),
+        }
+    }
 }
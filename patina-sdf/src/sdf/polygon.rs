@@ -1,6 +1,8 @@
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use crate::sdf::{AsSdf, Sdf};
 use itertools::Itertools;
+use patina_geo::aabb::Aabb;
 use patina_geo::geo2::polygon2::Polygon2;
 use patina_scalar::Scalar;
 use patina_vec::vec2::{Vec2, Vector2};
@@ -20,17 +22,27 @@ impl SdfLeafImpl<2> for Polygon2 {
                 .maximum(T::from_f64(0.0));
             let disp = d.clone() - e.clone() * proj.clone();
             sd = sd.minimum(disp.clone().dot(disp.clone()));
+            // Edges cross the horizontal ray when exactly one end is at or below `p`, so a vertex
+            // level with `p` is counted once.
             let a1 = p.y() - v1.y();
-            let a2 = v2.y() - p.y();
+            let a2 = p.y() - v2.y();
             let area = e.cross(d);
             sign = a1.clone().piecewise(
-                a2.clone().piecewise(area.clone().sign(), T::from_f64(1.0)),
-                a2.clone().piecewise(T::from_f64(1.0), -area.clone().sign()),
+                a2.clone().piecewise(T::from_f64(1.0), area.clone().sign()),
+                a2.clone().piecewise(-area.clone().sign(), T::from_f64(1.0)),
             ) * sign;
         }
         let result = sign * sd.sqrt();
         result
     }
+    fn bounds(&self) -> Option<Aabb<2>> {
+        Some(self.points().iter().copied().collect())
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Polygon {
+            points: self.points().to_vec(),
+        }
+    }
 }
 
 impl AsSdf<2> for Polygon2 {
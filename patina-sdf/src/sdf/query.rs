@@ -0,0 +1,80 @@
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::geo3::aabb3::Aabb3;
+use patina_geo::geo3::ray3::Ray3;
+use patina_geo::sphere::Sphere;
+use patina_vec::vec3::Vec3;
+
+/// How close to the surface a query must get before it is considered to have reached it.
+const SURFACE_EPS: f64 = 1e-9;
+const MAX_STEPS: usize = 1000;
+
+impl Sdf<3> {
+    /// The first time `t` in `0.0..=max_t` at which `ray` reaches the surface, in units of the
+    /// length of [Ray3::dir]. A ray that starts inside the solid hits it at `0.0`.
+    ///
+    /// This uses sphere tracing: since the SDF is a lower bound on the distance to the surface,
+    /// stepping by it can never cross the surface, so the first hit is never skipped.
+    pub fn raycast(&self, ray: &Ray3, max_t: f64) -> Option<f64> {
+        let speed = ray.dir().length();
+        assert!(speed > 0.0, "ray direction must be nonzero");
+        let mut t = 0.0;
+        for _ in 0..MAX_STEPS {
+            let d = self.evaluate(ray.at_time(t));
+            if d < SURFACE_EPS {
+                return Some(t);
+            }
+            t += d / speed;
+            if t > max_t {
+                return None;
+            }
+        }
+        None
+    }
+    /// A point on the surface near `point`, found by following the gradient. This is the closest
+    /// point when the SDF is exact near `point`. Returns `None` where the gradient vanishes, such
+    /// as at the center of a sphere, or if the search does not converge.
+    pub fn project(&self, point: Vec3) -> Option<Vec3> {
+        let mut p = point;
+        for _ in 0..MAX_STEPS {
+            let d = self.evaluate_deriv3(p.into_variable());
+            if d.value().abs() < SURFACE_EPS {
+                return Some(p);
+            }
+            let gradient = Vec3::from(*d.deriv());
+            let length2 = gradient.dot(gradient);
+            if length2 == 0.0 || length2.is_nan() {
+                return None;
+            }
+            p = p - gradient * (d.value() / length2);
+        }
+        None
+    }
+}
+
+#[test]
+fn test_query() {
+    let sphere = Sphere::new(Vec3::new(0.0, 0.0, 5.0), 1.0).as_sdf();
+    let ray = Ray3::new(Vec3::zero(), Vec3::new(0.0, 0.0, 2.0));
+    assert!((sphere.raycast(&ray, 10.0).unwrap() - 2.0).abs() < 1e-6);
+    assert_eq!(sphere.raycast(&ray, 1.5), None);
+    let miss = Ray3::new(Vec3::zero(), Vec3::new(1.0, 0.0, 1.0));
+    assert_eq!(sphere.raycast(&miss, 100.0), None);
+    let inside = Ray3::new(Vec3::new(0.0, 0.0, 5.0), Vec3::axis_x());
+    assert_eq!(sphere.raycast(&inside, 10.0), Some(0.0));
+
+    // A grazing ray against a box with a hole through it.
+    let part = Aabb3::new(Vec3::splat(-1.0), Vec3::splat(1.0))
+        .as_sdf()
+        .difference(&Sphere::new(Vec3::zero(), 0.5).as_sdf());
+    let ray = Ray3::new(Vec3::new(-3.0, 0.2, 0.999), Vec3::axis_x());
+    assert!((part.raycast(&ray, 10.0).unwrap() - 2.0).abs() < 1e-6);
+
+    let p = sphere.project(Vec3::new(3.0, 0.0, 5.0)).unwrap();
+    assert!((p - Vec3::new(1.0, 0.0, 5.0)).length() < 1e-6);
+    let p = sphere.project(Vec3::new(0.1, 0.2, 5.3)).unwrap();
+    assert!(sphere.evaluate(p).abs() < 1e-6);
+    assert!((p - Vec3::new(0.1, 0.2, 0.3).normalize() - Vec3::new(0.0, 0.0, 5.0)).length() < 1e-6);
+    assert_eq!(sphere.project(Vec3::new(0.0, 0.0, 5.0)), None);
+    let p = part.project(Vec3::new(0.5, 3.0, 0.2)).unwrap();
+    assert!((p - Vec3::new(0.5, 1.0, 0.2)).length() < 1e-6);
+}
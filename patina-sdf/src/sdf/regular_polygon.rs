@@ -0,0 +1,130 @@
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_geo::geo2::polygon2::Polygon2;
+use patina_scalar::Scalar;
+use patina_vec::vec2::{Vec2, Vector2};
+use patina_vec::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A regular polygon with `sides` vertices on the circle of the given radius around `center`. The
+/// first vertex is in the +x direction from `center`.
+#[derive(Debug, Clone)]
+pub struct RegularPolygon {
+    center: Vec2,
+    radius: f64,
+    sides: usize,
+    polygon: Polygon2,
+}
+
+impl RegularPolygon {
+    pub fn new(center: Vec2, radius: f64, sides: usize) -> Self {
+        assert!(sides >= 3, "a polygon needs at least three sides");
+        let polygon = Polygon2::new(
+            (0..sides)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f64 / sides as f64;
+                    center + Vec2::new(angle.cos(), angle.sin()) * radius
+                })
+                .collect(),
+        );
+        RegularPolygon {
+            center,
+            radius,
+            sides,
+            polygon,
+        }
+    }
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+    pub fn sides(&self) -> usize {
+        self.sides
+    }
+    /// The distance from the center to the middle of each side.
+    pub fn apothem(&self) -> f64 {
+        self.radius * (PI / self.sides as f64).cos()
+    }
+    pub fn polygon(&self) -> &Polygon2 {
+        &self.polygon
+    }
+}
+
+impl SdfLeafImpl<2> for RegularPolygon {
+    fn evaluate<T: Scalar>(&self, p: Vector2<T>) -> T {
+        self.polygon.evaluate(p)
+    }
+    fn bounds(&self) -> Option<Aabb<2>> {
+        self.polygon.bounds()
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::RegularPolygon {
+            center: self.center,
+            radius: self.radius,
+            sides: self.sides,
+        }
+    }
+}
+
+impl AsSdf<2> for RegularPolygon {
+    fn as_sdf(&self) -> Sdf<2> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+/// A [RegularPolygon] centered on `origin` and extruded along `axis`. The first vertex is in the
+/// direction of [Vec3::perpendicular] of `axis`.
+#[derive(Debug, Clone)]
+pub struct RegularPrism {
+    origin: Vec3,
+    axis: Vec3,
+    radius: f64,
+    sides: usize,
+}
+
+impl RegularPrism {
+    pub fn new(origin: Vec3, axis: Vec3, radius: f64, sides: usize) -> Self {
+        RegularPrism {
+            origin,
+            axis,
+            radius,
+            sides,
+        }
+    }
+    /// A hexagonal prism with the given distance across flats.
+    pub fn hexagonal(origin: Vec3, axis: Vec3, across_flats: f64) -> Self {
+        Self::new(origin, axis, across_flats / 3.0f64.sqrt(), 6)
+    }
+}
+
+impl AsSdf<3> for RegularPrism {
+    fn as_sdf(&self) -> Sdf<3> {
+        let u = self.axis.perpendicular();
+        RegularPolygon::new(Vec2::zero(), self.radius, self.sides)
+            .as_sdf()
+            .extrude(
+                self.origin,
+                u,
+                self.axis.normalize().cross(u),
+                self.axis.length(),
+            )
+    }
+}
+
+#[test]
+fn test_regular_polygon() {
+    let hexagon = RegularPolygon::new(Vec2::zero(), 2.0, 6);
+    let sdf = hexagon.as_sdf();
+    assert!((sdf.evaluate(Vec2::zero()) + hexagon.apothem()).abs() < 1e-12);
+    assert!(sdf.evaluate(Vec2::new(2.0, 0.0)).abs() < 1e-12);
+    assert!((sdf.evaluate(Vec2::new(0.0, 3.0)) - (3.0 - 3.0f64.sqrt())).abs() < 1e-12);
+
+    let nut = RegularPrism::hexagonal(Vec3::zero(), Vec3::new(0.0, 0.0, 5.0), 10.0).as_sdf();
+    assert!((nut.evaluate(Vec3::new(0.0, 0.0, 2.5)) + 2.5).abs() < 1e-12);
+    assert!((nut.evaluate(Vec3::new(0.0, 6.0, 2.0)) - 1.0).abs() < 1e-12);
+    assert!((nut.evaluate(Vec3::new(0.0, 0.0, 7.0)) - 2.0).abs() < 1e-12);
+}
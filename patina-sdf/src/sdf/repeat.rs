@@ -0,0 +1,373 @@
+use crate::sdf::bounds::{map_corners, revolved_bounds};
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::{TapeScalar, TapeValue};
+use crate::sdf::{AsSdf, Sdf, Sdf3, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Sphere;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+use std::f64::consts::PI;
+use std::ops::RangeInclusive;
+
+/// `count` copies of the inner SDF placed `spacing` apart along `direction`, starting at the
+/// origin. Each point is measured against the copy of its own cell and the copies either side of
+/// it, so the inner solid may reach into the neighbouring cells but must lie within a spacing of
+/// the origin along `direction`.
+#[derive(Debug)]
+pub struct SdfRepeatLinear<const N: usize> {
+    inner: Sdf<N>,
+    direction: Vector<f64, N>,
+    spacing: f64,
+    count: usize,
+}
+
+impl<const N: usize> SdfRepeatLinear<N> {
+    pub fn new(inner: Sdf<N>, direction: Vector<f64, N>, spacing: f64, count: usize) -> Self {
+        assert!(spacing > 0.0);
+        assert!(count > 0);
+        SdfRepeatLinear {
+            inner,
+            direction: direction.normalize(),
+            spacing,
+            count,
+        }
+    }
+    /// The copy whose cell contains the point `t` along the direction.
+    fn index(&self, t: f64) -> usize {
+        (t / self.spacing)
+            .round()
+            .clamp(0.0, (self.count - 1) as f64) as usize
+    }
+    /// The copies that may be nearest to a point in the cell of copy `index`.
+    fn neighbors(&self, index: usize) -> RangeInclusive<usize> {
+        index.saturating_sub(1)..=(index + 1).min(self.count - 1)
+    }
+    fn fold<T: Scalar>(&self, p: Vector<T, N>, index: usize) -> Vector<T, N> {
+        p - self.direction.into_scalars() * T::from_f64(index as f64 * self.spacing)
+    }
+    fn evaluate_copies<T: Scalar>(
+        &self,
+        p: Vector<T, N>,
+        t: f64,
+        evaluate: impl Fn(Vector<T, N>) -> T,
+    ) -> T {
+        self.neighbors(self.index(t))
+            .map(|index| evaluate(self.fold(p.clone(), index)))
+            .reduce(T::minimum)
+            .unwrap()
+    }
+    fn evaluate_deriv<const D: usize>(
+        &self,
+        p: Vector<Deriv<D>, N>,
+        evaluate: impl Fn(Vector<Deriv<D>, N>) -> Deriv<D>,
+    ) -> Deriv<D> {
+        let t = p.clone().map(|x| x.value()).dot(self.direction);
+        self.evaluate_copies(p, t, evaluate)
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfRepeatLinear<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.evaluate_copies(p, p.dot(self.direction), |p| self.inner.evaluate(p))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv1(p))
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv2(p))
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv3(p))
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let t = p.dot(self.direction.into_scalars());
+        let first = *self.neighbors(self.index(t.inf())).start();
+        let last = *self.neighbors(self.index(t.sup())).end();
+        let shift =
+            DecInterval::try_from((first as f64 * self.spacing, last as f64 * self.spacing))
+                .unwrap();
+        let folded = p - self.direction.into_scalars() * shift;
+        let (inner, range) = self.inner.evaluate_constrain(folded);
+        (
+            inner.map(|inner| inner.repeat_linear(self.direction, self.spacing, self.count)),
+            range,
+        )
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        let t = p.clone().dot(self.direction.into_scalars());
+        let clamp = |index: TapeValue| {
+            index
+                .maximum(TapeValue::from_f64(0.0))
+                .minimum(TapeValue::from_f64((self.count - 1) as f64))
+        };
+        let index = clamp((t / TapeValue::from_f64(self.spacing)).round());
+        // The same copies as [SdfRepeatLinear::neighbors], with the ends clamped onto their
+        // neighbours rather than dropped.
+        let offsets = if self.count > 1 { -1..=1 } else { 0..=0 };
+        offsets
+            .map(|offset| {
+                let index = clamp(index.clone() + TapeValue::from_f64(offset as f64));
+                let shift = index * TapeValue::from_f64(self.spacing);
+                self.inner
+                    .evaluate_tape(p.clone() - self.direction.into_scalars() * shift)
+            })
+            .reduce(TapeValue::minimum)
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        let bounds = self.inner.structural_bounds()?;
+        let last = self.direction * (self.spacing * (self.count - 1) as f64);
+        Some(bounds.union(&map_corners(&bounds, |p| p + last)))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.inner.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::RepeatLinear {
+            inner: Box::new(self.inner.node()),
+            direction: self.direction.into_iter().collect(),
+            spacing: self.spacing,
+            count: self.count,
+        }
+    }
+}
+
+/// `count` copies of the inner SDF rotated evenly around `axis` through the origin. Each point is
+/// rotated into the sector around the first copy, which is centered on the direction `radial`
+/// (projected perpendicular to `axis`), and into the sectors either side of it. The inner solid
+/// may reach into those neighbouring sectors but no further.
+#[derive(Debug)]
+pub struct SdfRepeatPolar {
+    inner: Sdf3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    count: usize,
+}
+
+impl SdfRepeatPolar {
+    pub fn new(inner: Sdf3, axis: Vec3, radial: Vec3, count: usize) -> Self {
+        assert!(count > 0);
+        let w = axis.normalize();
+        let u = radial - w * radial.dot(w);
+        assert!(u.length() > 1e-9, "radial direction must not be parallel to the axis");
+        let u = u.normalize();
+        let v = w.cross(u);
+        SdfRepeatPolar {
+            inner,
+            u,
+            v,
+            w,
+            count,
+        }
+    }
+    fn sector(&self) -> f64 {
+        2.0 * PI / self.count as f64
+    }
+    fn index(&self, u: f64, v: f64) -> usize {
+        ((v.atan2(u) / self.sector()).round() as isize).rem_euclid(self.count as isize) as usize
+    }
+    /// The offsets from the sector of a point to the copies that may be nearest to it.
+    fn offsets(&self) -> RangeInclusive<isize> {
+        match self.count {
+            1 => 0..=0,
+            2 => 0..=1,
+            _ => -1..=1,
+        }
+    }
+    fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> {
+        let count = self.count as isize;
+        self.offsets()
+            .map(move |offset| (index as isize + offset).rem_euclid(count) as usize)
+    }
+    fn fold<T: Scalar>(&self, p: Vector3<T>, index: usize) -> Vector3<T> {
+        let angle = index as f64 * self.sector();
+        let (sin, cos) = (T::from_f64(angle.sin()), T::from_f64(angle.cos()));
+        let pu = p.clone().dot(self.u.into_scalars());
+        let pv = p.clone().dot(self.v.into_scalars());
+        let pw = p.dot(self.w.into_scalars());
+        self.u.into_scalars() * (pu.clone() * cos.clone() + pv.clone() * sin.clone())
+            + self.v.into_scalars() * (pv * cos - pu * sin)
+            + self.w.into_scalars() * pw
+    }
+    fn evaluate_copies<T: Scalar>(
+        &self,
+        p: Vector3<T>,
+        index: usize,
+        evaluate: impl Fn(Vector3<T>) -> T,
+    ) -> T {
+        self.neighbors(index)
+            .map(|index| evaluate(self.fold(p.clone(), index)))
+            .reduce(T::minimum)
+            .unwrap()
+    }
+    fn evaluate_deriv<const D: usize>(
+        &self,
+        p: Vector3<Deriv<D>>,
+        evaluate: impl Fn(Vector3<Deriv<D>>) -> Deriv<D>,
+    ) -> Deriv<D> {
+        let value = p.clone().map(|x| x.value());
+        let index = self.index(value.dot(self.u), value.dot(self.v));
+        self.evaluate_copies(p, index, evaluate)
+    }
+}
+
+impl SdfImpl<3> for SdfRepeatPolar {
+    fn evaluate(&self, p: Vector<f64, 3>) -> f64 {
+        let index = self.index(p.dot(self.u), p.dot(self.v));
+        self.evaluate_copies(p, index, |p| self.inner.evaluate(p))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, 3>) -> Deriv<1> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv1(p))
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, 3>) -> Deriv<2> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv2(p))
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, 3>) -> Deriv<3> {
+        self.evaluate_deriv(p, |p| self.inner.evaluate_deriv3(p))
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, 3>) -> (Option<Sdf3>, DecInterval) {
+        let pu = p.dot(self.u.into_scalars());
+        let pv = p.dot(self.v.into_scalars());
+        let corners = [
+            (pu.inf(), pv.inf()),
+            (pu.inf(), pv.sup()),
+            (pu.sup(), pv.inf()),
+            (pu.sup(), pv.sup()),
+        ];
+        let index = self.index(corners[0].0, corners[0].1);
+        // Sectors are convex, so if every corner is in one sector then so is the whole region, and
+        // the inner SDF only needs to cover the region folded into that sector and its neighbours.
+        let folded = if !(pu.contains(0.0) && pv.contains(0.0))
+            && corners.iter().all(|&(u, v)| self.index(u, v) == index)
+        {
+            self.neighbors(index)
+                .map(|index| self.fold(p, index))
+                .reduce(|a, b| a.zip_with(b, DecInterval::convex_hull))
+                .unwrap()
+        } else {
+            let radius = corners
+                .iter()
+                .map(|&(u, v)| (u * u + v * v).sqrt())
+                .fold(0.0, f64::max);
+            let disk = DecInterval::try_from((-radius, radius)).unwrap();
+            let pw = p.dot(self.w.into_scalars());
+            self.u.into_scalars() * disk + self.v.into_scalars() * disk + self.w.into_scalars() * pw
+        };
+        let (inner, range) = self.inner.evaluate_constrain(folded);
+        (
+            inner.map(|inner| Sdf::new(SdfRepeatPolar { inner, ..*self })),
+            range,
+        )
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, 3>) -> Option<TapeValue> {
+        let pu = p.clone().dot(self.u.into_scalars());
+        let pv = p.clone().dot(self.v.into_scalars());
+        let pw = p.dot(self.w.into_scalars());
+        // The sector of the nearest copy, as in [SdfRepeatPolar::index].
+        let index = (pv.clone().atan2(pu.clone()) / TapeValue::from_f64(self.sector())).round();
+        // The index is a whole number, so testing it plus a half avoids a tie at zero.
+        let index = (index.clone() + TapeValue::from_f64(0.5)).piecewise(
+            index.clone() + TapeValue::from_f64(self.count as f64),
+            index,
+        );
+        // The angles of neighbouring sectors need no wrapping, since only their sines and cosines
+        // are used.
+        self.offsets()
+            .map(|offset| {
+                let angle = (index.clone() + TapeValue::from_f64(offset as f64))
+                    * TapeValue::from_f64(self.sector());
+                let (sin, cos) = (angle.clone().sin(), angle.cos());
+                let folded = self.u.into_scalars()
+                    * (pu.clone() * cos.clone() + pv.clone() * sin.clone())
+                    + self.v.into_scalars() * (pv.clone() * cos - pu.clone() * sin)
+                    + self.w.into_scalars() * pw.clone();
+                self.inner.evaluate_tape(folded)
+            })
+            .reduce(TapeValue::minimum)
+    }
+
+    fn bounds(&self) -> Option<Aabb<3>> {
+        Some(revolved_bounds(
+            Vec3::zero(),
+            self.w,
+            &self.inner.structural_bounds()?,
+        ))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.inner.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::RepeatPolar {
+            inner: Box::new(self.inner.node()),
+            axis: self.w,
+            radial: self.u,
+            count: self.count,
+        }
+    }
+}
+
+#[test]
+fn test_repeat() {
+    let sphere = Sphere::new(Vec3::zero(), 1.0).as_sdf();
+    let row = sphere.repeat_linear(Vec3::axis_x(), 3.0, 5);
+    assert_eq!(row.evaluate(Vec3::new(6.0, 0.0, 0.0)), -1.0);
+    assert_eq!(row.evaluate(Vec3::new(7.5, 0.0, 0.0)), 0.5);
+    assert_eq!(row.evaluate(Vec3::new(20.0, 0.0, 0.0)), 7.0);
+    assert_eq!(row.evaluate(Vec3::new(-5.0, 0.0, 0.0)), 4.0);
+
+    let axis = Vec3::axis_z();
+    let radial = Vec3::new(1.0, 1.0, 0.0).normalize();
+    let ring = sphere.translate(radial * 5.0).repeat_polar(axis, radial, 6);
+    for i in 0..6 {
+        let angle = i as f64 * PI / 3.0;
+        let center = radial * (5.0 * angle.cos()) + axis.cross(radial) * (5.0 * angle.sin());
+        assert!((ring.evaluate(center) + 1.0).abs() < 1e-12);
+        let between = radial * (5.0 * (angle + PI / 6.0).cos())
+            + axis.cross(radial) * (5.0 * (angle + PI / 6.0).sin());
+        assert!(ring.evaluate(between) > 0.0);
+    }
+    let cell = Vector3::new(
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+    );
+    let (_, range) = ring.evaluate_constrain(cell);
+    assert!(!range.contains(0.0));
+
+    // Copies that reach past the edge of their cell are still found from the neighbouring cell.
+    let row = sphere
+        .translate(Vec3::new(1.0, 0.0, 0.0))
+        .repeat_linear(Vec3::axis_x(), 3.0, 3);
+    assert!((row.evaluate(Vec3::new(1.6, 0.0, 0.0)) + 0.4).abs() < 1e-12);
+    let ring = sphere
+        .translate(Vec3::new(5.0, 1.5, 0.0))
+        .repeat_polar(axis, Vec3::axis_x(), 8);
+    let inside = Vec3::new(4.6, 2.0, 0.0);
+    assert!((ring.evaluate(inside) - (0.41f64.sqrt() - 1.0)).abs() < 1e-12);
+    for sdf in [&row, &ring] {
+        let compiled = sdf.compile();
+        for i in 0..50 {
+            let t = i as f64;
+            let p = Vec3::new((t * 0.7).sin() * 8.0, (t * 1.3).cos() * 8.0, 0.1);
+            assert!((compiled.evaluate(p) - sdf.evaluate(p)).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::sdf::bounds::{cylinder_bounds, is_empty};
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, Sdf2, Sdf3, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Circle;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::{Vec2, Vector2};
+use patina_vec::vec3::{Vec3, Vector3};
+use std::f64::consts::PI;
+use std::ops::Range;
+
+/// One of the flat ends of a partial revolution: the half plane spanned by `radial` and the axis.
+/// The solid lies on the `tangent` side of the start cap and the opposite side of the end cap.
+#[derive(Debug, Clone)]
+struct RevolveCap {
+    radial: Vec3,
+    tangent: Vec3,
+}
+
+impl RevolveCap {
+    fn new(axis: Vec3, u: Vec3, v: Vec3, angle: f64) -> Self {
+        let radial = u * angle.cos() + v * angle.sin();
+        RevolveCap {
+            radial,
+            tangent: axis.cross(radial),
+        }
+    }
+    /// The coordinates of `r` in the plane of the cap, and the distance from the plane.
+    fn project<T: Scalar>(&self, r: Vector3<T>) -> (T, T) {
+        (
+            r.clone().dot(self.radial.into_scalars()),
+            r.dot(self.tangent.into_scalars()).abs(),
+        )
+    }
+}
+
+/// The profile revolved around `axis` through `origin` from `angle_range.start` to
+/// `angle_range.end`, measured counterclockwise from [Vec3::perpendicular] of `axis`. As with
+/// [Sdf2::rotate], the profile's x coordinate is the distance from the axis and its y coordinate
+/// is the height along the axis.
+#[derive(Debug)]
+pub struct SdfRevolve {
+    profile: Sdf2,
+    origin: Vec3,
+    axis: Vec3,
+    angle_range: Range<f64>,
+    middle: Vec3,
+    caps: [RevolveCap; 2],
+}
+
+impl SdfRevolve {
+    pub fn new(profile: Sdf2, origin: Vec3, axis: Vec3, angle_range: Range<f64>) -> Self {
+        assert!(
+            angle_range.start < angle_range.end,
+            "revolve range must be nonempty"
+        );
+        assert!(
+            angle_range.end - angle_range.start < 2.0 * PI,
+            "use Sdf2::rotate for a full revolution"
+        );
+        let axis = axis.normalize();
+        let u = axis.perpendicular();
+        let v = axis.cross(u);
+        SdfRevolve {
+            profile,
+            origin,
+            axis,
+            middle: RevolveCap::new(axis, u, v, (angle_range.start + angle_range.end) / 2.0).radial,
+            caps: [
+                RevolveCap::new(axis, u, v, angle_range.start),
+                RevolveCap::new(axis, u, v, angle_range.end),
+            ],
+            angle_range,
+        }
+    }
+    /// The 2D points where the profile is evaluated: the revolved coordinates of `p` followed by
+    /// its coordinates in the plane of each cap.
+    fn profile_points<T: Scalar>(&self, p: Vector3<T>) -> [Vector2<T>; 3] {
+        let rel = p - self.origin.into_scalars();
+        let h = rel.clone().dot(self.axis.into_scalars());
+        let r = rel - self.axis.into_scalars() * h.clone();
+        let [start, end] = self.caps.each_ref().map(|cap| cap.project(r.clone()).0);
+        [
+            Vector2::new(r.length(), h.clone()),
+            Vector2::new(start, h.clone()),
+            Vector2::new(end, h),
+        ]
+    }
+    fn evaluate_with<T: Scalar>(&self, p: Vector3<T>, profile: impl Fn(Vector2<T>) -> T) -> T {
+        let rel = p.clone() - self.origin.into_scalars();
+        let h = rel.clone().dot(self.axis.into_scalars());
+        let r = rel - self.axis.into_scalars() * h;
+        let rho = r.clone().length();
+        let [revolved, start, end] = self.profile_points(p).map(profile);
+        // Positive outside the wedge between the caps. Comparing cosines avoids the branch cut of
+        // an angle, which would straddle any region behind the wedge.
+        let half_angle = (self.angle_range.end - self.angle_range.start) / 2.0;
+        let outside =
+            rho.clone() * T::from_f64(half_angle.cos()) - r.clone().dot(self.middle.into_scalars());
+        let [start_plane, end_plane] = self.caps.each_ref().map(|cap| cap.project(r.clone()));
+        // Inside the wedge, the caps are at least as far as the half planes that contain them.
+        let half_plane = |(x, w): (T, T)| x.piecewise(rho.clone(), w);
+        let inside = revolved
+            .maximum(-half_plane(start_plane.clone()))
+            .maximum(-half_plane(end_plane.clone()));
+        // Outside the wedge, the nearest point is on one of the flat caps.
+        let cap = |d2: T, (_, w): (T, T)| {
+            d2.clone()
+                .piecewise(w.clone(), Vector2::new(d2, w).length())
+        };
+        let capped = cap(start, start_plane).minimum(cap(end, end_plane));
+        outside.piecewise(inside, capped)
+    }
+}
+
+impl SdfImpl<3> for SdfRevolve {
+    fn evaluate(&self, p: Vector<f64, 3>) -> f64 {
+        self.evaluate_with(p, |q| self.profile.evaluate(q))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, 3>) -> Deriv<1> {
+        self.evaluate_with(p, |q| self.profile.evaluate_deriv1(q))
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, 3>) -> Deriv<2> {
+        self.evaluate_with(p, |q| self.profile.evaluate_deriv2(q))
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, 3>) -> Deriv<3> {
+        self.evaluate_with(p, |q| self.profile.evaluate_deriv3(q))
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, 3>) -> (Option<Sdf3>, DecInterval) {
+        let range = self.evaluate_with(p, |q| self.profile.evaluate_constrain(q).1);
+        // The simplified profile must be valid at every point where the profile is evaluated.
+        let hull = self
+            .profile_points(p)
+            .into_iter()
+            .reduce(|a, b| Vector2::new(a.x().convex_hull(b.x()), a.y().convex_hull(b.y())))
+            .unwrap();
+        let (profile, _) = self.profile.evaluate_constrain(hull);
+        (
+            profile.map(|profile| {
+                Sdf::new(SdfRevolve::new(
+                    profile,
+                    self.origin,
+                    self.axis,
+                    self.angle_range.clone(),
+                ))
+            }),
+            range,
+        )
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, 3>) -> Option<TapeValue> {
+        Some(self.evaluate_with(p, |q| self.profile.evaluate_tape(q)))
+    }
+
+    fn bounds(&self) -> Option<Aabb<3>> {
+        let profile = self.profile.structural_bounds()?;
+        if is_empty(&profile) {
+            return Some(Aabb::empty());
+        }
+        Some(cylinder_bounds(
+            self.origin,
+            self.axis,
+            profile.max().x(),
+            profile.min().y()..profile.max().y(),
+        ))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.profile.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Revolve {
+            profile: Box::new(self.profile.node()),
+            origin: self.origin,
+            axis: self.axis,
+            angle_range: self.angle_range.clone(),
+        }
+    }
+}
+
+impl Sdf2 {
+    /// Revolves this profile part of the way around `axis`, with flat caps at each end. See
+    /// [SdfRevolve].
+    pub fn revolve(&self, origin: Vec3, axis: Vec3, angle_range: Range<f64>) -> Sdf3 {
+        if angle_range.end - angle_range.start >= 2.0 * PI {
+            return self.rotate(origin, axis);
+        }
+        Sdf::new(SdfRevolve::new(self.clone(), origin, axis, angle_range))
+    }
+}
+
+#[test]
+fn test_revolve() {
+    let profile = Circle::new(Vec2::new(5.0, 0.0), 1.0).as_sdf();
+    let elbow = profile.revolve(Vec3::zero(), Vec3::axis_z(), 0.0..PI / 2.0);
+    let u = Vec3::axis_z().perpendicular();
+    let v = Vec3::axis_z().cross(u);
+    let at = |angle: f64, radius: f64| (u * angle.cos() + v * angle.sin()) * radius;
+    assert!((elbow.evaluate(at(PI / 4.0, 5.0)) + 1.0).abs() < 1e-12);
+    assert!((elbow.evaluate(at(PI / 4.0, 6.5)) - 0.5).abs() < 1e-12);
+    // Near the caps, inside and out.
+    assert!((elbow.evaluate(at(0.05, 5.0)) + 5.0 * 0.05f64.sin()).abs() < 1e-12);
+    assert!((elbow.evaluate(at(-0.1, 5.0)) - 5.0 * 0.1f64.sin()).abs() < 1e-12);
+    assert!((elbow.evaluate(at(PI / 2.0 + 0.1, 5.0)) - 5.0 * 0.1f64.sin()).abs() < 1e-12);
+    // Opposite the elbow, the nearest point is on the rim of a cap.
+    let far = at(PI * 1.25, 5.0);
+    let rim = u * 4.0;
+    assert!((elbow.evaluate(far) - far.distance(rim)).abs() < 1e-9);
+
+    let trim = profile.revolve(Vec3::zero(), Vec3::axis_z(), -PI..PI / 2.0);
+    assert!(trim.evaluate(at(-PI * 0.9, 5.0)) < 0.0);
+    assert!(trim.evaluate(at(PI * 0.75, 5.0)) > 0.0);
+
+    let region = Vector3::new(
+        DecInterval::try_from((-6.0, -4.0)).unwrap(),
+        DecInterval::try_from((-6.0, -4.0)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+    );
+    let (_, range) = elbow.evaluate_constrain(region);
+    assert!(range.inf() > 0.0);
+}
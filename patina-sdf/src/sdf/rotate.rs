@@ -1,6 +1,9 @@
+use crate::sdf::bounds::cylinder_bounds;
+use crate::sdf::node::SdfNode;
 use crate::sdf::transform::TransformImpl;
 use crate::sdf::{Sdf, Sdf2, SdfImpl};
 use inari::DecInterval;
+use patina_geo::aabb::Aabb;
 use patina_scalar::Scalar;
 use patina_scalar::deriv::Deriv;
 use patina_vec::vec::Vector;
@@ -28,4 +31,19 @@ impl TransformImpl<2, 3> for Rotate {
         let radius = (relative - self.axis.into_scalars() * elevation.clone()).length();
         inner(Vector::<T, 2>::new(radius, elevation))
     }
+    fn bounds(&self, inner: &Aabb<2>) -> Option<Aabb<3>> {
+        Some(cylinder_bounds(
+            self.origin,
+            self.axis,
+            inner.max().x(),
+            inner.min().y()..inner.max().y(),
+        ))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Rotate {
+            inner: Box::new(inner),
+            origin: self.origin,
+            axis: self.axis,
+        }
+    }
 }
@@ -0,0 +1,77 @@
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_scalar::Scalar;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+
+/// An axis-aligned box whose edges and corners are rounded with the given radius. The rounded
+/// solid stays inside `aabb`.
+#[derive(Debug, Clone)]
+pub struct RoundedAabb<const N: usize> {
+    aabb: Aabb<N>,
+    radius: f64,
+}
+
+pub type RoundedBox = RoundedAabb<3>;
+pub type RoundedRect = RoundedAabb<2>;
+
+impl<const N: usize> RoundedAabb<N> {
+    pub fn new(aabb: Aabb<N>, radius: f64) -> Self {
+        assert!(
+            aabb.dimensions().into_iter().all(|d| d >= 2.0 * radius),
+            "rounding radius must fit in the box"
+        );
+        RoundedAabb { aabb, radius }
+    }
+    pub fn aabb(&self) -> &Aabb<N> {
+        &self.aabb
+    }
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+impl<const N: usize> SdfLeafImpl<N> for RoundedAabb<N> {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
+        let center = self.aabb.center().into_scalars::<T>();
+        let inner = (self.aabb.dimensions() / 2.0).map(|x| x - self.radius);
+        let delta = (p - center).abs() - inner.into_scalars();
+        let outside = delta.clone().maximum(Vector::zero()).length();
+        let inside = delta
+            .into_iter()
+            .reduce(T::maximum)
+            .unwrap()
+            .minimum(T::from_f64(0.0));
+        outside + inside - T::from_f64(self.radius)
+    }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        Some(self.aabb)
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::RoundedBox {
+            min: self.aabb.min().into_iter().collect(),
+            max: self.aabb.max().into_iter().collect(),
+            radius: self.radius,
+        }
+    }
+}
+
+impl<const N: usize> AsSdf<N> for RoundedAabb<N> {
+    fn as_sdf(&self) -> Sdf<N> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+#[test]
+fn test_rounded_box() {
+    let rounded = RoundedBox::new(Aabb::new(Vec3::splat(-2.0), Vec3::splat(2.0)), 1.0).as_sdf();
+    assert_eq!(rounded.evaluate(Vec3::zero()), -2.0);
+    assert_eq!(rounded.evaluate(Vec3::new(3.0, 0.0, 0.5)), 1.0);
+    assert!((rounded.evaluate(Vec3::splat(2.0)) - (3.0f64.sqrt() - 1.0)).abs() < 1e-12);
+    let rect = RoundedRect::new(Aabb::new(Vec2::zero(), Vec2::new(4.0, 2.0)), 0.5).as_sdf();
+    assert_eq!(rect.evaluate(Vec2::new(2.0, 1.5)), -0.5);
+    assert_eq!(rect.evaluate(Vec2::new(4.5, 1.0)), 0.5);
+}
@@ -0,0 +1,101 @@
+use crate::sdf::bounds::{corners, is_empty, map_corners};
+use crate::sdf::node::{SdfNode, SdfRegistry};
+use crate::sdf::transform::TransformImpl;
+use crate::sdf::{AsSdf, Sdf2};
+use patina_geo::aabb::Aabb;
+use patina_geo::geo3::plane::Plane;
+use patina_geo::sphere::Sphere;
+use patina_scalar::Scalar;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::Vec3;
+
+/// The cross-section of a 3D solid by the plane through `origin` spanned by the orthonormal
+/// `axis1` and `axis2`. Distances within the plane are at least the distances in 3D, so the
+/// result is a bound rather than an exact SDF.
+#[derive(Debug, Clone)]
+pub struct Slice {
+    origin: Vec3,
+    axis1: Vec3,
+    axis2: Vec3,
+}
+
+impl Slice {
+    pub fn new(origin: Vec3, axis1: Vec3, axis2: Vec3) -> Self {
+        Slice {
+            origin,
+            axis1,
+            axis2,
+        }
+    }
+    /// Slices by `plane`, with `axis1` as close to the X axis as possible, or to the Y axis when
+    /// the plane is perpendicular to X. The Z plane keeps the X and Y coordinates.
+    pub fn from_plane(plane: &Plane) -> Self {
+        let normal = plane.normal();
+        let mut axis1 = Vec3::axis_x() - normal * normal.x();
+        if axis1.length() < 1e-6 {
+            axis1 = Vec3::axis_y() - normal * normal.y();
+        }
+        let axis1 = axis1.normalize();
+        Slice::new(plane.origin(), axis1, normal.cross(axis1))
+    }
+}
+
+impl TransformImpl<3, 2> for Slice {
+    fn evaluate<T: Scalar>(&self, p: Vector<T, 2>, inner: impl FnOnce(Vector<T, 3>) -> T) -> T {
+        inner(
+            self.origin.into_scalars()
+                + self.axis1.into_scalars() * p.x().clone()
+                + self.axis2.into_scalars() * p.y().clone(),
+        )
+    }
+    fn bounds(&self, inner: &Aabb<3>) -> Option<Aabb<2>> {
+        let normal = self.axis1.cross(self.axis2);
+        let sides = corners(inner)
+            .map(|corner| normal.dot(corner - self.origin))
+            .collect::<Vec<_>>();
+        if sides.iter().all(|&side| side > 0.0) || sides.iter().all(|&side| side < 0.0) {
+            return Some(Aabb::empty());
+        }
+        Some(map_corners(inner, |p| {
+            Vec2::new(
+                (p - self.origin).dot(self.axis1),
+                (p - self.origin).dot(self.axis2),
+            )
+        }))
+    }
+    fn node(&self, inner: SdfNode) -> SdfNode {
+        SdfNode::Slice {
+            inner: Box::new(inner),
+            origin: self.origin,
+            axis1: self.axis1,
+            axis2: self.axis2,
+        }
+    }
+}
+
+#[test]
+fn test_slice() {
+    let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 2.0).as_sdf();
+    let slice = sphere.slice_z(3.0);
+    assert_eq!(slice.evaluate(Vec2::new(1.0, 2.0)), -2.0);
+    assert_eq!(slice.evaluate(Vec2::new(3.0, 2.0)), 0.0);
+    let slice = sphere.slice_z(3.0 + 3f64.sqrt());
+    assert!(slice.evaluate(Vec2::new(2.0, 2.0)).abs() < 1e-12);
+
+    // The X plane keeps the Y and Z coordinates.
+    let slice = sphere.slice(&Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::axis_x()));
+    assert_eq!(slice.evaluate(Vec2::new(2.0, 3.0)), -2.0);
+    assert_eq!(slice.evaluate(Vec2::new(4.0, 3.0)), 0.0);
+    assert_eq!(slice.evaluate(Vec2::new(2.0, 5.0)), 0.0);
+    let bounds = slice.structural_bounds().unwrap();
+    assert_eq!(bounds.min(), Vec2::new(0.0, 1.0));
+    assert_eq!(bounds.max(), Vec2::new(4.0, 5.0));
+
+    assert!(is_empty(&sphere.slice_z(10.0).structural_bounds().unwrap()));
+    let copy: Sdf2 = SdfRegistry::new().build(&slice.node()).unwrap();
+    assert_eq!(
+        copy.evaluate(Vec2::new(2.5, 3.5)),
+        slice.evaluate(Vec2::new(2.5, 3.5))
+    );
+}
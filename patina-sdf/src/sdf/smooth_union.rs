@@ -0,0 +1,157 @@
+use crate::sdf::bounds::expand;
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Sphere;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec3::{Vec3, Vector3};
+
+/// A union that blends the two surfaces together within a distance of `k` of the crease using a
+/// quadratic [smooth minimum](https://iquilezles.org/articles/smin/). Within the blend the value is
+/// `h * a + (1 - h) * b - k * h * (1 - h)`, but the gradient works out to `h * ∇a + (1 - h) * ∇b`,
+/// a convex combination of the input gradients, so the result is still 1-Lipschitz and an SDFB.
+///
+/// The exponential smooth minimum is deliberately not offered: it differs from the hard minimum
+/// everywhere rather than only within `k` of the crease, so [SdfImpl::evaluate_constrain] could
+/// never prune either side.
+#[derive(Debug)]
+pub struct SdfSmoothUnion<const N: usize> {
+    a: Sdf<N>,
+    b: Sdf<N>,
+    k: f64,
+}
+
+impl<const N: usize> SdfSmoothUnion<N> {
+    pub fn new(a: Sdf<N>, b: Sdf<N>, k: f64) -> Self {
+        assert!(k > 0.0, "smoothing radius must be positive");
+        Self { a, b, k }
+    }
+    pub fn into_sdf(self) -> Sdf<N> {
+        Sdf::new(self)
+    }
+    fn smooth_min<T: Scalar>(&self, a: T, b: T) -> T {
+        let k = T::from_f64(self.k);
+        let h = (T::from_f64(0.5) + T::from_f64(0.5) * (b.clone() - a.clone()) / k.clone())
+            .maximum(T::from_f64(0.0))
+            .minimum(T::from_f64(1.0));
+        b.clone() + h.clone() * (a - b) - k * h.clone() * (T::from_f64(1.0) - h)
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfSmoothUnion<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.smooth_min(self.a.evaluate(p), self.b.evaluate(p))
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.smooth_min(
+            self.a.evaluate_deriv1(p.clone()),
+            self.b.evaluate_deriv1(p.clone()),
+        )
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.smooth_min(
+            self.a.evaluate_deriv2(p.clone()),
+            self.b.evaluate_deriv2(p.clone()),
+        )
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.smooth_min(
+            self.a.evaluate_deriv3(p.clone()),
+            self.b.evaluate_deriv3(p.clone()),
+        )
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let (a2, ai) = self.a.evaluate_constrain(p);
+        let (b2, bi) = self.b.evaluate_constrain(p);
+        let k = DecInterval::from_f64(self.k);
+        if (ai + k).precedes(bi) {
+            (Some(a2.unwrap_or(self.a.clone())), ai)
+        } else if (bi + k).precedes(ai) {
+            (Some(b2.unwrap_or(self.b.clone())), bi)
+        } else if a2.is_some() || b2.is_some() {
+            (
+                Some(Sdf::new(SdfSmoothUnion::new(
+                    a2.unwrap_or(self.a.clone()),
+                    b2.unwrap_or(self.b.clone()),
+                    self.k,
+                ))),
+                self.smooth_min(ai, bi),
+            )
+        } else {
+            (None, self.smooth_min(ai, bi))
+        }
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        let a = self.a.evaluate_tape(p.clone());
+        let b = self.b.evaluate_tape(p);
+        let k = TapeValue::from_f64(self.k);
+        // Away from the fillet the result is exactly one side, so that constraining the tape
+        // drops the other side, as [SdfImpl::evaluate_constrain] does.
+        let blend = self.smooth_min(a.clone(), b.clone());
+        let b_or_blend = (a.clone() - b.clone() - k.clone()).piecewise(blend, b.clone());
+        Some((b - a.clone() - k).piecewise(b_or_blend, a))
+    }
+
+    /// The blend is at most `k / 4` below the smaller input, so it only adds material within
+    /// that distance of the inputs.
+    fn bounds(&self) -> Option<Aabb<N>> {
+        let bounds = self
+            .a
+            .structural_bounds()?
+            .union(&self.b.structural_bounds()?);
+        Some(expand(&bounds, self.k / 4.0))
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.a.complexity() + self.b.complexity()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::SmoothUnion {
+            a: Box::new(self.a.node()),
+            b: Box::new(self.b.node()),
+            k: self.k,
+        }
+    }
+}
+
+#[test]
+fn test_smooth_union() {
+    let sphere1 = Sphere::new(Vec3::new(-1.0, 0.0, 0.0), 1.0).as_sdf();
+    let sphere2 = Sphere::new(Vec3::new(1.0, 0.0, 0.0), 1.0).as_sdf();
+    let hard = sphere1.union(&sphere2);
+    let smooth = sphere1.smooth_union(&sphere2, 0.5);
+    let crease = Vec3::new(0.0, 0.5, 0.0);
+    assert!(smooth.evaluate(crease) < hard.evaluate(crease));
+    let far = Vec3::new(-3.0, 0.0, 0.0);
+    assert_eq!(smooth.evaluate(far), hard.evaluate(far));
+    let region = Vector3::new(
+        DecInterval::try_from((-2.5, -2.0)).unwrap(),
+        DecInterval::try_from((-0.25, 0.25)).unwrap(),
+        DecInterval::try_from((-0.25, 0.25)).unwrap(),
+    );
+    let (pruned, _) = smooth.evaluate_constrain(region);
+    assert_eq!(pruned.unwrap().complexity(), 1);
+
+    // The spheres touch at the origin, where the smooth operations cut away more than the hard
+    // ones and agree with them once the distances differ by more than the radius.
+    let origin = Vec3::zero();
+    let lens = sphere1.smooth_intersection(&sphere2, 0.5);
+    assert_eq!(sphere1.intersection(&sphere2).evaluate(origin), 0.0);
+    assert!(lens.evaluate(origin) > 0.0);
+    assert_eq!(lens.evaluate(far), 3.0);
+    let bite = sphere1.smooth_difference(&sphere2, 0.5);
+    assert_eq!(sphere1.difference(&sphere2).evaluate(origin), 0.0);
+    assert!(bite.evaluate(origin) > 0.0);
+    let inside = Vec3::new(-1.5, 0.0, 0.0);
+    assert_eq!(bite.evaluate(inside), -0.5);
+}
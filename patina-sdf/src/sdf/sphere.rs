@@ -1,5 +1,8 @@
+use crate::sdf::bounds::expand;
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
 use patina_geo::sphere::{NSphere, Sphere};
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
@@ -15,4 +18,13 @@ impl<const N: usize> SdfLeafImpl<N> for NSphere<N> {
     fn evaluate<T: Scalar>(&self, p: Vector<T, N>) -> T {
         (p - self.origin().into_scalars::<T>()).length() - T::from_f64(self.radius())
     }
+    fn bounds(&self) -> Option<Aabb<N>> {
+        Some(expand(&Aabb::from_point(self.origin()), self.radius()))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Sphere {
+            center: self.origin().into_iter().collect(),
+            radius: self.radius(),
+        }
+    }
 }
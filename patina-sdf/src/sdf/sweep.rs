@@ -0,0 +1,542 @@
+use crate::sdf::bounds::{corners, cylinder_bounds, expand, is_empty};
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
+use crate::sdf::{AsSdf, Sdf, Sdf2, Sdf3, SdfImpl};
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::Circle;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::mat3::Mat3;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::{Vec2, Vector2};
+use patina_vec::vec3::{Vec3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Helices are approximated by polylines that stay within this distance (in mm) of the helix.
+const HELIX_TOLERANCE: f64 = 0.01;
+
+/// Helices of large radius use at most this many segments per turn, even if that takes them
+/// beyond [HELIX_TOLERANCE].
+const HELIX_SEGMENTS_PER_TURN: f64 = 64.0;
+
+/// The distance to the part of a prism with distance `d2` that lies where `beyond <= 0`, if
+/// `beyond` is the distance past a cap perpendicular to the prism.
+pub(crate) fn cap<T: Scalar>(d2: T, beyond: T) -> T {
+    beyond.clone().piecewise(
+        d2.clone().maximum(beyond.clone()),
+        d2.clone()
+            .piecewise(beyond.clone(), Vector2::new(d2, beyond).length()),
+    )
+}
+
+/// Limits `value` to the region `beyond <= 0`, where the neighbouring piece of the path takes
+/// over. Inside the region `value` is left alone, so that the bound stays negative inside the
+/// solid where two pieces meet.
+fn junction<T: Scalar>(value: T, beyond: T) -> T {
+    beyond
+        .clone()
+        .piecewise(value.clone(), value.maximum(beyond))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum SweepShape {
+    /// The profile moved along a straight line.
+    Segment {
+        origin: Vec3,
+        tangent: Vec3,
+        normal: Vec3,
+        binormal: Vec3,
+        length: f64,
+    },
+    /// The profile revolved by at most half a turn around `axis` through `center`. `normal` and
+    /// `binormal` are the profile axes in the basis of the direction away from `axis` and `axis`
+    /// itself. Corners of polylines are arcs with a radius of zero.
+    Arc {
+        center: Vec3,
+        axis: Vec3,
+        radius: f64,
+        start_tangent: Vec3,
+        end_tangent: Vec3,
+        normal: Vec2,
+        binormal: Vec2,
+    },
+}
+
+/// A piece of a [SweepPath]. `caps` records whether the start and end of the piece are the ends
+/// of the path, as opposed to junctions with the neighbouring pieces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepPiece {
+    shape: SweepShape,
+    caps: [bool; 2],
+}
+
+impl SweepPiece {
+    fn new(shape: SweepShape) -> Self {
+        SweepPiece {
+            shape,
+            caps: [false, false],
+        }
+    }
+    fn evaluate<T: Scalar>(&self, p: Vector3<T>, profile: impl FnOnce(Vector2<T>) -> T) -> T {
+        let (mut value, beyond, perpendicular) = match &self.shape {
+            SweepShape::Segment {
+                origin,
+                tangent,
+                normal,
+                binormal,
+                length,
+            } => {
+                let rel = p - origin.into_scalars();
+                let q = Vector2::new(
+                    rel.clone().dot(normal.into_scalars()),
+                    rel.clone().dot(binormal.into_scalars()),
+                );
+                let z = rel.dot(tangent.into_scalars());
+                (profile(q), [-z.clone(), z - T::from_f64(*length)], true)
+            }
+            SweepShape::Arc {
+                center,
+                axis,
+                radius,
+                start_tangent,
+                end_tangent,
+                normal,
+                binormal,
+            } => {
+                let rel = p - center.into_scalars();
+                let h = rel.clone().dot(axis.into_scalars());
+                let x =
+                    (rel.clone() - axis.into_scalars() * h.clone()).length() - T::from_f64(*radius);
+                let q = Vector2::new(
+                    x.clone() * T::from_f64(normal.x()) + h.clone() * T::from_f64(normal.y()),
+                    x * T::from_f64(binormal.x()) + h * T::from_f64(binormal.y()),
+                );
+                let beyond = [
+                    -rel.clone().dot(start_tangent.into_scalars()),
+                    rel.dot(end_tangent.into_scalars()),
+                ];
+                (profile(q), beyond, false)
+            }
+        };
+        for (is_cap, beyond) in self.caps.iter().zip(&beyond) {
+            if *is_cap {
+                // The end planes of an arc are not perpendicular to the revolved profile, so
+                // distances past them are only combined with `max`.
+                value = if perpendicular {
+                    cap(value, beyond.clone())
+                } else {
+                    value.maximum(beyond.clone())
+                };
+            }
+        }
+        self.junctions(value, beyond)
+    }
+    /// Bounds the piece given the bounds of its profile.
+    fn bounds(&self, profile: &Aabb<2>) -> Aabb<3> {
+        if is_empty(profile) {
+            return Aabb::empty();
+        }
+        let reach = corners(profile).map(|q| q.length()).fold(0.0, f64::max);
+        match &self.shape {
+            SweepShape::Segment {
+                origin,
+                tangent,
+                length,
+                ..
+            } => expand(
+                &[*origin, *origin + *tangent * *length]
+                    .into_iter()
+                    .collect(),
+                reach,
+            ),
+            SweepShape::Arc {
+                center,
+                axis,
+                radius,
+                ..
+            } => cylinder_bounds(*center, *axis, radius.abs() + reach, -reach..reach),
+        }
+    }
+    fn junctions<T: Scalar>(&self, value: T, beyond: [T; 2]) -> T {
+        let internal = self
+            .caps
+            .iter()
+            .zip(beyond)
+            .filter(|(is_cap, _)| !**is_cap)
+            .map(|(_, beyond)| beyond)
+            .reduce(T::maximum);
+        match internal {
+            Some(beyond) => junction(value, beyond),
+            None => value,
+        }
+    }
+}
+
+/// The frame of the profile as it moves along a [SweepPath]. The profile's x axis is `normal`,
+/// its y axis is `binormal`, and it faces along `tangent`.
+#[derive(Copy, Clone, Debug)]
+struct SweepFrame {
+    point: Vec3,
+    tangent: Vec3,
+    normal: Vec3,
+    binormal: Vec3,
+}
+
+impl SweepFrame {
+    fn new(point: Vec3, tangent: Vec3, normal: Vec3) -> Self {
+        let normal = (normal - tangent * normal.dot(tangent)).normalize();
+        SweepFrame {
+            point,
+            tangent,
+            normal,
+            binormal: tangent.cross(normal),
+        }
+    }
+    fn rotate(&self, center: Vec3, axis: Vec3, angle: f64) -> Self {
+        let rotation = Mat3::rotate(axis, angle);
+        SweepFrame {
+            point: center + rotation * (self.point - center),
+            tangent: rotation * self.tangent,
+            normal: rotation * self.normal,
+            binormal: rotation * self.binormal,
+        }
+    }
+    /// Turns the frame around `axis` through `center` by `angle` (at most half a turn) and
+    /// returns the arc swept by the profile.
+    fn arc(&mut self, center: Vec3, axis: Vec3, angle: f64) -> SweepPiece {
+        let radial = self.tangent.cross(axis);
+        let end = self.rotate(center, axis, angle);
+        let piece = SweepPiece::new(SweepShape::Arc {
+            center,
+            axis,
+            radius: (self.point - center).dot(radial),
+            start_tangent: self.tangent,
+            end_tangent: end.tangent,
+            normal: Vec2::new(self.normal.dot(radial), self.normal.dot(axis)),
+            binormal: Vec2::new(self.binormal.dot(radial), self.binormal.dot(axis)),
+        });
+        *self = end;
+        piece
+    }
+    /// Moves the frame to `point`, turning at the current point if necessary, and returns the
+    /// pieces swept by the profile.
+    fn line_to(&mut self, point: Vec3, pieces: &mut Vec<SweepPiece>) {
+        let delta = point - self.point;
+        let length = delta.length();
+        if length == 0.0 {
+            return;
+        }
+        let tangent = delta / length;
+        let turn = self.tangent.cross(tangent);
+        if turn.length() > 1e-12 {
+            let angle = turn.length().atan2(self.tangent.dot(tangent));
+            pieces.push(self.arc(self.point, turn.normalize(), angle));
+        }
+        *self = SweepFrame::new(self.point, tangent, self.normal);
+        pieces.push(SweepPiece::new(SweepShape::Segment {
+            origin: self.point,
+            tangent: self.tangent,
+            normal: self.normal,
+            binormal: self.binormal,
+            length,
+        }));
+        self.point = point;
+    }
+}
+
+/// A path in 3D along which [Sdf2::sweep] moves a profile. The profile is carried along the path
+/// without twisting, so at every point it lies in the plane perpendicular to the path.
+#[derive(Debug, Clone)]
+pub struct SweepPath {
+    pieces: Vec<SweepPiece>,
+}
+
+impl SweepPath {
+    /// Closed paths join their last piece back onto the first instead of capping the ends.
+    fn new(mut pieces: Vec<SweepPiece>, closed: bool) -> Self {
+        if !closed {
+            pieces.first_mut().unwrap().caps[0] = true;
+            pieces.last_mut().unwrap().caps[1] = true;
+        }
+        SweepPath { pieces }
+    }
+    /// A path through each of `points` in turn. The profile starts with its x axis along
+    /// [Vec3::perpendicular] of the first segment, and sharp corners are filled by revolving the
+    /// profile around the corner.
+    pub fn polyline(points: &[Vec3]) -> Self {
+        assert!(points.len() >= 2, "polyline needs at least two points");
+        let tangent = points
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .find(|d| d.length() > 0.0)
+            .expect("polyline has no length")
+            .normalize();
+        let mut frame = SweepFrame::new(points[0], tangent, tangent.perpendicular());
+        let mut pieces = vec![];
+        for &point in &points[1..] {
+            frame.line_to(point, &mut pieces);
+        }
+        SweepPath::new(pieces, false)
+    }
+    /// A circular arc starting at `start` and turning counterclockwise around `axis` through
+    /// `center` by `angle` radians, closing into a ring if `angle` is a full turn or more. The
+    /// profile's x axis points away from the center and its y axis points down `axis`.
+    pub fn arc(center: Vec3, axis: Vec3, start: Vec3, angle: f64) -> Self {
+        assert!(angle > 0.0, "arc angle must be positive");
+        let closed = angle >= 2.0 * PI;
+        let angle = angle.min(2.0 * PI);
+        let axis = axis.normalize();
+        let radial = start - center;
+        let point = start - axis * radial.dot(axis);
+        let radial = (point - center).normalize();
+        let mut frame = SweepFrame::new(point, axis.cross(radial), radial);
+        let count = (angle / PI).ceil() as usize;
+        let pieces = (0..count)
+            .map(|_| frame.arc(center, axis, angle / count as f64))
+            .collect();
+        SweepPath::new(pieces, closed)
+    }
+    /// A helix of the given radius around `axis` through `origin`, starting at
+    /// [Vec3::perpendicular] of `axis` and rising by `pitch` per counterclockwise turn. The
+    /// profile's x axis points away from the helix axis. The helix is approximated by a polyline
+    /// within [HELIX_TOLERANCE] of the true helix, with at most [HELIX_SEGMENTS_PER_TURN]
+    /// segments per turn.
+    pub fn helix(origin: Vec3, axis: Vec3, radius: f64, pitch: f64, turns: f64) -> Self {
+        assert!(radius > 0.0 && turns > 0.0);
+        let w = axis.normalize();
+        let u = w.perpendicular();
+        let v = w.cross(u);
+        let step = (2.0 * (1.0 - HELIX_TOLERANCE / radius).max(-1.0).acos())
+            .max(2.0 * PI / HELIX_SEGMENTS_PER_TURN);
+        let count = ((turns * 2.0 * PI / step).ceil() as usize).max(1);
+        let point = |angle: f64| {
+            origin + (u * angle.cos() + v * angle.sin()) * radius + w * (pitch * angle / (2.0 * PI))
+        };
+        let total = turns * 2.0 * PI;
+        let tangent = (point(total / count as f64) - point(0.0)).normalize();
+        let mut frame = SweepFrame::new(point(0.0), tangent, u);
+        let mut pieces = vec![];
+        for i in 1..=count {
+            let angle = total * i as f64 / count as f64;
+            frame.line_to(point(angle), &mut pieces);
+            // Keep the profile aligned with the helix axis instead of letting it drift.
+            let outward = u * angle.cos() + v * angle.sin();
+            frame = SweepFrame::new(frame.point, frame.tangent, outward);
+        }
+        SweepPath::new(pieces, false)
+    }
+}
+
+/// A 2D profile swept along a [SweepPath]. Each piece of the path is bounded separately and
+/// [SdfImpl::evaluate_constrain] drops the pieces that are too far away to matter. Points are
+/// evaluated against every piece, so that the tree and its tape agree.
+#[derive(Debug)]
+pub struct SdfSweep {
+    pieces: Vec<(SweepPiece, Sdf2)>,
+    /// The union of the bounds of the pieces, or `None` if a profile is unbounded.
+    bounds: Option<Aabb<3>>,
+}
+
+impl SdfSweep {
+    pub fn new(profile: Sdf2, path: &SweepPath) -> Self {
+        Self::from_pieces(
+            path.pieces
+                .iter()
+                .map(|piece| (piece.clone(), profile.clone()))
+                .collect(),
+        )
+    }
+    pub(crate) fn from_pieces(pieces: Vec<(SweepPiece, Sdf2)>) -> Self {
+        let bounds = pieces
+            .iter()
+            .map(|(piece, profile)| Some(piece.bounds(&profile.structural_bounds()?)))
+            .collect();
+        SdfSweep { pieces, bounds }
+    }
+    /// The smallest value of the pieces at `p`.
+    fn nearest<T: Scalar>(
+        &self,
+        p: Vector3<T>,
+        evaluate: impl Fn(&SweepPiece, &Sdf2, Vector3<T>) -> T,
+    ) -> T {
+        self.pieces
+            .iter()
+            .map(|(piece, profile)| evaluate(piece, profile, p.clone()))
+            .reduce(T::minimum)
+            .unwrap()
+    }
+}
+
+impl SdfImpl<3> for SdfSweep {
+    fn evaluate(&self, p: Vector<f64, 3>) -> f64 {
+        self.nearest(p, |piece, profile, p| {
+            piece.evaluate(p, |q| profile.evaluate(q))
+        })
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, 3>) -> Deriv<1> {
+        self.nearest(p, |piece, profile, p| {
+            piece.evaluate(p, |q| profile.evaluate_deriv1(q))
+        })
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, 3>) -> Deriv<2> {
+        self.nearest(p, |piece, profile, p| {
+            piece.evaluate(p, |q| profile.evaluate_deriv2(q))
+        })
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, 3>) -> Deriv<3> {
+        self.nearest(p, |piece, profile, p| {
+            piece.evaluate(p, |q| profile.evaluate_deriv3(q))
+        })
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, 3>) -> (Option<Sdf3>, DecInterval) {
+        let constrained: Vec<_> = self
+            .pieces
+            .iter()
+            .map(|(piece, profile)| {
+                let mut constrained = None;
+                let int = piece.evaluate(p, |q| {
+                    let (c, int) = profile.evaluate_constrain(q);
+                    constrained = c;
+                    int
+                });
+                (constrained, int)
+            })
+            .collect();
+        // As in a union, the piece with the lowest upper bound is always kept.
+        let (best, bound) = constrained
+            .iter()
+            .enumerate()
+            .map(|(i, (_, int))| (i, int.sup()))
+            .reduce(|x, y| if y.1 < x.1 { y } else { x })
+            .unwrap();
+        let mut changed = false;
+        let mut pieces = vec![];
+        let mut range: Option<DecInterval> = None;
+        for (i, ((c, int), (piece, profile))) in
+            constrained.into_iter().zip(&self.pieces).enumerate()
+        {
+            if i != best && bound <= int.inf() {
+                changed = true;
+                continue;
+            }
+            changed |= c.is_some();
+            pieces.push((piece.clone(), c.unwrap_or(profile.clone())));
+            range = Some(range.map_or(int, |range| range.minimum(int)));
+        }
+        let range = range.unwrap();
+        if changed {
+            (Some(Sdf::new(SdfSweep::from_pieces(pieces))), range)
+        } else {
+            (None, range)
+        }
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, 3>) -> Option<TapeValue> {
+        Some(self.nearest(p, |piece, profile, p| {
+            piece.evaluate(p, |q| profile.evaluate_tape(q))
+        }))
+    }
+
+    fn bounds(&self) -> Option<Aabb<3>> {
+        self.bounds
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self
+            .pieces
+            .iter()
+            .map(|(_, profile)| profile.complexity())
+            .sum::<usize>()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Sweep {
+            pieces: self
+                .pieces
+                .iter()
+                .map(|(piece, profile)| (piece.clone(), profile.node()))
+                .collect(),
+        }
+    }
+}
+
+impl Sdf2 {
+    /// Sweeps this profile along `path`.
+    pub fn sweep(&self, path: &SweepPath) -> Sdf3 {
+        Sdf::new(SdfSweep::new(self.clone(), path))
+    }
+}
+
+#[test]
+fn test_sweep() {
+    let profile = Circle::new(Vec2::zero(), 1.0).as_sdf();
+
+    let bent = profile.sweep(&SweepPath::polyline(&[
+        Vec3::zero(),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(10.0, 10.0, 0.0),
+    ]));
+    assert_eq!(bent.evaluate(Vec3::new(5.0, 0.0, 0.0)), -1.0);
+    assert_eq!(bent.evaluate(Vec3::new(10.0, 5.0, 3.0)), 2.0);
+    assert_eq!(bent.evaluate(Vec3::new(-2.0, 0.0, 0.0)), 2.0);
+    // Outside the corner the tube is rounded.
+    let corner = Vec3::new(10.0, 0.0, 0.0) + Vec3::new(1.0, -1.0, 0.0).normalize() * 2.0;
+    assert!((bent.evaluate(corner) - 1.0).abs() < 1e-12);
+
+    let ring = profile.sweep(&SweepPath::arc(
+        Vec3::zero(),
+        Vec3::axis_z(),
+        Vec3::new(5.0, 0.0, 0.0),
+        2.0 * PI,
+    ));
+    for i in 0..12 {
+        let angle = i as f64 * PI / 6.0 + 0.1;
+        let dir = Vec3::new(angle.cos(), angle.sin(), 0.0);
+        assert!((ring.evaluate(dir * 5.0) + 1.0).abs() < 1e-12);
+        assert!((ring.evaluate(dir * 7.0) - 1.0).abs() < 1e-12);
+    }
+
+    let helix = profile.sweep(&SweepPath::helix(
+        Vec3::zero(),
+        Vec3::axis_z(),
+        5.0,
+        4.0,
+        3.0,
+    ));
+    let u = Vec3::axis_z().perpendicular();
+    let v = Vec3::axis_z().cross(u);
+    for i in 1..30 {
+        let angle = i as f64 * 0.6;
+        let center =
+            (u * angle.cos() + v * angle.sin()) * 5.0 + Vec3::axis_z() * (4.0 * angle / (2.0 * PI));
+        assert!((helix.evaluate(center) + 1.0).abs() < 2.0 * HELIX_TOLERANCE);
+        // Halfway between two turns.
+        assert!(helix.evaluate(center + Vec3::axis_z() * 2.0) > 0.5);
+    }
+    assert!(helix.evaluate(Vec3::zero()) > 3.0);
+    // The tape takes the same minimum over the pieces as the tree.
+    for sdf in [&bent, &helix] {
+        let compiled = sdf.compile();
+        for i in 0..50 {
+            let p = Vec3::new(i as f64 * 0.3 - 7.0, 1.5, i as f64 * 0.25 - 1.0);
+            assert!((compiled.evaluate(p) - sdf.evaluate(p)).abs() < 1e-12);
+        }
+    }
+    let wide = SweepPath::helix(Vec3::zero(), Vec3::axis_z(), 100.0, 4.0, 2.0);
+    assert!(wide.pieces.len() <= 2 * 2 * HELIX_SEGMENTS_PER_TURN as usize);
+
+    let region = Vector3::new(
+        DecInterval::try_from((4.5, 5.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+    );
+    let (pruned, range) = helix.evaluate_constrain(region);
+    assert!(range.contains(0.0));
+    assert!(pruned.unwrap().complexity() < helix.complexity() / 10);
+}
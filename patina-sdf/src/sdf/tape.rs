@@ -0,0 +1,899 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf, Sdf3, SdfImpl, gradient_variables};
+use arrayvec::ArrayVec;
+use inari::DecInterval;
+use patina_geo::aabb::Aabb;
+use patina_geo::sphere::{Circle, Sphere};
+use patina_mesh::mesh::Mesh;
+use patina_scalar::Scalar;
+use patina_scalar::deriv::Deriv;
+use patina_vec::vec::Vector;
+use patina_vec::vec2::Vec2;
+use patina_vec::vec3::{Vec3, Vector3};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::{self, Discriminant};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A register of a [Tape].
+pub type Reg = u32;
+
+/// An instruction of a [Tape]. Each instruction reads its operand registers and writes one
+/// output register.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Op {
+    /// The coordinate of the evaluated point along an axis.
+    Input(usize),
+    Const(f64),
+    Copy(Reg),
+    Neg(Reg),
+    Abs(Reg),
+    Sqrt(Reg),
+    Recip(Reg),
+    Sin(Reg),
+    Cos(Reg),
+    /// Rounds to the nearest integer, with a derivative of zero.
+    Round(Reg),
+    Add(Reg, Reg),
+    Sub(Reg, Reg),
+    Mul(Reg, Reg),
+    Div(Reg, Reg),
+    Min(Reg, Reg),
+    Max(Reg, Reg),
+    /// `Atan2(y, x)` as in [Scalar::atan2].
+    Atan2(Reg, Reg),
+    /// `Piecewise(x, neg, pos)` as in [Scalar::piecewise].
+    Piecewise(Reg, Reg, Reg),
+    /// Calls a node that could not be lowered. The index is into the call sites of the tape.
+    Call(usize),
+}
+
+impl Op {
+    /// The registers read by this instruction, except for the arguments of a call.
+    fn operands(&self) -> ArrayVec<Reg, 3> {
+        match *self {
+            Op::Input(_) | Op::Const(_) | Op::Call(_) => ArrayVec::new(),
+            Op::Copy(a)
+            | Op::Neg(a)
+            | Op::Abs(a)
+            | Op::Sqrt(a)
+            | Op::Recip(a)
+            | Op::Sin(a)
+            | Op::Cos(a)
+            | Op::Round(a) => [a].into_iter().collect(),
+            Op::Add(a, b)
+            | Op::Sub(a, b)
+            | Op::Mul(a, b)
+            | Op::Div(a, b)
+            | Op::Min(a, b)
+            | Op::Max(a, b)
+            | Op::Atan2(a, b) => [a, b].into_iter().collect(),
+            Op::Piecewise(a, b, c) => ArrayVec::from([a, b, c]),
+        }
+    }
+    fn map_operands(self, mut f: impl FnMut(Reg) -> Reg) -> Op {
+        match self {
+            Op::Input(_) | Op::Const(_) | Op::Call(_) => self,
+            Op::Copy(a) => Op::Copy(f(a)),
+            Op::Neg(a) => Op::Neg(f(a)),
+            Op::Abs(a) => Op::Abs(f(a)),
+            Op::Sqrt(a) => Op::Sqrt(f(a)),
+            Op::Recip(a) => Op::Recip(f(a)),
+            Op::Sin(a) => Op::Sin(f(a)),
+            Op::Cos(a) => Op::Cos(f(a)),
+            Op::Round(a) => Op::Round(f(a)),
+            Op::Add(a, b) => Op::Add(f(a), f(b)),
+            Op::Sub(a, b) => Op::Sub(f(a), f(b)),
+            Op::Mul(a, b) => Op::Mul(f(a), f(b)),
+            Op::Div(a, b) => Op::Div(f(a), f(b)),
+            Op::Min(a, b) => Op::Min(f(a), f(b)),
+            Op::Max(a, b) => Op::Max(f(a), f(b)),
+            Op::Atan2(a, b) => Op::Atan2(f(a), f(b)),
+            Op::Piecewise(a, b, c) => Op::Piecewise(f(a), f(b), f(c)),
+        }
+    }
+    /// Identifies instructions that always compute the same value, with constants compared
+    /// bitwise.
+    fn key(&self) -> (Discriminant<Op>, [u64; 3]) {
+        let mut args = [0; 3];
+        for (arg, reg) in args.iter_mut().zip(self.operands()) {
+            *arg = reg as u64;
+        }
+        match *self {
+            Op::Input(axis) | Op::Call(axis) => args[0] = axis as u64,
+            Op::Const(value) => args[0] = value.to_bits(),
+            _ => {}
+        }
+        (mem::discriminant(self), args)
+    }
+}
+
+/// A node that cannot be lowered onto a [Tape], such as a mesh, which the tape calls through its
+/// [Sdf] instead.
+pub trait TapeCall: 'static + Send + Sync + Debug {
+    fn evaluate(&self, args: &[f64]) -> f64;
+    fn evaluate_deriv1(&self, args: &[Deriv<1>]) -> Deriv<1>;
+    fn evaluate_deriv2(&self, args: &[Deriv<2>]) -> Deriv<2>;
+    fn evaluate_deriv3(&self, args: &[Deriv<3>]) -> Deriv<3>;
+    fn evaluate_constrain(&self, args: &[DecInterval]) -> (Option<Arc<dyn TapeCall>>, DecInterval);
+}
+
+impl<const N: usize> TapeCall for Sdf<N> {
+    fn evaluate(&self, args: &[f64]) -> f64 {
+        Sdf::evaluate(self, args.iter().cloned().collect())
+    }
+    fn evaluate_deriv1(&self, args: &[Deriv<1>]) -> Deriv<1> {
+        Sdf::evaluate_deriv1(self, args.iter().cloned().collect())
+    }
+    fn evaluate_deriv2(&self, args: &[Deriv<2>]) -> Deriv<2> {
+        Sdf::evaluate_deriv2(self, args.iter().cloned().collect())
+    }
+    fn evaluate_deriv3(&self, args: &[Deriv<3>]) -> Deriv<3> {
+        Sdf::evaluate_deriv3(self, args.iter().cloned().collect())
+    }
+    fn evaluate_constrain(&self, args: &[DecInterval]) -> (Option<Arc<dyn TapeCall>>, DecInterval) {
+        let (sdf, range) = Sdf::evaluate_constrain(self, args.iter().cloned().collect());
+        (sdf.map(|sdf| Arc::new(sdf) as Arc<dyn TapeCall>), range)
+    }
+}
+
+/// The scalar types a [Tape] can be evaluated with.
+pub trait TapeScalar: Scalar {
+    fn round(self) -> Self;
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self;
+}
+
+impl TapeScalar for f64 {
+    fn round(self) -> Self {
+        f64::round(self)
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        call.evaluate(args)
+    }
+}
+
+impl TapeScalar for Deriv<1> {
+    fn round(self) -> Self {
+        Deriv::constant(self.value().round())
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        call.evaluate_deriv1(args)
+    }
+}
+
+impl TapeScalar for Deriv<2> {
+    fn round(self) -> Self {
+        Deriv::constant(self.value().round())
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        call.evaluate_deriv2(args)
+    }
+}
+
+impl TapeScalar for Deriv<3> {
+    fn round(self) -> Self {
+        Deriv::constant(self.value().round())
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        call.evaluate_deriv3(args)
+    }
+}
+
+impl TapeScalar for DecInterval {
+    fn round(self) -> Self {
+        DecInterval::round(self)
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        call.evaluate_constrain(args).1
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CallSite {
+    call: Arc<dyn TapeCall>,
+    args: Vec<Reg>,
+}
+
+fn for_each_operand(op: &Op, calls: &[CallSite], mut f: impl FnMut(Reg)) {
+    if let Op::Call(call) = op {
+        calls[*call].args.iter().copied().for_each(f);
+    } else {
+        op.operands().into_iter().for_each(f);
+    }
+}
+
+/// A flat list of instructions that evaluates an [Sdf] without walking the tree. See
+/// [Sdf::tape].
+#[derive(Debug, Clone)]
+pub struct Tape {
+    instrs: Vec<(Reg, Op)>,
+    calls: Vec<CallSite>,
+    /// The initial values of the first registers.
+    constants: Vec<f64>,
+    registers: usize,
+    output: Reg,
+}
+
+impl Tape {
+    /// The number of instructions.
+    pub fn instruction_count(&self) -> usize {
+        self.instrs.len()
+    }
+    pub fn registers(&self) -> usize {
+        self.registers
+    }
+    fn initial_registers<T: Scalar>(&self) -> Vec<T> {
+        let mut regs: Vec<T> = self.constants.iter().map(|&c| T::from_f64(c)).collect();
+        regs.resize(self.registers, T::from_f64(0.0));
+        regs
+    }
+    fn evaluate_op<T: TapeScalar>(&self, op: Op, regs: &[T], p: &[T]) -> T {
+        let r = |reg: Reg| regs[reg as usize].clone();
+        match op {
+            Op::Input(axis) => p[axis].clone(),
+            Op::Const(value) => T::from_f64(value),
+            Op::Copy(a) => r(a),
+            Op::Neg(a) => -r(a),
+            Op::Abs(a) => r(a).abs(),
+            Op::Sqrt(a) => r(a).sqrt(),
+            Op::Recip(a) => r(a).recip(),
+            Op::Sin(a) => r(a).sin(),
+            Op::Cos(a) => r(a).cos(),
+            Op::Round(a) => r(a).round(),
+            Op::Add(a, b) => r(a) + r(b),
+            Op::Sub(a, b) => r(a) - r(b),
+            Op::Mul(a, b) => r(a) * r(b),
+            Op::Div(a, b) => r(a) / r(b),
+            Op::Min(a, b) => r(a).minimum(r(b)),
+            Op::Max(a, b) => r(a).maximum(r(b)),
+            Op::Atan2(a, b) => r(a).atan2(r(b)),
+            Op::Piecewise(a, b, c) => r(a).piecewise(r(b), r(c)),
+            Op::Call(call) => {
+                let site = &self.calls[call];
+                let args: Vec<T> = site.args.iter().map(|&a| r(a)).collect();
+                T::call(&site.call, &args)
+            }
+        }
+    }
+    /// Evaluates the tape at `p`, which has one coordinate per dimension of the [Sdf].
+    pub fn evaluate<T: TapeScalar>(&self, p: &[T]) -> T {
+        let mut regs = self.initial_registers();
+        for &(out, op) in &self.instrs {
+            regs[out as usize] = self.evaluate_op(op, &regs, p);
+        }
+        regs[self.output as usize].clone()
+    }
+    /// Evaluates the tape at every point of `ps`. Each instruction is run over the whole batch
+    /// before the next, so the loops over simple instructions can be vectorized.
+    pub fn evaluate_batch<T: TapeScalar, const N: usize>(
+        &self,
+        ps: &[Vector<T, N>],
+        out: &mut [T],
+    ) {
+        assert_eq!(ps.len(), out.len());
+        let lanes = ps.len();
+        let mut regs = vec![T::from_f64(0.0); self.registers * lanes];
+        for (column, &c) in regs.chunks_mut(lanes.max(1)).zip(&self.constants) {
+            column.fill(T::from_f64(c));
+        }
+        let mut column = Vec::with_capacity(lanes);
+        for &(dest, op) in &self.instrs {
+            let r = |reg: Reg| &regs[reg as usize * lanes..][..lanes];
+            let unary = |a: Reg, f: fn(T) -> T| r(a).iter().map(move |x| f(x.clone()));
+            let binary = |a: Reg, b: Reg, f: fn(T, T) -> T| {
+                r(a).iter()
+                    .zip(r(b))
+                    .map(move |(x, y)| f(x.clone(), y.clone()))
+            };
+            column.clear();
+            match op {
+                Op::Input(axis) => column.extend(ps.iter().map(|p| p[axis].clone())),
+                Op::Const(value) => column.resize(lanes, T::from_f64(value)),
+                Op::Copy(a) => column.extend_from_slice(r(a)),
+                Op::Neg(a) => column.extend(unary(a, T::neg)),
+                Op::Abs(a) => column.extend(unary(a, T::abs)),
+                Op::Sqrt(a) => column.extend(unary(a, T::sqrt)),
+                Op::Recip(a) => column.extend(unary(a, T::recip)),
+                Op::Sin(a) => column.extend(unary(a, T::sin)),
+                Op::Cos(a) => column.extend(unary(a, T::cos)),
+                Op::Round(a) => column.extend(unary(a, T::round)),
+                Op::Add(a, b) => column.extend(binary(a, b, T::add)),
+                Op::Sub(a, b) => column.extend(binary(a, b, T::sub)),
+                Op::Mul(a, b) => column.extend(binary(a, b, T::mul)),
+                Op::Div(a, b) => column.extend(binary(a, b, T::div)),
+                Op::Min(a, b) => column.extend(binary(a, b, T::minimum)),
+                Op::Max(a, b) => column.extend(binary(a, b, T::maximum)),
+                Op::Atan2(a, b) => column.extend(binary(a, b, T::atan2)),
+                Op::Piecewise(a, b, c) => column.extend(
+                    r(a).iter()
+                        .zip(r(b))
+                        .zip(r(c))
+                        .map(|((x, neg), pos)| x.clone().piecewise(neg.clone(), pos.clone())),
+                ),
+                Op::Call(call) => {
+                    let site = &self.calls[call];
+                    column.extend((0..lanes).map(|lane| {
+                        let args: Vec<T> = site.args.iter().map(|&a| r(a)[lane].clone()).collect();
+                        T::call(&site.call, &args)
+                    }))
+                }
+            }
+            regs[dest as usize * lanes..][..lanes].clone_from_slice(&column);
+        }
+        out.clone_from_slice(&regs[self.output as usize * lanes..][..lanes]);
+    }
+    /// Bounds the tape over the box `p`, like [Sdf::evaluate_constrain]. Where one side of a
+    /// `min`, `max` or piecewise function is chosen everywhere in the box, the other side is
+    /// removed from the returned tape, along with every instruction that only it used.
+    pub fn evaluate_constrain(&self, p: &[DecInterval]) -> (Option<Tape>, DecInterval) {
+        let mut regs: Vec<DecInterval> = self.initial_registers();
+        let mut calls = self.calls.clone();
+        let mut choices = vec![];
+        for (index, &(out, op)) in self.instrs.iter().enumerate() {
+            let r = |reg: Reg| regs[reg as usize];
+            let choice = match op {
+                Op::Min(a, b) if r(a).sup() <= r(b).inf() => Some(a),
+                Op::Min(a, b) if r(b).sup() <= r(a).inf() => Some(b),
+                Op::Max(a, b) if r(a).inf() >= r(b).sup() => Some(a),
+                Op::Max(a, b) if r(b).inf() >= r(a).sup() => Some(b),
+                Op::Piecewise(x, neg, _) if !r(x).is_empty() && r(x).sup() < 0.0 => Some(neg),
+                Op::Piecewise(x, _, pos) if !r(x).is_empty() && r(x).inf() >= 0.0 => Some(pos),
+                _ => None,
+            };
+            if let Some(choice) = choice {
+                choices.push((index, Op::Copy(choice)));
+            }
+            regs[out as usize] = if let Op::Call(call) = op {
+                let site = &self.calls[call];
+                let args: Vec<_> = site.args.iter().map(|&a| r(a)).collect();
+                let (simplified, range) = site.call.evaluate_constrain(&args);
+                if let Some(simplified) = simplified {
+                    choices.push((index, Op::Call(calls.len())));
+                    calls.push(CallSite {
+                        call: simplified,
+                        args: site.args.clone(),
+                    });
+                }
+                range
+            } else {
+                self.evaluate_op(op, &regs, p)
+            };
+        }
+        let range = regs[self.output as usize];
+        if choices.is_empty() {
+            (None, range)
+        } else {
+            (Some(self.simplify(choices, calls)), range)
+        }
+    }
+    /// Replaces the instructions at the given indices, then drops dead instructions and calls.
+    fn simplify(&self, mut choices: Vec<(usize, Op)>, calls: Vec<CallSite>) -> Tape {
+        let mut live = vec![false; self.registers];
+        live[self.output as usize] = true;
+        let mut instrs = vec![];
+        for (index, &(out, op)) in self.instrs.iter().enumerate().rev() {
+            let op = match choices.last() {
+                Some(&(choice, replacement)) if choice == index => {
+                    choices.pop();
+                    replacement
+                }
+                _ => op,
+            };
+            if !mem::take(&mut live[out as usize]) {
+                continue;
+            }
+            if op == Op::Copy(out) {
+                live[out as usize] = true;
+                continue;
+            }
+            for_each_operand(&op, &calls, |reg| live[reg as usize] = true);
+            instrs.push((out, op));
+        }
+        instrs.reverse();
+        let mut kept = vec![];
+        for (_, op) in &mut instrs {
+            if let Op::Call(call) = op {
+                kept.push(calls[*call].clone());
+                *call = kept.len() - 1;
+            }
+        }
+        Tape {
+            instrs,
+            calls: kept,
+            constants: self.constants.clone(),
+            registers: self.registers,
+            output: self.output,
+        }
+    }
+}
+
+/// Records instructions in SSA form, where each instruction is identified by its index.
+#[derive(Default)]
+pub struct TapeBuilder {
+    ops: Vec<Op>,
+    calls: Vec<CallSite>,
+    cse: HashMap<(Discriminant<Op>, [u64; 3]), Reg>,
+}
+
+impl TapeBuilder {
+    fn push(&mut self, op: Op) -> Reg {
+        if !matches!(op, Op::Call(_)) {
+            if let Some(&reg) = self.cse.get(&op.key()) {
+                return reg;
+            }
+            self.cse.insert(op.key(), self.ops.len() as Reg);
+        }
+        self.ops.push(op);
+        (self.ops.len() - 1) as Reg
+    }
+    /// Drops instructions that do not contribute to `output` and assigns registers, reusing each
+    /// register once the value in it is no longer needed.
+    fn finish(self, output: Reg) -> Tape {
+        let output = output as usize;
+        let mut live = vec![false; self.ops.len()];
+        live[output] = true;
+        for (index, op) in self.ops.iter().enumerate().rev() {
+            if live[index] {
+                for_each_operand(op, &self.calls, |reg| live[reg as usize] = true);
+            }
+        }
+        let mut last_use = vec![0; self.ops.len()];
+        for (index, op) in self.ops.iter().enumerate() {
+            if live[index] {
+                for_each_operand(op, &self.calls, |reg| last_use[reg as usize] = index);
+            }
+        }
+        last_use[output] = usize::MAX;
+        // Constants are loaded into registers of their own before evaluation starts.
+        let mut assigned = vec![0; self.ops.len()];
+        let mut constants = vec![];
+        for (index, op) in self.ops.iter().enumerate() {
+            if let (true, &Op::Const(value)) = (live[index], op) {
+                assigned[index] = constants.len() as Reg;
+                constants.push(value);
+            }
+        }
+        let mut free = vec![];
+        let mut registers = constants.len() as Reg;
+        let mut instrs = vec![];
+        let mut calls = vec![];
+        for (index, &op) in self.ops.iter().enumerate() {
+            if !live[index] || matches!(op, Op::Const(_)) {
+                continue;
+            }
+            let op = if let Op::Call(call) = op {
+                let site = &self.calls[call];
+                calls.push(CallSite {
+                    call: site.call.clone(),
+                    args: site.args.iter().map(|&a| assigned[a as usize]).collect(),
+                });
+                Op::Call(calls.len() - 1)
+            } else {
+                op.map_operands(|a| assigned[a as usize])
+            };
+            // Operands are read before the output is written, so their registers can be reused
+            // for the output.
+            for_each_operand(&self.ops[index], &self.calls, |reg| {
+                if last_use[reg as usize] == index
+                    && !matches!(self.ops[reg as usize], Op::Const(_))
+                {
+                    last_use[reg as usize] = usize::MAX;
+                    free.push(assigned[reg as usize]);
+                }
+            });
+            let out = free.pop().unwrap_or_else(|| {
+                registers += 1;
+                registers - 1
+            });
+            assigned[index] = out;
+            instrs.push((out, op));
+        }
+        Tape {
+            instrs,
+            calls,
+            constants,
+            registers: registers as usize,
+            output: assigned[output],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum TapeOperand {
+    Const(f64),
+    Value(Reg),
+}
+
+/// A value recorded onto a tape while lowering an [Sdf]. Constants are folded as they are
+/// combined, so only values that depend on the evaluated point become instructions.
+#[derive(Clone)]
+pub struct TapeValue {
+    builder: Option<Rc<RefCell<TapeBuilder>>>,
+    operand: TapeOperand,
+}
+
+impl TapeValue {
+    fn input(builder: &Rc<RefCell<TapeBuilder>>, axis: usize) -> Self {
+        let reg = builder.borrow_mut().push(Op::Input(axis));
+        TapeValue {
+            builder: Some(builder.clone()),
+            operand: TapeOperand::Value(reg),
+        }
+    }
+    fn reg(&self, builder: &mut TapeBuilder) -> Reg {
+        match self.operand {
+            TapeOperand::Const(value) => builder.push(Op::Const(value)),
+            TapeOperand::Value(reg) => reg,
+        }
+    }
+    fn record<const K: usize>(
+        args: [&TapeValue; K],
+        op: impl FnOnce([Reg; K]) -> Op,
+        fold: impl FnOnce([f64; K]) -> f64,
+    ) -> TapeValue {
+        let Some(builder) = args.iter().find_map(|arg| arg.builder.clone()) else {
+            return TapeValue::from_f64(fold(args.map(|arg| match arg.operand {
+                TapeOperand::Const(value) => value,
+                TapeOperand::Value(_) => unreachable!(),
+            })));
+        };
+        let reg = {
+            let mut b = builder.borrow_mut();
+            let regs = args.map(|arg| arg.reg(&mut b));
+            b.push(op(regs))
+        };
+        TapeValue {
+            builder: Some(builder),
+            operand: TapeOperand::Value(reg),
+        }
+    }
+}
+
+impl Debug for TapeValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for TapeValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.operand {
+            TapeOperand::Const(value) => write!(f, "{}", value),
+            TapeOperand::Value(reg) => write!(f, "%{}", reg),
+        }
+    }
+}
+
+impl Neg for TapeValue {
+    type Output = Self;
+    fn neg(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Neg(a), |[a]| -a)
+    }
+}
+
+impl Add for TapeValue {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        TapeValue::record([&self, &rhs], |[a, b]| Op::Add(a, b), |[a, b]| a + b)
+    }
+}
+
+impl Sub for TapeValue {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        TapeValue::record([&self, &rhs], |[a, b]| Op::Sub(a, b), |[a, b]| a - b)
+    }
+}
+
+impl Mul for TapeValue {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        TapeValue::record([&self, &rhs], |[a, b]| Op::Mul(a, b), |[a, b]| a * b)
+    }
+}
+
+impl Div for TapeValue {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        TapeValue::record([&self, &rhs], |[a, b]| Op::Div(a, b), |[a, b]| a / b)
+    }
+}
+
+impl AddAssign for TapeValue {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+impl SubAssign for TapeValue {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.clone() - rhs;
+    }
+}
+
+impl MulAssign for TapeValue {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = self.clone() * rhs;
+    }
+}
+
+impl DivAssign for TapeValue {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = self.clone() / rhs;
+    }
+}
+
+impl Scalar for TapeValue {
+    fn recip(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Recip(a), |[a]| a.recip())
+    }
+    fn minimum(self, other: Self) -> Self {
+        TapeValue::record(
+            [&self, &other],
+            |[a, b]| Op::Min(a, b),
+            |[a, b]| a.minimum(b),
+        )
+    }
+    fn maximum(self, other: Self) -> Self {
+        TapeValue::record(
+            [&self, &other],
+            |[a, b]| Op::Max(a, b),
+            |[a, b]| a.maximum(b),
+        )
+    }
+    fn piecewise(self, neg: Self, pos: Self) -> Self {
+        match self.operand {
+            TapeOperand::Const(value) => {
+                if value < 0.0 {
+                    neg
+                } else {
+                    pos
+                }
+            }
+            TapeOperand::Value(_) => TapeValue::record(
+                [&self, &neg, &pos],
+                |[a, b, c]| Op::Piecewise(a, b, c),
+                |_| unreachable!(),
+            ),
+        }
+    }
+    fn from_f64(value: f64) -> Self {
+        TapeValue {
+            builder: None,
+            operand: TapeOperand::Const(value),
+        }
+    }
+    fn sqrt(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Sqrt(a), |[a]| a.sqrt())
+    }
+    fn abs(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Abs(a), |[a]| a.abs())
+    }
+    fn sin(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Sin(a), |[a]| a.sin())
+    }
+    fn cos(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Cos(a), |[a]| a.cos())
+    }
+    fn atan2(self, x: Self) -> Self {
+        TapeValue::record([&self, &x], |[a, b]| Op::Atan2(a, b), |[a, b]| a.atan2(b))
+    }
+}
+
+impl TapeScalar for TapeValue {
+    fn round(self) -> Self {
+        TapeValue::record([&self], |[a]| Op::Round(a), |[a]| a.round())
+    }
+    fn call(call: &Arc<dyn TapeCall>, args: &[Self]) -> Self {
+        let Some(builder) = args.iter().find_map(|arg| arg.builder.clone()) else {
+            let args: Vec<f64> = args
+                .iter()
+                .map(|arg| match arg.operand {
+                    TapeOperand::Const(value) => value,
+                    TapeOperand::Value(_) => unreachable!(),
+                })
+                .collect();
+            return TapeValue::from_f64(call.evaluate(&args));
+        };
+        let reg = {
+            let mut b = builder.borrow_mut();
+            let args = args.iter().map(|arg| arg.reg(&mut b)).collect();
+            b.calls.push(CallSite {
+                call: call.clone(),
+                args,
+            });
+            let call = b.calls.len() - 1;
+            b.push(Op::Call(call))
+        };
+        TapeValue {
+            builder: Some(builder),
+            operand: TapeOperand::Value(reg),
+        }
+    }
+}
+
+/// An [Sdf] evaluated by interpreting a [Tape]. See [Sdf::compile].
+#[derive(Debug)]
+pub struct SdfTape<const N: usize> {
+    source: Sdf<N>,
+    tape: Tape,
+}
+
+impl<const N: usize> SdfTape<N> {
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+}
+
+impl<const N: usize> SdfImpl<N> for SdfTape<N> {
+    fn evaluate(&self, p: Vector<f64, N>) -> f64 {
+        self.tape.evaluate(p.as_ref())
+    }
+
+    fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
+        self.tape.evaluate(p.as_ref())
+    }
+
+    fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
+        self.tape.evaluate(p.as_ref())
+    }
+
+    fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
+        self.tape.evaluate(p.as_ref())
+    }
+
+    fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
+        let (tape, range) = self.tape.evaluate_constrain(p.as_ref());
+        (
+            tape.map(|tape| {
+                Sdf::new(SdfTape {
+                    source: self.source.clone(),
+                    tape,
+                })
+            }),
+            range,
+        )
+    }
+
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        Some(self.tape.evaluate(p.as_ref()))
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        self.tape.evaluate_batch(ps, out)
+    }
+
+    fn evaluate_gradient_batch(
+        &self,
+        ps: &[Vector<f64, N>],
+        out: &mut [f64],
+        gradients: &mut [Vector<f64, N>],
+    ) {
+        let ps: Vec<_> = ps.iter().map(|&p| gradient_variables(p)).collect();
+        let mut derivs = vec![Deriv::constant(0.0); ps.len()];
+        self.tape.evaluate_batch(&ps, &mut derivs);
+        for ((d, out), gradient) in derivs.iter().zip(out).zip(gradients) {
+            *out = d.value();
+            *gradient = Vector::from_fn(|axis| d.deriv()[axis]);
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        self.source.structural_bounds()
+    }
+
+    fn complexity(&self) -> usize {
+        self.tape.instruction_count()
+    }
+
+    /// The tree this tape was compiled from. A simplified tape still describes the whole tree.
+    fn node(&self) -> SdfNode {
+        self.source.node()
+    }
+}
+
+impl<const N: usize> Sdf<N> {
+    /// Records this tree onto the tape that `p` belongs to. Nodes that cannot be lowered are
+    /// recorded as calls.
+    pub fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> TapeValue {
+        match self.0.imp.evaluate_tape(p.clone()) {
+            Some(value) => value,
+            None => TapeValue::call(&(Arc::new(self.clone()) as Arc<dyn TapeCall>), p.as_ref()),
+        }
+    }
+    /// Lowers this tree to a flat list of instructions.
+    pub fn tape(&self) -> Tape {
+        let builder = Rc::new(RefCell::new(TapeBuilder::default()));
+        let output = self.evaluate_tape(Vector::from_fn(|axis| TapeValue::input(&builder, axis)));
+        let output = output.reg(&mut builder.borrow_mut());
+        builder.take().finish(output)
+    }
+    /// An equivalent [Sdf] that interprets the [Tape] of this tree instead of walking it.
+    pub fn compile(&self) -> Sdf<N> {
+        Sdf::new(SdfTape {
+            source: self.clone(),
+            tape: self.tape(),
+        })
+    }
+}
+
+#[test]
+fn test_tape() {
+    let arm = Circle::new(Vec2::new(4.0, 0.0), 0.5).as_sdf().revolve(
+        Vec3::zero(),
+        Vec3::axis_z(),
+        0.0..2.0,
+    );
+    let cube = Mesh::from_aabb(Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))).as_sdf();
+    let model = Sphere::new(Vec3::zero(), 2.0)
+        .as_sdf()
+        .smooth_union(&arm.repeat_polar(Vec3::axis_z(), Vec3::axis_x(), 3), 0.5)
+        .union(&cube.translate(Vec3::new(0.0, 0.0, 3.0)))
+        .difference(&Sphere::new(Vec3::new(0.0, 0.0, 2.0), 1.0).as_sdf())
+        .shell(0.3);
+    let compiled = model.compile();
+    let tape = compiled
+        .downcast_ref::<SdfTape<3>>()
+        .unwrap()
+        .tape()
+        .clone();
+    assert!(tape.registers() < tape.instruction_count());
+    for i in 0..50 {
+        let t = i as f64;
+        let p = Vec3::new((t * 0.7).sin() * 5.0, (t * 1.3).cos() * 5.0, t * 0.1 - 1.0);
+        assert!((compiled.evaluate(p) - model.evaluate(p)).abs() < 1e-12);
+        let d1 = compiled.evaluate_deriv3(p.into_variable());
+        let d2 = model.evaluate_deriv3(p.into_variable());
+        assert!((Vec3::from(*d1.deriv()) - Vec3::from(*d2.deriv())).length() < 1e-12);
+    }
+
+    // Batches agree with evaluating one point at a time, for both the tree and the tape.
+    let ps: Vec<Vec3> = (0..50)
+        .map(|i| Vec3::new(i as f64 * 0.2 - 5.0, (i as f64).sin() * 3.0, 1.5))
+        .collect();
+    for sdf in [&model, &compiled] {
+        let mut ds = vec![0.0; ps.len()];
+        sdf.evaluate_batch(&ps, &mut ds);
+        let mut gs = vec![0.0; ps.len()];
+        let mut gradients = vec![Vec3::zero(); ps.len()];
+        sdf.evaluate_gradient_batch(&ps, &mut gs, &mut gradients);
+        for ((p, d), (g, gradient)) in ps.iter().zip(ds).zip(gs.into_iter().zip(gradients)) {
+            assert!((d - model.evaluate(*p)).abs() < 1e-12);
+            assert!((g - d).abs() < 1e-12);
+            let expected = Vec3::from(*model.evaluate_deriv3(p.into_variable()).deriv());
+            assert!((gradient - expected).length() < 1e-12);
+        }
+    }
+
+    // Far from the arms, the cube and the hole, only the sphere is left.
+    let region = Vector3::new(
+        DecInterval::try_from((-0.3, 0.3)).unwrap(),
+        DecInterval::try_from((-0.3, 0.3)).unwrap(),
+        DecInterval::try_from((-2.1, -1.6)).unwrap(),
+    );
+    let (simplified, range) = compiled.evaluate_constrain(region);
+    assert!(range.contains(0.0));
+    let simplified = simplified.unwrap();
+    let simplified_tape = simplified.downcast_ref::<SdfTape<3>>().unwrap().tape();
+    assert!(simplified_tape.instruction_count() * 2 < tape.instruction_count());
+    for p in [
+        Vec3::new(0.0, 0.0, -1.8),
+        Vec3::new(0.3, -0.3, -2.1),
+        Vec3::new(-0.2, 0.1, -1.6),
+    ] {
+        assert_eq!(simplified.evaluate(p), compiled.evaluate(p));
+    }
+    // The cube is called through the tree, and is pruned the same way.
+    let region = Vector3::new(
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((-0.5, 0.5)).unwrap(),
+        DecInterval::try_from((3.5, 4.5)).unwrap(),
+    );
+    let (simplified, range) = compiled.evaluate_constrain(region);
+    assert!(range.contains(0.0));
+    let p = Vec3::new(0.2, 0.1, 4.0);
+    assert_eq!(simplified.unwrap().evaluate(p), compiled.evaluate(p));
+}
@@ -0,0 +1,80 @@
+use crate::sdf::bounds::cylinder_bounds;
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
+use patina_scalar::Scalar;
+use patina_vec::vec3::{Vec3, Vector3};
+
+/// A ring centered on `origin` in the plane perpendicular to `axis`. The tube of radius
+/// `minor_radius` follows a circle of radius `major_radius`.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    origin: Vec3,
+    axis: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl Torus {
+    pub fn new(origin: Vec3, axis: Vec3, major_radius: f64, minor_radius: f64) -> Self {
+        Torus {
+            origin,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+        }
+    }
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+    pub fn axis(&self) -> Vec3 {
+        self.axis
+    }
+    pub fn major_radius(&self) -> f64 {
+        self.major_radius
+    }
+    pub fn minor_radius(&self) -> f64 {
+        self.minor_radius
+    }
+}
+
+impl SdfLeafImpl<3> for Torus {
+    fn evaluate<T: Scalar>(&self, p: Vector3<T>) -> T {
+        let rel = p - self.origin.into_scalars();
+        let h = rel.clone().dot(self.axis.into_scalars());
+        let rho = (rel - self.axis.into_scalars() * h.clone()).length();
+        let radial = rho - T::from_f64(self.major_radius);
+        (radial.clone() * radial + h.clone() * h).sqrt() - T::from_f64(self.minor_radius)
+    }
+    fn bounds(&self) -> Option<Aabb<3>> {
+        Some(cylinder_bounds(
+            self.origin,
+            self.axis,
+            self.major_radius + self.minor_radius,
+            -self.minor_radius..self.minor_radius,
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Torus {
+            origin: self.origin,
+            axis: self.axis,
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+        }
+    }
+}
+
+impl AsSdf<3> for Torus {
+    fn as_sdf(&self) -> Sdf<3> {
+        Sdf::new(SdfLeaf::new(self.clone()))
+    }
+}
+
+#[test]
+fn test_torus() {
+    let torus = Torus::new(Vec3::zero(), Vec3::axis_z(), 5.0, 1.0).as_sdf();
+    assert_eq!(torus.evaluate(Vec3::new(5.0, 0.0, 0.0)), -1.0);
+    assert_eq!(torus.evaluate(Vec3::zero()), 4.0);
+    assert_eq!(torus.evaluate(Vec3::new(0.0, -5.0, 3.0)), 2.0);
+}
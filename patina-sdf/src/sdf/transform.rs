@@ -1,5 +1,9 @@
+use crate::sdf::bounds::is_empty;
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
 use crate::sdf::{Sdf, SdfImpl};
 use inari::DecInterval;
+use patina_geo::aabb::Aabb;
 use patina_scalar::Scalar;
 use patina_scalar::deriv::Deriv;
 use patina_vec::vec::Vector;
@@ -15,6 +19,12 @@ impl<const NI: usize, const NO: usize, T> Transform<NI, NO, T> {
     pub fn new(transform: T, inner: Sdf<NI>) -> Self {
         Transform { transform, inner }
     }
+    pub fn transform_impl(&self) -> &T {
+        &self.transform
+    }
+    pub fn inner(&self) -> &Sdf<NI> {
+        &self.inner
+    }
 }
 
 pub trait TransformImpl<const NI: usize, const NO: usize>:
@@ -25,6 +35,11 @@ pub trait TransformImpl<const NI: usize, const NO: usize>:
         input: Vector<T, NO>,
         inner: impl FnOnce(Vector<T, NI>) -> T,
     ) -> T;
+    /// Bounds the transformed solid given nonempty bounds of the inner solid. See
+    /// [SdfImpl::bounds].
+    fn bounds(&self, inner: &Aabb<NI>) -> Option<Aabb<NO>>;
+    /// See [SdfImpl::node]. `inner` describes the transformed SDF.
+    fn node(&self, inner: SdfNode) -> SdfNode;
 }
 
 impl<const NI: usize, const NO: usize, T: TransformImpl<NI, NO>> SdfImpl<NO>
@@ -69,7 +84,23 @@ impl<const NI: usize, const NO: usize, T: TransformImpl<NI, NO>> SdfImpl<NO>
         }
     }
 
+    fn evaluate_tape(&self, p: Vector<TapeValue, NO>) -> Option<TapeValue> {
+        Some(self.transform.evaluate(p, |x| self.inner.evaluate_tape(x)))
+    }
+
+    fn bounds(&self) -> Option<Aabb<NO>> {
+        let inner = self.inner.structural_bounds()?;
+        if is_empty(&inner) {
+            return Some(Aabb::empty());
+        }
+        self.transform.bounds(&inner)
+    }
+
     fn complexity(&self) -> usize {
         1 + self.inner.complexity()
     }
+
+    fn node(&self) -> SdfNode {
+        self.transform.node(self.inner.node())
+    }
 }
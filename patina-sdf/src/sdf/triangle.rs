@@ -1,10 +1,67 @@
+use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
+use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
 use patina_geo::geo3::triangle3::Triangle3;
 use patina_scalar::Scalar;
-use patina_vec::vec::Vector;
-use crate::sdf::leaf::SdfLeafImpl;
+use patina_vec::vec3::{Vec3, Vector3};
 
-impl SdfLeafImpl<2> for Triangle3{
-    fn evaluate<T: Scalar>(&self, p: Vector<T, 2>) -> T {
-        todo!()
+/// The unsigned distance to a triangle. A triangle has no interior, so this is usually
+/// [offset](Sdf::offset) to give it thickness.
+impl SdfLeafImpl<3> for Triangle3 {
+    fn evaluate<T: Scalar>(&self, p: Vector3<T>) -> T {
+        let [a, b, c] = *self.points();
+        let normal = (b - a).cross(c - a);
+        let edge = |v1: Vec3, v2: Vec3| {
+            let e = v2 - v1;
+            let d = p.clone() - v1.into_scalars();
+            let t = (d.clone().dot(e.into_scalars()) / T::from_f64(e.length_squared()))
+                .maximum(T::from_f64(0.0))
+                .minimum(T::from_f64(1.0));
+            (d - e.into_scalars() * t).length_squared()
+        };
+        let edges = edge(a, b).minimum(edge(b, c)).minimum(edge(c, a));
+        if normal.length_squared() == 0.0 {
+            return edges.sqrt();
+        }
+        // Positive on the inner side of an edge, within the plane of the triangle.
+        let side = |v1: Vec3, v2: Vec3| {
+            (p.clone() - v1.into_scalars())
+                .dot(normal.cross(v2 - v1).into_scalars())
+                .sign()
+        };
+        let inside = side(a, b) + side(b, c) + side(c, a) - T::from_f64(2.0);
+        let height = (p.clone() - a.into_scalars()).dot(normal.normalize().into_scalars());
+        inside.piecewise(edges, height.clone() * height).sqrt()
     }
-}
\ No newline at end of file
+    fn bounds(&self) -> Option<Aabb<3>> {
+        Some(self.points().iter().copied().collect())
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::Triangle {
+            points: *self.points(),
+        }
+    }
+}
+
+impl AsSdf<3> for Triangle3 {
+    fn as_sdf(&self) -> Sdf<3> {
+        Sdf::new(SdfLeaf::new(*self))
+    }
+}
+
+#[test]
+fn test_triangle() {
+    let triangle = Triangle3::new([
+        Vec3::zero(),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 4.0, 0.0),
+    ])
+    .as_sdf();
+    assert_eq!(triangle.evaluate(Vec3::new(1.0, 1.0, -2.0)), 2.0);
+    assert_eq!(triangle.evaluate(Vec3::new(-3.0, 1.0, 4.0)), 5.0);
+    assert_eq!(triangle.evaluate(Vec3::new(1.0, -1.0, 0.0)), 1.0);
+    assert!((triangle.evaluate(Vec3::new(3.0, 3.0, 0.0)) - 2.0f64.sqrt()).abs() < 1e-12);
+    let plate = triangle.offset(0.5);
+    assert_eq!(plate.evaluate(Vec3::new(1.0, 1.0, 0.0)), -0.5);
+}
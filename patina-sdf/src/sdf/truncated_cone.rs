@@ -1,7 +1,9 @@
 use crate::sdf::leaf::{SdfLeaf, SdfLeafImpl};
+use crate::sdf::node::SdfNode;
 use crate::sdf::rotate::Rotate;
 use crate::sdf::transform::Transform;
 use crate::sdf::{AsSdf, Sdf};
+use patina_geo::aabb::Aabb;
 use patina_geo::geo3::cylinder::Cylinder;
 use patina_scalar::Scalar;
 use patina_vec::vec::Vector;
@@ -27,12 +29,18 @@ impl TruncatedCone {
 }
 
 #[derive(Debug)]
-struct TruncatedConeCrossSection {
+pub(crate) struct TruncatedConeCrossSection {
     height: f64,
     r1: f64,
     r2: f64,
 }
 
+impl TruncatedConeCrossSection {
+    pub(crate) fn new(height: f64, r1: f64, r2: f64) -> Self {
+        TruncatedConeCrossSection { height, r1, r2 }
+    }
+}
+
 impl SdfLeafImpl<2> for TruncatedConeCrossSection {
     fn evaluate<T: Scalar>(&self, p: Vector<T, 2>) -> T {
         let r1 = T::from_f64(self.r1);
@@ -65,17 +73,31 @@ impl SdfLeafImpl<2> for TruncatedConeCrossSection {
             ),
         )
     }
+    /// Only the half with a nonnegative radius is revolved.
+    fn bounds(&self) -> Option<Aabb<2>> {
+        Some(Aabb::new(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(self.r1.max(self.r2), self.height),
+        ))
+    }
+    fn node(&self) -> SdfNode {
+        SdfNode::TruncatedConeSection {
+            height: self.height,
+            r1: self.r1,
+            r2: self.r2,
+        }
+    }
 }
 
 impl AsSdf<3> for TruncatedCone {
     fn as_sdf(&self) -> Sdf<3> {
         Sdf::new(Transform::new(
             Rotate::new(self.origin, self.axis),
-            Sdf::new(SdfLeaf::new(TruncatedConeCrossSection {
-                height: self.axis.length(),
-                r1: self.r1,
-                r2: self.r2,
-            })),
+            Sdf::new(SdfLeaf::new(TruncatedConeCrossSection::new(
+                self.axis.length(),
+                self.r1,
+                self.r2,
+            ))),
         ))
     }
 }
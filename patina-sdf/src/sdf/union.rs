@@ -1,19 +1,66 @@
+use crate::sdf::node::SdfNode;
+use crate::sdf::tape::TapeValue;
 use crate::sdf::{Sdf, SdfImpl};
 use inari::DecInterval;
+use patina_geo::aabb::Aabb;
 use patina_scalar::Scalar;
 use patina_scalar::deriv::Deriv;
 use patina_vec::vec::Vector;
 use patina_vec::vec3::{Vec3, Vector3};
 
+/// How many points [SdfUnion] and [SdfIntersection](crate::sdf::intersection::SdfIntersection)
+/// pass to their children at once, so that the scratch space for a child fits on the stack.
+const BATCH_CHUNK: usize = 256;
+
+/// Evaluates the first child into `out` and folds each other child into it with `combine`.
+pub(crate) fn combine_batch<const N: usize>(
+    children: &[Sdf<N>],
+    ps: &[Vector<f64, N>],
+    out: &mut [f64],
+    combine: impl Fn(f64, f64) -> f64,
+) {
+    let (first, rest) = children.split_first().unwrap();
+    if rest.is_empty() {
+        return first.evaluate_batch(ps, out);
+    }
+    let mut scratch = [0.0; BATCH_CHUNK];
+    for (ps, out) in ps.chunks(BATCH_CHUNK).zip(out.chunks_mut(BATCH_CHUNK)) {
+        let scratch = &mut scratch[..ps.len()];
+        first.evaluate_batch(ps, out);
+        for child in rest {
+            child.evaluate_batch(ps, scratch);
+            for (out, d) in out.iter_mut().zip(scratch.iter()) {
+                *out = combine(*out, *d);
+            }
+        }
+    }
+}
+
+/// The union of any number of solids. Nested unions are flattened into a single node so that
+/// [SdfImpl::evaluate_constrain] can drop every irrelevant child in one step.
 #[derive(Debug)]
 pub struct SdfUnion<const N: usize> {
-    a: Sdf<N>,
-    b: Sdf<N>,
+    children: Vec<Sdf<N>>,
 }
 
 impl<const N: usize> SdfUnion<N> {
     pub fn new(a: Sdf<N>, b: Sdf<N>) -> Self {
-        Self { a, b }
+        Self::from_children([a, b])
+    }
+    pub fn from_children(children: impl IntoIterator<Item = Sdf<N>>) -> Self {
+        let mut flat = vec![];
+        for child in children {
+            if let Some(union) = child.downcast_ref::<SdfUnion<N>>() {
+                flat.extend(union.children.iter().cloned());
+            } else {
+                flat.push(child);
+            }
+        }
+        assert!(!flat.is_empty());
+        Self { children: flat }
+    }
+    pub fn children(&self) -> &[Sdf<N>] {
+        &self.children
     }
     pub fn into_sdf(self) -> Sdf<N> {
         Sdf::new(self)
@@ -22,48 +69,96 @@ impl<const N: usize> SdfUnion<N> {
 
 impl<const N: usize> SdfImpl<N> for SdfUnion<N> {
     fn evaluate(&self, p: Vector<f64, N>) -> f64 {
-        self.a.evaluate(p).minimum(self.b.evaluate(p))
+        self.children
+            .iter()
+            .map(|x| x.evaluate(p))
+            .reduce(f64::minimum)
+            .unwrap()
     }
 
     fn evaluate_deriv1(&self, p: Vector<Deriv<1>, N>) -> Deriv<1> {
-        self.a
-            .evaluate_deriv1(p.clone())
-            .minimum(self.b.evaluate_deriv1(p.clone()))
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv1(p.clone()))
+            .reduce(Deriv::minimum)
+            .unwrap()
     }
 
     fn evaluate_deriv2(&self, p: Vector<Deriv<2>, N>) -> Deriv<2> {
-        self.a
-            .evaluate_deriv2(p.clone())
-            .minimum(self.b.evaluate_deriv2(p.clone()))
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv2(p.clone()))
+            .reduce(Deriv::minimum)
+            .unwrap()
     }
 
     fn evaluate_deriv3(&self, p: Vector<Deriv<3>, N>) -> Deriv<3> {
-        self.a
-            .evaluate_deriv3(p.clone())
-            .minimum(self.b.evaluate_deriv3(p.clone()))
+        self.children
+            .iter()
+            .map(|x| x.evaluate_deriv3(p.clone()))
+            .reduce(Deriv::minimum)
+            .unwrap()
     }
 
     fn evaluate_constrain(&self, p: Vector<DecInterval, N>) -> (Option<Sdf<N>>, DecInterval) {
-        let (a2, ai) = self.a.evaluate_constrain(p);
-        let (b2, bi) = self.b.evaluate_constrain(p);
-        if ai.precedes(bi) {
-            (Some(a2.unwrap_or(self.a.clone())), ai)
-        } else if bi.precedes(ai) {
-            (Some(b2.unwrap_or(self.b.clone())), bi)
-        } else if a2.is_some() || b2.is_some() {
-            (
-                Some(Sdf::new(SdfUnion::new(
-                    a2.unwrap_or(self.a.clone()),
-                    b2.unwrap_or(self.b.clone()),
-                ))),
-                ai.minimum(bi),
-            )
+        let constrained: Vec<_> = self
+            .children
+            .iter()
+            .map(|x| x.evaluate_constrain(p))
+            .collect();
+        // The child with the lowest upper bound is always kept. Any other child whose lower
+        // bound is above that can never be the minimum.
+        let (best, bound) = constrained
+            .iter()
+            .enumerate()
+            .map(|(i, (_, int))| (i, int.sup()))
+            .reduce(|x, y| if y.1 < x.1 { y } else { x })
+            .unwrap();
+        let mut changed = false;
+        let mut children = vec![];
+        let mut range: Option<DecInterval> = None;
+        for (i, ((c, int), child)) in constrained.into_iter().zip(&self.children).enumerate() {
+            if i != best && bound <= int.inf() {
+                changed = true;
+                continue;
+            }
+            changed |= c.is_some();
+            children.push(c.unwrap_or(child.clone()));
+            range = Some(range.map_or(int, |range| range.minimum(int)));
+        }
+        let range = range.unwrap();
+        if children.len() == 1 {
+            (children.pop(), range)
+        } else if changed {
+            (Some(SdfUnion::from_children(children).into_sdf()), range)
         } else {
-            (None, ai.minimum(bi))
+            (None, range)
         }
     }
 
+    fn evaluate_tape(&self, p: Vector<TapeValue, N>) -> Option<TapeValue> {
+        self.children
+            .iter()
+            .map(|x| x.evaluate_tape(p.clone()))
+            .reduce(TapeValue::minimum)
+    }
+
+    fn evaluate_batch(&self, ps: &[Vector<f64, N>], out: &mut [f64]) {
+        combine_batch(&self.children, ps, out, f64::minimum);
+    }
+
+    fn bounds(&self) -> Option<Aabb<N>> {
+        self.children
+            .iter()
+            .map(|x| x.structural_bounds())
+            .collect()
+    }
+
     fn complexity(&self) -> usize {
-        1 + self.a.complexity() + self.b.complexity()
+        1 + self.children.iter().map(|x| x.complexity()).sum::<usize>()
+    }
+
+    fn node(&self) -> SdfNode {
+        SdfNode::Union(self.children.iter().map(|child| child.node()).collect())
     }
 }
@@ -25,9 +25,11 @@ impl Subdivide {
         }
     }
     pub fn subdivide(&mut self, mesh: &Mesh, sdf: &Sdf3) -> Mesh {
-        let mut vertex_normals: Vec<Vec3> = vec![];
-        for vertex in mesh.vertices() {
-            vertex_normals.push(sdf.normal(*vertex));
+        let mut ds = vec![0.0; mesh.vertices().len()];
+        let mut vertex_normals = vec![Vec3::zero(); mesh.vertices().len()];
+        sdf.evaluate_gradient_batch(mesh.vertices(), &mut ds, &mut vertex_normals);
+        for normal in &mut vertex_normals {
+            *normal = normal.normalize();
         }
         let mut divide_edges = vec![];
         for tri in mesh.triangles() {
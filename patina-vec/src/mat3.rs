@@ -1,9 +1,31 @@
 use crate::mat::Matrix;
-use crate::vec3::Vec3;
+use crate::vec3::{Vec3, Vector3};
+use patina_scalar::Scalar;
 
 pub type Matrix3<T> = Matrix<T, 3>;
 pub type Mat3 = Matrix3<f64>;
 
+impl<T> Matrix3<T> {
+    pub fn determinant(&self) -> T
+    where
+        T: Scalar,
+    {
+        self.row(0).dot(self.row(1).cross(self.row(2)))
+    }
+    pub fn invert3(&self) -> Self
+    where
+        T: Scalar,
+    {
+        let [r0, r1, r2] = [self.row(0), self.row(1), self.row(2)];
+        let det = r0.clone().dot(r1.clone().cross(r2.clone()));
+        Self::from_cols([
+            r1.clone().cross(r2.clone()),
+            r2.cross(r0.clone()),
+            r0.cross(r1),
+        ]) / det
+    }
+}
+
 impl Mat3 {
     pub fn rotate(axis: Vec3, angle: f64) -> Self {
         let [x, y, z] = axis.into();
@@ -27,4 +49,86 @@ impl Mat3 {
             ),
         ])
     }
+    pub fn scale(scale: Vec3) -> Self {
+        Mat3::from_fn(|r, c| if r == c { scale[r] } else { 0.0 })
+    }
+    /// The eigenvalues of a symmetric matrix, from largest to smallest.
+    pub fn symmetric_eigenvalues(&self) -> [f64; 3] {
+        self.symmetric_eigen().map(|(value, _)| value)
+    }
+    /// The eigenvalues of a symmetric matrix with their unit eigenvectors, from largest to
+    /// smallest, by Jacobi rotations.
+    pub fn symmetric_eigen(&self) -> [(f64, Vec3); 3] {
+        let mut a: [[f64; 3]; 3] = std::array::from_fn(|r| std::array::from_fn(|c| self[(r, c)]));
+        let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        let scale = (0..3).map(|i| a[i][i].abs()).sum::<f64>();
+        for _ in 0..32 {
+            let (p, q) = [(0, 1), (0, 2), (1, 2)]
+                .into_iter()
+                .max_by(|&(p1, q1), &(p2, q2)| a[p1][q1].abs().total_cmp(&a[p2][q2].abs()))
+                .unwrap();
+            if a[p][q].abs() <= 1e-15 * scale {
+                break;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in &mut a {
+                let (ap, aq) = (row[p], row[q]);
+                row[p] = c * ap - s * aq;
+                row[q] = s * ap + c * aq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+            a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+            for row in &mut v {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = c * vp - s * vq;
+                row[q] = s * vp + c * vq;
+            }
+        }
+        let mut eigen = [0, 1, 2].map(|i| (a[i][i], Vec3::new(v[0][i], v[1][i], v[2][i])));
+        eigen.sort_by(|(x, _), (y, _)| y.total_cmp(x));
+        eigen
+    }
+}
+
+#[test]
+fn test_invert() {
+    let mat = Mat3::from_rows([
+        Vec3::new(2.0, 0.0, 1.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        Vec3::new(0.0, 0.0, 8.0),
+    ]);
+    assert_eq!(Mat3::id(), mat * mat.invert3());
+    assert_eq!(mat.determinant(), 64.0);
+}
+
+#[test]
+fn test_symmetric_eigenvalues() {
+    let mat = Mat3::from_rows([
+        Vec3::new(2.0, 1.0, 0.0),
+        Vec3::new(1.0, 2.0, 0.0),
+        Vec3::new(0.0, 0.0, 5.0),
+    ]);
+    let [e1, e2, e3] = mat.symmetric_eigenvalues();
+    assert!((e1 - 5.0).abs() < 1e-12);
+    assert!((e2 - 3.0).abs() < 1e-12);
+    assert!((e3 - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_symmetric_eigen() {
+    let mat = Mat3::from_rows([
+        Vec3::new(4.0, 1.0, 2.0),
+        Vec3::new(1.0, 3.0, 0.5),
+        Vec3::new(2.0, 0.5, 1.0),
+    ]);
+    let eigen = mat.symmetric_eigen();
+    assert!(eigen[0].0 >= eigen[1].0 && eigen[1].0 >= eigen[2].0);
+    for (value, vector) in eigen {
+        assert!((vector.length() - 1.0).abs() < 1e-12);
+        assert!((mat * vector - vector * value).length() < 1e-12);
+    }
 }
@@ -30,6 +30,17 @@ impl Matrix4<f64> {
     pub fn rotate(axis: Vec3, angle: f64) -> Self {
         Self::from_mat3(Mat3::rotate(axis, angle))
     }
+    pub fn scale(v: Vec3) -> Self {
+        Self::from_mat3(Mat3::scale(v))
+    }
+    /// The upper-left 3x3 block, i.e. the linear part of an affine transform.
+    pub fn linear(&self) -> Mat3 {
+        Mat3::from_fn(|r, c| self[(r, c)])
+    }
+    /// The translation applied by an affine transform.
+    pub fn translation(&self) -> Vec3 {
+        Vec3::new(self[(0, 3)], self[(1, 3)], self[(2, 3)])
+    }
     pub fn from_mat3(m: Mat3) -> Self {
         Mat4::from_rows([
             Vec4::from_vec3(m.row(0)),
@@ -3,7 +3,6 @@ use patina_scalar::Scalar;
 use patina_scalar::deriv::Deriv;
 use rand::Rng;
 use rand::distr::{Distribution, StandardUniform};
-use serde::Deserialize;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter;
 use std::iter::Sum;
@@ -348,6 +347,21 @@ where
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, const N: usize> serde::Serialize for Vector<T, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for x in &self.0 {
+            tuple.serialize_element(x)?;
+        }
+        tuple.end()
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de, T: Scalar + serde::Deserialize<'de>, const N: usize> serde::Deserialize<'de>
     for Vector<T, N>
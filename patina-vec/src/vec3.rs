@@ -18,3 +18,17 @@ impl<T> Vector3<T> {
         )
     }
 }
+
+impl Vec3 {
+    /// A unit vector perpendicular to this one. The result is a fixed function of the input, so
+    /// callers can rely on it to build a repeatable local frame.
+    pub fn perpendicular(self) -> Vec3 {
+        let w = self.normalize();
+        let other = if w.x().abs() < 0.9 {
+            Vec3::axis_x()
+        } else {
+            Vec3::axis_y()
+        };
+        (other - w * other.dot(w)).normalize()
+    }
+}